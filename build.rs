@@ -0,0 +1,13 @@
+use std::env;
+
+fn main() {
+    println!("cargo:rustc-check-cfg=cfg(ossl320)");
+
+    // The version of the linked OpenSSL is passed by openssl-sys.
+    if let Ok(version) = env::var("DEP_OPENSSL_VERSION_NUMBER") {
+        let version = u64::from_str_radix(&version, 16).unwrap();
+        if version >= 0x3020_0000 {
+            println!("cargo:rustc-cfg=ossl320");
+        }
+    }
+}
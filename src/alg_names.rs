@@ -0,0 +1,50 @@
+//! Registered names of JOSE algorithms.
+//!
+//! These constants are the values of the alg and enc header claims
+//! that are supported by this crate.
+
+// JWE algorithms (alg).
+pub const RSA1_5: &str = "RSA1_5";
+pub const RSA_OAEP: &str = "RSA-OAEP";
+pub const RSA_OAEP_256: &str = "RSA-OAEP-256";
+pub const RSA_OAEP_384: &str = "RSA-OAEP-384";
+pub const RSA_OAEP_512: &str = "RSA-OAEP-512";
+pub const A128KW: &str = "A128KW";
+pub const A192KW: &str = "A192KW";
+pub const A256KW: &str = "A256KW";
+pub const DIR: &str = "dir";
+pub const ECDH_ES: &str = "ECDH-ES";
+pub const ECDH_ES_A128KW: &str = "ECDH-ES+A128KW";
+pub const ECDH_ES_A192KW: &str = "ECDH-ES+A192KW";
+pub const ECDH_ES_A256KW: &str = "ECDH-ES+A256KW";
+pub const A128GCMKW: &str = "A128GCMKW";
+pub const A192GCMKW: &str = "A192GCMKW";
+pub const A256GCMKW: &str = "A256GCMKW";
+pub const PBES2_HS256_A128KW: &str = "PBES2-HS256+A128KW";
+pub const PBES2_HS384_A192KW: &str = "PBES2-HS384+A192KW";
+pub const PBES2_HS512_A256KW: &str = "PBES2-HS512+A256KW";
+
+// JWE content encryptions (enc).
+pub const A128CBC_HS256: &str = "A128CBC-HS256";
+pub const A192CBC_HS384: &str = "A192CBC-HS384";
+pub const A256CBC_HS512: &str = "A256CBC-HS512";
+pub const A128GCM: &str = "A128GCM";
+pub const A192GCM: &str = "A192GCM";
+pub const A256GCM: &str = "A256GCM";
+
+// JWS algorithms (alg).
+pub const HS256: &str = "HS256";
+pub const HS384: &str = "HS384";
+pub const HS512: &str = "HS512";
+pub const RS256: &str = "RS256";
+pub const RS384: &str = "RS384";
+pub const RS512: &str = "RS512";
+pub const PS256: &str = "PS256";
+pub const PS384: &str = "PS384";
+pub const PS512: &str = "PS512";
+pub const ES256: &str = "ES256";
+pub const ES384: &str = "ES384";
+pub const ES512: &str = "ES512";
+#[cfg(feature = "secp256k1")]
+pub const ES256K: &str = "ES256K";
+pub const EDDSA: &str = "EdDSA";
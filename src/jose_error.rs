@@ -20,6 +20,9 @@ pub enum JoseError {
     #[error("Invalid key format: {0}")]
     InvalidKeyFormat(#[source] anyhow::Error),
 
+    #[error("Invalid key type: {actual} (expected {expected})")]
+    InvalidKeyType { expected: String, actual: String },
+
     #[error("Invalid json: {0}")]
     InvalidJson(#[source] anyhow::Error),
 
@@ -28,4 +31,7 @@ pub enum JoseError {
 
     #[error("Invalid signature: {0}")]
     InvalidSignature(#[source] anyhow::Error),
+
+    #[error("Random generation failed: {0}")]
+    RandomGenerationFailed(#[source] anyhow::Error),
 }
@@ -8,20 +8,29 @@ mod jwe_content_encryption;
 mod jwe_context;
 mod jwe_header;
 mod jwe_header_set;
+mod jwe_json_aad;
+mod jwe_key_descriptor;
+mod jwe_key_length_error;
 pub mod zip;
 
+use std::io::{Read, Write};
 use std::sync::LazyLock;
 
-use crate::JoseError;
+use crate::jwe::jwe_context::EncryptedContent;
+use crate::jwk::{Jwk, JwkSet};
+use crate::{alg_names, JoseError};
 
 pub use crate::jwe::jwe_algorithm::JweAlgorithm;
 pub use crate::jwe::jwe_algorithm::JweDecrypter;
 pub use crate::jwe::jwe_algorithm::JweEncrypter;
+pub use crate::jwe::jwe_algorithm::KeyManagementMode;
 pub use crate::jwe::jwe_compression::JweCompression;
 pub use crate::jwe::jwe_content_encryption::JweContentEncryption;
 pub use crate::jwe::jwe_context::JweContext;
 pub use crate::jwe::jwe_header::JweHeader;
 pub use crate::jwe::jwe_header_set::JweHeaderSet;
+pub use crate::jwe::jwe_key_descriptor::JweKeyDescriptor;
+pub use crate::jwe::jwe_key_length_error::KeyLengthError;
 
 pub use crate::jwe::alg::direct::DirectJweAlgorithm::Dir;
 
@@ -89,6 +98,24 @@ where
     DEFAULT_CONTEXT.serialize_compact_with_selector(payload, header, selector)
 }
 
+/// Return a representation of the data that is formatted by compact serialization
+/// to the writer.
+///
+/// # Arguments
+///
+/// * `payload` - The reader of the payload data.
+/// * `header` - The JWE header claims.
+/// * `encrypter` - The JWE encrypter.
+/// * `out` - The writer of the representation.
+pub fn serialize_compact_to_writer(
+    payload: impl Read,
+    header: &JweHeader,
+    encrypter: &dyn JweEncrypter,
+    out: impl Write,
+) -> Result<(), JoseError> {
+    DEFAULT_CONTEXT.serialize_compact_to_writer(payload, header, encrypter, out)
+}
+
 /// Return a representation of the data that is formatted by flattened json serialization.
 ///
 /// # Arguments
@@ -181,6 +208,19 @@ where
     )
 }
 
+/// Deserialize the input that is formatted by compact or json serialization.
+///
+/// # Arguments
+///
+/// * `input` - The input data.
+/// * `decrypter` - The JWE decrypter.
+pub fn deserialize(
+    input: &str,
+    decrypter: &dyn JweDecrypter,
+) -> Result<(Vec<u8>, JweHeader), JoseError> {
+    DEFAULT_CONTEXT.deserialize(input, decrypter)
+}
+
 /// Deserialize the input that is formatted by compact serialization.
 ///
 /// # Arguments
@@ -240,6 +280,97 @@ where
     DEFAULT_CONTEXT.deserialize_json_with_selector(input, selector)
 }
 
+/// Encrypt the payload using the protected header as the additional authenticated data.
+///
+/// A random IV is generated when the content encryption requires it. The returned values
+/// are the IV, the ciphertext and the authentication tag; the IV and the tag are empty
+/// when the content encryption does not use them.
+///
+/// # Arguments
+///
+/// * `cencryption` - The content encryption algorithm.
+/// * `key` - The content encryption key.
+/// * `header` - The JWE protected header claims.
+/// * `payload` - The payload data.
+pub fn encrypt_content(
+    cencryption: &dyn JweContentEncryption,
+    key: &[u8],
+    header: &JweHeader,
+    payload: &[u8],
+) -> Result<EncryptedContent, JoseError> {
+    DEFAULT_CONTEXT.encrypt_content(cencryption, key, header, payload)
+}
+
+/// Return the content encryption algorithm (enc) of the highest server preference
+/// that is also supported by the client.
+///
+/// # Arguments
+///
+/// * `client_supported` - The enc values supported by the client.
+/// * `server_supported` - The enc values supported by the server in order of preference.
+pub fn negotiate_enc<'a>(
+    client_supported: &[&str],
+    server_supported: &[&'a str],
+) -> Option<&'a str> {
+    server_supported
+        .iter()
+        .find(|enc| client_supported.contains(enc))
+        .copied()
+}
+
+/// Decrypt the input that is formatted by compact serialization by using a JWK set,
+/// and return the payload and the header.
+///
+/// # Arguments
+///
+/// * `input` - The input data.
+/// * `jwk_set` - a JWK set.
+/// * `allowed` - acceptable values for JWE algorithm header claim (alg).
+pub fn decrypt_with_key_set(
+    input: impl AsRef<[u8]>,
+    jwk_set: &JwkSet,
+    allowed: &[&str],
+) -> Result<(Vec<u8>, JweHeader), JoseError> {
+    DEFAULT_CONTEXT.decrypt_with_key_set(input, jwk_set, allowed)
+}
+
+/// Return a decrypter of the algorithm for a key in a JWK set,
+/// or None if the algorithm is not supported.
+///
+/// # Arguments
+///
+/// * `alg` - A value of the alg header claim.
+/// * `jwk` - A key in a JWK set.
+#[allow(deprecated)]
+pub(crate) fn decrypter_from_jwk(
+    alg: &str,
+    jwk: &Jwk,
+) -> Result<Option<Box<dyn JweDecrypter>>, JoseError> {
+    let decrypter: Box<dyn JweDecrypter> = match alg {
+        alg_names::DIR => Box::new(Dir.decrypter_from_jwk(jwk)?),
+        alg_names::ECDH_ES => Box::new(ECDH_ES.decrypter_from_jwk(jwk)?),
+        alg_names::ECDH_ES_A128KW => Box::new(ECDH_ES_A128KW.decrypter_from_jwk(jwk)?),
+        alg_names::ECDH_ES_A192KW => Box::new(ECDH_ES_A192KW.decrypter_from_jwk(jwk)?),
+        alg_names::ECDH_ES_A256KW => Box::new(ECDH_ES_A256KW.decrypter_from_jwk(jwk)?),
+        alg_names::A128KW => Box::new(A128KW.decrypter_from_jwk(jwk)?),
+        alg_names::A192KW => Box::new(A192KW.decrypter_from_jwk(jwk)?),
+        alg_names::A256KW => Box::new(A256KW.decrypter_from_jwk(jwk)?),
+        alg_names::A128GCMKW => Box::new(A128GCMKW.decrypter_from_jwk(jwk)?),
+        alg_names::A192GCMKW => Box::new(A192GCMKW.decrypter_from_jwk(jwk)?),
+        alg_names::A256GCMKW => Box::new(A256GCMKW.decrypter_from_jwk(jwk)?),
+        alg_names::PBES2_HS256_A128KW => Box::new(PBES2_HS256_A128KW.decrypter_from_jwk(jwk)?),
+        alg_names::PBES2_HS384_A192KW => Box::new(PBES2_HS384_A192KW.decrypter_from_jwk(jwk)?),
+        alg_names::PBES2_HS512_A256KW => Box::new(PBES2_HS512_A256KW.decrypter_from_jwk(jwk)?),
+        alg_names::RSA1_5 => Box::new(RSA1_5.decrypter_from_jwk(jwk)?),
+        alg_names::RSA_OAEP => Box::new(RSA_OAEP.decrypter_from_jwk(jwk)?),
+        alg_names::RSA_OAEP_256 => Box::new(RSA_OAEP_256.decrypter_from_jwk(jwk)?),
+        alg_names::RSA_OAEP_384 => Box::new(RSA_OAEP_384.decrypter_from_jwk(jwk)?),
+        alg_names::RSA_OAEP_512 => Box::new(RSA_OAEP_512.decrypter_from_jwk(jwk)?),
+        _ => return Ok(None),
+    };
+    Ok(Some(decrypter))
+}
+
 #[cfg(test)]
 mod tests {
     use std::fs;
@@ -248,13 +379,186 @@ mod tests {
     use anyhow::Result;
 
     use crate::jwe::{
-        self, Dir, JweAlgorithm, JweHeader, JweHeaderSet, ECDH_ES_A128KW, PBES2_HS256_A128KW,
-        RSA_OAEP,
+        self, enc::A128GCM, Dir, JweAlgorithm, JweContentEncryption, JweHeader, JweHeaderSet,
+        KeyManagementMode, A128KW, A256KW, ECDH_ES, ECDH_ES_A128KW, PBES2_HS256_A128KW, RSA_OAEP,
     };
-    use crate::jwk::Jwk;
+    use crate::jwk::{Jwk, JwkSet};
     use crate::util;
     use crate::Value;
 
+    #[test]
+    fn test_jwe_decrypt_with_key_set() -> Result<()> {
+        let mut jwk_set = JwkSet::new();
+        let mut keys = Vec::new();
+        for key_id in ["key-1", "key-2", "key-3"] {
+            let mut jwk = Jwk::new("oct");
+            jwk.set_key_id(key_id);
+            jwk.set_parameter(
+                "k",
+                Some(Value::String(util::encode_base64_urlsafe_nopad(
+                    util::random_bytes(16),
+                ))),
+            )?;
+            jwk_set.push_key(jwk.clone());
+            keys.push(jwk);
+        }
+
+        let payload = b"test payload!";
+
+        let mut header = JweHeader::new();
+        header.set_content_encryption("A128GCM");
+        header.set_key_id("key-2");
+        let encrypter = A128KW.encrypter_from_jwk(&keys[1])?;
+        let jwe = jwe::serialize_compact(payload, &header, &encrypter)?;
+        let (dst_payload, dst_header) = jwe::decrypt_with_key_set(&jwe, &jwk_set, &["A128KW"])?;
+        assert_eq!(&dst_payload, payload);
+        assert_eq!(dst_header.key_id(), Some("key-2"));
+
+        let mut header = JweHeader::new();
+        header.set_content_encryption("A128GCM");
+        let encrypter = A128KW.encrypter_from_bytes(keys[2].parameter_bytes("k")?.unwrap())?;
+        let jwe = jwe::serialize_compact(payload, &header, &encrypter)?;
+        let (dst_payload, _) = jwe::decrypt_with_key_set(&jwe, &jwk_set, &["A128KW"])?;
+        assert_eq!(&dst_payload, payload);
+
+        assert!(jwe::decrypt_with_key_set(&jwe, &jwk_set, &["RSA-OAEP"]).is_err());
+
+        let mut header = JweHeader::new();
+        header.set_content_encryption("A128GCM");
+        header.set_key_id("key-1");
+        let encrypter = A128KW.encrypter_from_bytes(keys[2].parameter_bytes("k")?.unwrap())?;
+        let jwe = jwe::serialize_compact(payload, &header, &encrypter)?;
+        assert!(jwe::decrypt_with_key_set(&jwe, &jwk_set, &["A128KW"]).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_jwe_key_management_mode() {
+        assert_eq!(
+            RSA_OAEP.key_management_mode(),
+            KeyManagementMode::KeyEncryption
+        );
+        assert_eq!(A128KW.key_management_mode(), KeyManagementMode::KeyWrapping);
+        assert_eq!(
+            Dir.key_management_mode(),
+            KeyManagementMode::DirectEncryption
+        );
+        assert_eq!(
+            ECDH_ES.key_management_mode(),
+            KeyManagementMode::DirectKeyAgreement
+        );
+        assert_eq!(
+            ECDH_ES_A128KW.key_management_mode(),
+            KeyManagementMode::KeyAgreementWithKeyWrapping
+        );
+        assert_eq!(
+            PBES2_HS256_A128KW.key_management_mode(),
+            KeyManagementMode::KeyWrapping
+        );
+    }
+
+    #[test]
+    fn test_jwe_deserialize_compact_or_json() -> Result<()> {
+        let key = util::random_bytes(32);
+        let encrypter = A256KW.encrypter_from_bytes(&key)?;
+        let decrypter = A256KW.decrypter_from_bytes(&key)?;
+        let payload = b"hello world";
+
+        let mut header = JweHeader::new();
+        header.set_content_encryption("A128GCM");
+        let compact = jwe::serialize_compact(payload, &header, &encrypter)?;
+
+        let mut header = JweHeaderSet::new();
+        header.set_content_encryption("A128GCM", true);
+        let json = jwe::serialize_flattened_json(payload, Some(&header), None, None, &encrypter)?;
+
+        for input in [compact, json.clone(), format!("\n {}", json)] {
+            let (dst_payload, dst_header) = jwe::deserialize(&input, &decrypter)?;
+            assert_eq!(dst_payload, payload);
+            assert_eq!(dst_header.algorithm(), Some("A256KW"));
+        }
+
+        assert!(jwe::deserialize("{\"protected\":", &decrypter).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_jwe_replicated_jwt_claims() -> Result<()> {
+        let key = util::random_bytes(32);
+        let encrypter = A256KW.encrypter_from_bytes(&key)?;
+        let decrypter = A256KW.decrypter_from_bytes(&key)?;
+
+        for audience in [vec!["client-1"], vec!["client-1", "client-2"]] {
+            let mut header = JweHeader::new();
+            header.set_content_encryption("A128GCM");
+            header.set_content_type("JWT");
+            header.set_issuer("https://issuer.example.com");
+            header.set_subject("user-1");
+            header.set_audience(audience.clone());
+
+            let jwe = jwe::serialize_compact(b"payload", &header, &encrypter)?;
+            let (_, dst_header) = jwe::deserialize_compact(&jwe, &decrypter)?;
+            assert_eq!(dst_header.issuer(), Some("https://issuer.example.com"));
+            assert_eq!(dst_header.subject(), Some("user-1"));
+            assert_eq!(dst_header.audience(), Some(audience));
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_negotiate_enc() {
+        let server = ["A256GCM", "A128GCM", "A128CBC-HS256"];
+
+        assert_eq!(
+            jwe::negotiate_enc(&["A128CBC-HS256", "A128GCM"], &server),
+            Some("A128GCM")
+        );
+        assert_eq!(
+            jwe::negotiate_enc(&["A128GCM", "A256GCM"], &server),
+            Some("A256GCM")
+        );
+        assert_eq!(
+            jwe::negotiate_enc(&["A128CBC-HS256"], &server),
+            Some("A128CBC-HS256")
+        );
+        assert_eq!(jwe::negotiate_enc(&["A192GCM"], &server), None);
+        assert_eq!(jwe::negotiate_enc(&[], &server), None);
+        assert_eq!(jwe::negotiate_enc(&["A128GCM"], &[]), None);
+    }
+
+    #[test]
+    fn test_jwe_a256kw_with_a256cbc_hs512() -> Result<()> {
+        let key = util::random_bytes(32);
+        let encrypter = A256KW.encrypter_from_bytes(&key)?;
+        let decrypter = A256KW.decrypter_from_bytes(&key)?;
+        let src_payload = b"test payload!";
+
+        let mut src_header = JweHeader::new();
+        src_header.set_content_encryption("A256CBC-HS512");
+        let jwe = jwe::serialize_compact(src_payload, &src_header, &encrypter)?;
+        let (dst_payload, dst_header) = jwe::deserialize_compact(&jwe, &decrypter)?;
+        assert_eq!(dst_header.algorithm(), Some("A256KW"));
+        assert_eq!(dst_header.content_encryption(), Some("A256CBC-HS512"));
+        assert_eq!(dst_payload, src_payload);
+
+        let mut src_header = JweHeaderSet::new();
+        src_header.set_content_encryption("A256CBC-HS512", true);
+        let jwe = jwe::serialize_general_json(
+            src_payload,
+            Some(&src_header),
+            &[(None, &encrypter)],
+            None,
+        )?;
+        let (dst_payload, dst_header) = jwe::deserialize_json(&jwe, &decrypter)?;
+        assert_eq!(dst_header.content_encryption(), Some("A256CBC-HS512"));
+        assert_eq!(dst_payload, src_payload);
+
+        Ok(())
+    }
+
     #[test]
     fn test_jwe_compact_serialization() -> Result<()> {
         for enc in vec![
@@ -306,9 +610,10 @@ mod tests {
         let src_payload = b"test payload!";
         let mut src_header = JweHeaderSet::new();
         src_header.set_key_id("xxx", true);
+        src_header.set_content_encryption("A128GCM", true);
         src_header.set_token_type("JWT", false);
         let mut src_rheader = JweHeader::new();
-        src_rheader.set_content_encryption("A128GCM");
+        src_rheader.set_jwk_set_url("https://example.com/jwks");
 
         let encrypter = alg.encrypter_from_pem(&public_key)?;
         let jwt = jwe::serialize_flattened_json(
@@ -324,11 +629,12 @@ mod tests {
 
         src_header.set_algorithm(alg.name(), true);
         assert_eq!(
-            src_rheader.content_encryption(),
+            src_header.content_encryption(),
             dst_header.content_encryption()
         );
         assert_eq!(src_header.key_id(), dst_header.key_id());
         assert_eq!(src_header.token_type(), dst_header.token_type());
+        assert_eq!(src_rheader.jwk_set_url(), dst_header.jwk_set_url());
         assert_eq!(src_payload.to_vec(), dst_payload);
 
         Ok(())
@@ -383,6 +689,34 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_jwe_encrypt_content() -> Result<()> {
+        let src_payload = b"test payload!";
+        let key = util::random_bytes(A128GCM.key_len());
+
+        let mut header = JweHeader::new();
+        header.set_algorithm("dir");
+        header.set_content_encryption(A128GCM.name());
+
+        let (iv, ciphertext, tag) = jwe::encrypt_content(&A128GCM, &key, &header, src_payload)?;
+        assert_eq!(iv.len(), A128GCM.iv_len());
+        assert_eq!(tag.len(), A128GCM.tag_len());
+
+        let jwe = format!(
+            "{}..{}.{}.{}",
+            util::encode_base64_urlsafe_nopad(serde_json::to_vec(header.claims_set())?),
+            util::encode_base64_urlsafe_nopad(iv),
+            util::encode_base64_urlsafe_nopad(ciphertext),
+            util::encode_base64_urlsafe_nopad(tag),
+        );
+
+        let decrypter = Dir.decrypter_from_bytes(&key)?;
+        let (dst_payload, _) = jwe::deserialize_compact(&jwe, &decrypter)?;
+        assert_eq!(src_payload.to_vec(), dst_payload);
+
+        Ok(())
+    }
+
     fn load_file(path: &str) -> Result<Vec<u8>> {
         let mut pb = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
         pb.push("data");
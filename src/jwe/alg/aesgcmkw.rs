@@ -5,10 +5,12 @@ use std::ops::Deref;
 use anyhow::bail;
 use openssl::symm::{self, Cipher};
 
-use crate::jwe::{JweAlgorithm, JweContentEncryption, JweDecrypter, JweEncrypter, JweHeader};
+use crate::jwe::{
+    JweAlgorithm, JweContentEncryption, JweDecrypter, JweEncrypter, JweHeader, KeyManagementMode,
+};
 use crate::jwk::Jwk;
-use crate::util;
-use crate::{JoseError, JoseHeader, Value};
+use crate::util::{self, OpensslRandomSource, RandomSource};
+use crate::{alg_names, JoseError, JoseHeader, Value};
 
 #[derive(Debug, Eq, PartialEq, Copy, Clone)]
 pub enum AesgcmkwJweAlgorithm {
@@ -170,9 +172,21 @@ impl AesgcmkwJweAlgorithm {
 impl JweAlgorithm for AesgcmkwJweAlgorithm {
     fn name(&self) -> &str {
         match self {
-            Self::A128gcmkw => "A128GCMKW",
-            Self::A192gcmkw => "A192GCMKW",
-            Self::A256gcmkw => "A256GCMKW",
+            Self::A128gcmkw => alg_names::A128GCMKW,
+            Self::A192gcmkw => alg_names::A192GCMKW,
+            Self::A256gcmkw => alg_names::A256GCMKW,
+        }
+    }
+
+    fn key_management_mode(&self) -> KeyManagementMode {
+        KeyManagementMode::KeyWrapping
+    }
+
+    fn recommended_content_encryption(&self) -> &'static str {
+        match self {
+            Self::A128gcmkw => alg_names::A128GCM,
+            Self::A192gcmkw => alg_names::A192GCM,
+            Self::A256gcmkw => alg_names::A256GCM,
         }
     }
 
@@ -234,20 +248,31 @@ impl JweEncrypter for AesgcmkwJweEncrypter {
     }
 
     fn encrypt(
+        &self,
+        key: &[u8],
+        in_header: &JweHeader,
+        out_header: &mut JweHeader,
+    ) -> Result<Option<Vec<u8>>, JoseError> {
+        self.encrypt_with_random_source(key, in_header, out_header, &OpensslRandomSource)
+    }
+
+    fn encrypt_with_random_source(
         &self,
         key: &[u8],
         _in_header: &JweHeader,
         out_header: &mut JweHeader,
+        random_source: &dyn RandomSource,
     ) -> Result<Option<Vec<u8>>, JoseError> {
         (|| -> anyhow::Result<Option<Vec<u8>>> {
-            let iv = util::random_bytes(12);
+            let mut iv = [0; 12];
+            random_source.fill(&mut iv)?;
 
             let cipher = self.algorithm.cipher();
             let mut tag = [0; 16];
             let encrypted_key =
                 symm::encrypt_aead(cipher, &self.private_key, Some(&iv), b"", &key, &mut tag)?;
 
-            let iv = util::encode_base64_urlsafe_nopad(&iv);
+            let iv = util::encode_base64_urlsafe_nopad(iv);
             out_header.set_claim("iv", Some(Value::String(iv)))?;
 
             let tag = util::encode_base64_urlsafe_nopad(&tag);
@@ -303,6 +328,10 @@ impl JweDecrypter for AesgcmkwJweDecrypter {
         }
     }
 
+    fn expected_encrypted_key_len(&self, cencryption: &dyn JweContentEncryption) -> Option<usize> {
+        Some(cencryption.key_len())
+    }
+
     fn decrypt(
         &self,
         encrypted_key: Option<&[u8]>,
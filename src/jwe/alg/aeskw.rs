@@ -5,7 +5,9 @@ use std::ops::Deref;
 use anyhow::bail;
 use openssl::aes::{self, AesKey};
 
-use crate::jwe::{JweAlgorithm, JweContentEncryption, JweDecrypter, JweEncrypter, JweHeader};
+use crate::jwe::{
+    JweAlgorithm, JweContentEncryption, JweDecrypter, JweEncrypter, JweHeader, KeyManagementMode,
+};
 use crate::jwk::Jwk;
 use crate::{util, JoseError, Value};
 
@@ -167,6 +169,18 @@ impl JweAlgorithm for AeskwJweAlgorithm {
         }
     }
 
+    fn key_management_mode(&self) -> KeyManagementMode {
+        KeyManagementMode::KeyWrapping
+    }
+
+    fn recommended_content_encryption(&self) -> &'static str {
+        match self {
+            Self::A128kw => "A128GCM",
+            Self::A192kw => "A192GCM",
+            Self::A256kw => "A256GCM",
+        }
+    }
+
     fn box_clone(&self) -> Box<dyn JweAlgorithm> {
         Box::new(self.clone())
     }
@@ -293,10 +307,14 @@ impl JweDecrypter for AeskwJweDecrypter {
         }
     }
 
+    fn expected_encrypted_key_len(&self, cencryption: &dyn JweContentEncryption) -> Option<usize> {
+        Some(cencryption.key_len() + 8)
+    }
+
     fn decrypt(
         &self,
         encrypted_key: Option<&[u8]>,
-        _cencryption: &dyn JweContentEncryption,
+        cencryption: &dyn JweContentEncryption,
         _header: &JweHeader,
     ) -> Result<Cow<[u8]>, JoseError> {
         (|| -> anyhow::Result<Cow<[u8]>> {
@@ -305,6 +323,15 @@ impl JweDecrypter for AeskwJweDecrypter {
                 None => bail!("A encrypted_key is required."),
             };
 
+            let expected_len = cencryption.key_len() + 8;
+            if encrypted_key.len() != expected_len {
+                bail!(
+                    "The encrypted_key size must be {}: {}",
+                    expected_len,
+                    encrypted_key.len()
+                );
+            }
+
             let aes = match AesKey::new_decrypt(&self.private_key) {
                 Ok(val) => val,
                 Err(_) => bail!("Failed to set decrypt key."),
@@ -348,6 +375,7 @@ mod tests {
     use crate::jwe::JweHeader;
     use crate::jwk::Jwk;
     use crate::util;
+    use crate::JoseError;
 
     #[test]
     fn encrypt_and_decrypt_aes() -> Result<()> {
@@ -384,4 +412,36 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn decrypt_aes_with_truncated_encrypted_key() -> Result<()> {
+        let enc = AescbcHmacJweEncryption::A128cbcHs256;
+
+        for alg in [
+            AeskwJweAlgorithm::A128kw,
+            AeskwJweAlgorithm::A192kw,
+            AeskwJweAlgorithm::A256kw,
+        ] {
+            let mut header = JweHeader::new();
+            header.set_content_encryption(enc.name());
+
+            let key = util::random_bytes(alg.key_len());
+            let encrypter = alg.encrypter_from_bytes(&key)?;
+            let src_key = util::random_bytes(enc.key_len());
+            let mut out_header = header.clone();
+            let encrypted_key = encrypter
+                .encrypt(&src_key, &header, &mut out_header)?
+                .unwrap();
+
+            let decrypter = alg.decrypter_from_bytes(&key)?;
+            for len in [0, 4, encrypted_key.len() - 8] {
+                match decrypter.decrypt(Some(&encrypted_key[..len]), &enc, &out_header) {
+                    Err(JoseError::InvalidJweFormat(_)) => {}
+                    other => panic!("Truncated encrypted_key was accepted: {:?}", other),
+                }
+            }
+        }
+
+        Ok(())
+    }
 }
@@ -4,7 +4,9 @@ use std::ops::Deref;
 
 use anyhow::bail;
 
-use crate::jwe::{JweAlgorithm, JweContentEncryption, JweDecrypter, JweEncrypter, JweHeader};
+use crate::jwe::{
+    JweAlgorithm, JweContentEncryption, JweDecrypter, JweEncrypter, JweHeader, KeyManagementMode,
+};
 use crate::jwk::Jwk;
 use crate::{util, JoseError, Value};
 
@@ -122,6 +124,10 @@ impl JweAlgorithm for DirectJweAlgorithm {
         }
     }
 
+    fn key_management_mode(&self) -> KeyManagementMode {
+        KeyManagementMode::DirectEncryption
+    }
+
     fn box_clone(&self) -> Box<dyn JweAlgorithm> {
         Box::new(self.clone())
     }
@@ -307,6 +313,7 @@ mod tests {
             assert_eq!(encrypted_key, None);
 
             let decrypter = alg.decrypter_from_jwk(&jwk)?;
+            assert_eq!(decrypter.expected_encrypted_key_len(&enc), None);
             let dst_key = decrypter.decrypt(encrypted_key.as_deref(), &enc, &out_header)?;
 
             assert_eq!(&src_key, &dst_key);
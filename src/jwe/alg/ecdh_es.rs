@@ -5,10 +5,12 @@ use std::ops::Deref;
 use anyhow::bail;
 use openssl::aes::{self, AesKey};
 use openssl::derive::Deriver;
-use openssl::hash::{Hasher, MessageDigest};
+use openssl::hash::MessageDigest;
 use openssl::pkey::{PKey, Private, Public};
 
-use crate::jwe::{JweAlgorithm, JweContentEncryption, JweDecrypter, JweEncrypter, JweHeader};
+use crate::jwe::{
+    JweAlgorithm, JweContentEncryption, JweDecrypter, JweEncrypter, JweHeader, KeyManagementMode,
+};
 use crate::jwk::alg::{
     ec::{EcCurve, EcKeyPair},
     ecx::{EcxCurve, EcxKeyPair},
@@ -226,13 +228,7 @@ impl EcdhEsJweAlgorithm {
                             None => bail!("A parameter y is required."),
                         };
 
-                        let mut vec = Vec::with_capacity(1 + x.len() + y.len());
-                        vec.push(0x04);
-                        vec.extend_from_slice(&x);
-                        vec.extend_from_slice(&y);
-
-                        let pkcs8 = EcKeyPair::to_pkcs8(&vec, true, curve);
-                        let public_key = PKey::public_key_from_der(&pkcs8)?;
+                        let public_key = EcKeyPair::public_key_from_coordinates(&x, &y, curve)?;
 
                         (public_key, EcdhEsKeyType::Ec(curve))
                     }
@@ -248,8 +244,7 @@ impl EcdhEsJweAlgorithm {
                             None => bail!("A parameter x is required."),
                         };
 
-                        let pkcs8 = EcxKeyPair::to_pkcs8(&x, true, curve);
-                        let public_key = PKey::public_key_from_der(&pkcs8)?;
+                        let public_key = EcxKeyPair::public_key_from_bytes(&x, curve)?;
 
                         (public_key, EcdhEsKeyType::Ecx(curve))
                     }
@@ -502,47 +497,14 @@ impl EcdhEsJweAlgorithm {
         apu: Option<&[u8]>,
         apv: Option<&[u8]>,
     ) -> anyhow::Result<Vec<u8>> {
-        let shared_key_len_bytes = ((shared_key_len * 8) as u32).to_be_bytes();
-        let alg_len_bytes = (alg.len() as u32).to_be_bytes();
-        let apu_len_bytes = (match apu {
-            Some(val) => val.len(),
-            None => 0,
-        } as u32)
-            .to_be_bytes();
-        let apv_len_bytes = (match apv {
-            Some(val) => val.len(),
-            None => 0,
-        } as u32)
-            .to_be_bytes();
-
-        let mut shared_key = Vec::new();
-        let md = MessageDigest::sha256();
-        let count = util::ceiling(shared_key_len, md.size());
-        for i in 0..count {
-            let mut hasher = Hasher::new(md)?;
-            hasher.update(&((i + 1) as u32).to_be_bytes())?;
-            hasher.update(&derived_key)?;
-            hasher.update(&alg_len_bytes)?;
-            hasher.update(alg.as_bytes())?;
-            hasher.update(&apu_len_bytes)?;
-            if let Some(val) = apu {
-                hasher.update(val)?;
-            }
-            hasher.update(&apv_len_bytes)?;
-            if let Some(val) = apv {
-                hasher.update(val)?;
-            }
-            hasher.update(&shared_key_len_bytes)?;
-
-            let digest = hasher.finish()?;
-            shared_key.extend(digest.to_vec());
-        }
-
-        if shared_key.len() > shared_key_len {
-            shared_key.truncate(shared_key_len);
-        } else if shared_key.len() < shared_key_len {
-            unreachable!();
-        }
+        let shared_key = util::concat_kdf(
+            derived_key,
+            shared_key_len,
+            alg.as_bytes(),
+            apu.unwrap_or_default(),
+            apv.unwrap_or_default(),
+            MessageDigest::sha256(),
+        )?;
 
         Ok(shared_key)
     }
@@ -558,6 +520,22 @@ impl JweAlgorithm for EcdhEsJweAlgorithm {
         }
     }
 
+    fn key_management_mode(&self) -> KeyManagementMode {
+        match self {
+            Self::EcdhEs => KeyManagementMode::DirectKeyAgreement,
+            _ => KeyManagementMode::KeyAgreementWithKeyWrapping,
+        }
+    }
+
+    fn recommended_content_encryption(&self) -> &'static str {
+        match self {
+            Self::EcdhEs => "A256GCM",
+            Self::EcdhEsA128kw => "A128GCM",
+            Self::EcdhEsA192kw => "A192GCM",
+            Self::EcdhEsA256kw => "A256GCM",
+        }
+    }
+
     fn box_clone(&self) -> Box<dyn JweAlgorithm> {
         Box::new(self.clone())
     }
@@ -828,6 +806,13 @@ impl JweDecrypter for EcdhEsJweDecrypter {
         }
     }
 
+    fn expected_encrypted_key_len(&self, cencryption: &dyn JweContentEncryption) -> Option<usize> {
+        match self.algorithm {
+            EcdhEsJweAlgorithm::EcdhEs => None,
+            _ => Some(cencryption.key_len() + 8),
+        }
+    }
+
     fn decrypt(
         &self,
         encrypted_key: Option<&[u8]>,
@@ -903,18 +888,12 @@ impl JweDecrypter for EcdhEsJweDecrypter {
                                     util::decode_base64_urlsafe_no_pad(val)?
                                 }
                                 Some(_) => {
-                                    bail!("The x parameter in epk header claim must be a string.")
+                                    bail!("The y parameter in epk header claim must be a string.")
                                 }
-                                None => bail!("The x parameter in epk header claim is required."),
+                                None => bail!("The y parameter in epk header claim is required."),
                             };
 
-                            let mut vec = Vec::with_capacity(1 + x.len() + y.len());
-                            vec.push(0x04);
-                            vec.extend_from_slice(&x);
-                            vec.extend_from_slice(&y);
-
-                            let pkcs8 = EcKeyPair::to_pkcs8(&vec, true, *curve);
-                            PKey::public_key_from_der(&pkcs8)?
+                            EcKeyPair::public_key_from_coordinates(&x, &y, *curve)?
                         }
                         EcdhEsKeyType::Ecx(curve) => {
                             let x = match map.get("x") {
@@ -927,8 +906,7 @@ impl JweDecrypter for EcdhEsJweDecrypter {
                                 None => bail!("The x parameter in epk header claim is required."),
                             };
 
-                            let pkcs8 = EcxKeyPair::to_pkcs8(&x, true, *curve);
-                            PKey::public_key_from_der(&pkcs8)?
+                            EcxKeyPair::public_key_from_bytes(&x, *curve)?
                         }
                     }
                 }
@@ -938,7 +916,12 @@ impl JweDecrypter for EcdhEsJweDecrypter {
 
             let mut deriver = Deriver::new(&self.private_key)?;
             deriver.set_peer(&public_key)?;
-            let derived_key = deriver.derive_to_vec()?;
+            // Some OpenSSL versions fail to derive the all-zero X25519/X448
+            // output instead of returning it, so both are rejected alike.
+            let derived_key = match deriver.derive_to_vec() {
+                Ok(val) if !val.iter().all(|b| *b == 0) => val,
+                _ => bail!("The shared secret derived from epk header claim must not be zero."),
+            };
 
             // concat KDF
             if let EcdhEsJweAlgorithm::EcdhEs = self.algorithm {
@@ -969,6 +952,15 @@ impl JweDecrypter for EcdhEsJweDecrypter {
                     None => unreachable!(),
                 };
 
+                let expected_len = cencryption.key_len() + 8;
+                if encrypted_key.len() != expected_len {
+                    bail!(
+                        "The encrypted_key size must be {}: {}",
+                        expected_len,
+                        encrypted_key.len()
+                    );
+                }
+
                 let mut key = vec![0; encrypted_key.len() - 8];
                 match aes::unwrap_key(&aes, None, &mut key, &encrypted_key) {
                     Ok(len) => {
@@ -1012,6 +1004,7 @@ mod tests {
     use crate::jwk::alg::{ec::EcCurve, ecx::EcxCurve};
     use crate::jwk::Jwk;
     use crate::util;
+    use crate::{JoseError, JoseHeader, Value};
 
     #[test]
     fn encrypt_and_decrypt_ecdh_es_with_pkcs8_der() -> Result<()> {
@@ -1265,6 +1258,205 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn encrypt_and_decrypt_ecdh_es_with_generated_ecx_key_pair() -> Result<()> {
+        let enc = AesgcmJweEncryption::A256gcm;
+
+        for alg in [EcdhEsJweAlgorithm::EcdhEs, EcdhEsJweAlgorithm::EcdhEsA256kw] {
+            for curve in [EcxCurve::X25519, EcxCurve::X448] {
+                let key_pair = alg.generate_ecx_key_pair(curve)?;
+
+                let mut header = JweHeader::new();
+                header.set_content_encryption(enc.name());
+
+                let encrypter = alg.encrypter_from_jwk(&key_pair.to_jwk_public_key())?;
+                let mut out_header = header.clone();
+                let src_key = match encrypter.compute_content_encryption_key(
+                    &enc,
+                    &header,
+                    &mut out_header,
+                )? {
+                    Some(val) => val,
+                    None => Cow::Owned(util::random_bytes(enc.key_len())),
+                };
+                let encrypted_key = encrypter.encrypt(&src_key, &header, &mut out_header)?;
+
+                match out_header.claim("epk") {
+                    Some(Value::Object(epk)) => {
+                        assert_eq!(epk.get("kty"), Some(&Value::String("OKP".to_string())));
+                        assert_eq!(
+                            epk.get("crv"),
+                            Some(&Value::String(curve.name().to_string()))
+                        );
+                        assert!(epk.get("x").is_some());
+                        assert!(epk.get("y").is_none());
+                    }
+                    other => panic!("The epk header claim is invalid: {:?}", other),
+                }
+
+                out_header.set_algorithm(alg.name());
+                let decrypter = alg.decrypter_from_jwk(&key_pair.to_jwk_key_pair())?;
+                let dst_key = decrypter.decrypt(encrypted_key.as_deref(), &enc, &out_header)?;
+
+                assert_eq!(&src_key, &dst_key);
+            }
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn reject_ecdh_es_jwk_with_off_curve_point() -> Result<()> {
+        for curve in [
+            EcCurve::P256,
+            EcCurve::P384,
+            EcCurve::P521,
+            EcCurve::Secp256k1,
+        ] {
+            let alg = EcdhEsJweAlgorithm::EcdhEs;
+            let key_pair = alg.generate_ec_key_pair(curve)?;
+            let mut jwk = key_pair.to_jwk_public_key();
+
+            let mut y = match jwk.parameter("y") {
+                Some(Value::String(val)) => util::decode_base64_urlsafe_no_pad(val)?,
+                _ => unreachable!(),
+            };
+            let last = y.len() - 1;
+            y[last] ^= 0x01;
+            jwk.set_parameter(
+                "y",
+                Some(Value::String(util::encode_base64_urlsafe_nopad(&y))),
+            )?;
+
+            match alg.encrypter_from_jwk(&jwk) {
+                Err(JoseError::InvalidKeyFormat(_)) => {}
+                other => panic!("Off-curve point was accepted: {:?}", other),
+            }
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn reject_ecdh_es_jwk_with_short_x() -> Result<()> {
+        for (curve, expected_len) in [("X25519", 32), ("X448", 56)] {
+            let mut jwk = Jwk::new("OKP");
+            jwk.set_parameter("crv", Some(Value::String(curve.to_string())))?;
+            jwk.set_parameter(
+                "x",
+                Some(Value::String(util::encode_base64_urlsafe_nopad(vec![
+                    1u8;
+                    expected_len - 1
+                ]))),
+            )?;
+
+            match EcdhEsJweAlgorithm::EcdhEs.encrypter_from_jwk(&jwk) {
+                Err(JoseError::InvalidKeyFormat(err)) => assert_eq!(
+                    err.to_string(),
+                    format!(
+                        "The length of public key must be {} for {}: {}",
+                        expected_len,
+                        curve,
+                        expected_len - 1
+                    )
+                ),
+                other => panic!("A short x parameter was accepted: {:?}", other),
+            }
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn reject_ecdh_es_epk_with_mismatched_curve() -> Result<()> {
+        let enc = AesgcmJweEncryption::A128gcm;
+
+        for alg in [EcdhEsJweAlgorithm::EcdhEs, EcdhEsJweAlgorithm::EcdhEsA128kw] {
+            for (sender_key, recipient_key) in [
+                (
+                    "der/EC_P-384_spki_public.der",
+                    "der/EC_P-256_pkcs8_private.der",
+                ),
+                (
+                    "der/X25519_spki_public.der",
+                    "der/EC_P-256_pkcs8_private.der",
+                ),
+                ("der/X448_spki_public.der", "der/X25519_pkcs8_private.der"),
+            ] {
+                let mut header = JweHeader::new();
+                header.set_content_encryption(enc.name());
+
+                let encrypter = alg.encrypter_from_der(&load_file(sender_key)?)?;
+                let mut out_header = header.clone();
+                let src_key = match encrypter.compute_content_encryption_key(
+                    &enc,
+                    &header,
+                    &mut out_header,
+                )? {
+                    Some(val) => val,
+                    None => Cow::Owned(util::random_bytes(enc.key_len())),
+                };
+                let encrypted_key = encrypter.encrypt(&src_key, &header, &mut out_header)?;
+
+                out_header.set_algorithm(alg.name());
+                let decrypter = alg.decrypter_from_der(&load_file(recipient_key)?)?;
+                match decrypter.decrypt(encrypted_key.as_deref(), &enc, &out_header) {
+                    Err(JoseError::InvalidJweFormat(err)) => {
+                        let message = err.to_string();
+                        assert!(
+                            message.contains("epk header claim is invalid"),
+                            "{}",
+                            message
+                        );
+                    }
+                    other => panic!("Mismatched epk was accepted: {:?}", other),
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn reject_ecdh_es_epk_with_zero_shared_secret() -> Result<()> {
+        let enc = AesgcmJweEncryption::A128gcm;
+
+        for alg in [EcdhEsJweAlgorithm::EcdhEs, EcdhEsJweAlgorithm::EcdhEsA128kw] {
+            // The u-coordinate 0 is a point of small order on Curve25519,
+            // so the X25519 function outputs the all-zero value.
+            let mut epk = crate::Map::new();
+            epk.insert("kty".to_string(), Value::String("OKP".to_string()));
+            epk.insert("crv".to_string(), Value::String("X25519".to_string()));
+            epk.insert(
+                "x".to_string(),
+                Value::String(util::encode_base64_urlsafe_nopad([0u8; 32])),
+            );
+
+            let mut header = JweHeader::new();
+            header.set_algorithm(alg.name());
+            header.set_content_encryption(enc.name());
+            header.set_claim("epk", Some(Value::Object(epk)))?;
+
+            let encrypted_key = match alg {
+                EcdhEsJweAlgorithm::EcdhEs => None,
+                _ => Some(vec![0u8; 24]),
+            };
+
+            let decrypter = alg.decrypter_from_der(&load_file("der/X25519_pkcs8_private.der")?)?;
+            match decrypter.decrypt(encrypted_key.as_deref(), &enc, &header) {
+                Err(JoseError::InvalidJweFormat(err)) => {
+                    assert_eq!(
+                        err.to_string(),
+                        "The shared secret derived from epk header claim must not be zero."
+                    );
+                }
+                other => panic!("The zero shared secret was accepted: {:?}", other),
+            }
+        }
+
+        Ok(())
+    }
+
     fn load_file(path: &str) -> Result<Vec<u8>> {
         let mut pb = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
         pb.push("data");
@@ -5,14 +5,20 @@ use std::ops::Deref;
 
 use anyhow::bail;
 use openssl::aes::{self, AesKey};
-use openssl::hash::MessageDigest;
 use openssl::pkcs5;
 
-use crate::jwe::{JweAlgorithm, JweContentEncryption, JweDecrypter, JweEncrypter, JweHeader};
+use crate::jwe::{
+    JweAlgorithm, JweContentEncryption, JweDecrypter, JweEncrypter, JweHeader, KeyManagementMode,
+};
 use crate::jwk::Jwk;
-use crate::util::{self, HashAlgorithm};
+use crate::util::{self, HashAlgorithm, OpensslRandomSource, RandomSource};
 use crate::{JoseError, JoseHeader, Number, Value};
 
+const MIN_SALT_LEN: usize = 8;
+const DEFAULT_SALT_LEN: usize = 16;
+const MIN_ITER_COUNT: usize = 1000;
+const DEFAULT_MAX_ITER_COUNT: usize = 1000000;
+
 #[derive(Debug, Eq, PartialEq, Copy, Clone)]
 pub enum Pbes2HmacAeskwJweAlgorithm {
     /// PBES2 with HMAC SHA-256 and "A128KW" wrapping
@@ -38,7 +44,7 @@ impl Pbes2HmacAeskwJweAlgorithm {
             Ok(Pbes2HmacAeskwJweEncrypter {
                 algorithm: self.clone(),
                 private_key,
-                salt_len: 8,
+                salt_len: DEFAULT_SALT_LEN,
                 iter_count: 1000,
                 key_id: None,
             })
@@ -80,7 +86,7 @@ impl Pbes2HmacAeskwJweAlgorithm {
             Ok(Pbes2HmacAeskwJweEncrypter {
                 algorithm: self.clone(),
                 private_key: k,
-                salt_len: 8,
+                salt_len: DEFAULT_SALT_LEN,
                 iter_count: 1000,
                 key_id,
             })
@@ -102,6 +108,7 @@ impl Pbes2HmacAeskwJweAlgorithm {
             Ok(Pbes2HmacAeskwJweDecrypter {
                 algorithm: self.clone(),
                 private_key,
+                max_iter_count: DEFAULT_MAX_ITER_COUNT,
                 key_id: None,
             })
         })()
@@ -143,6 +150,7 @@ impl Pbes2HmacAeskwJweAlgorithm {
             Ok(Pbes2HmacAeskwJweDecrypter {
                 algorithm: self.clone(),
                 private_key: k,
+                max_iter_count: DEFAULT_MAX_ITER_COUNT,
                 key_id,
             })
         })()
@@ -164,6 +172,23 @@ impl Pbes2HmacAeskwJweAlgorithm {
             Self::Pbes2Hs512A256kw => 32,
         }
     }
+
+    /// Return the salt input of PBKDF2: (UTF8(alg) || 0x00 || p2s).
+    fn salt_input(&self, p2s: &[u8]) -> Vec<u8> {
+        let name = self.name().as_bytes();
+        let mut salt = Vec::with_capacity(name.len() + 1 + p2s.len());
+        salt.extend_from_slice(name);
+        salt.push(0);
+        salt.extend_from_slice(p2s);
+        salt
+    }
+
+    fn derive_key(&self, password: &[u8], p2s: &[u8], p2c: usize) -> anyhow::Result<Vec<u8>> {
+        let md = self.hash_algorithm().message_digest()?;
+        let mut derived_key = vec![0; self.derived_key_len()];
+        pkcs5::pbkdf2_hmac(password, &self.salt_input(p2s), p2c, md, &mut derived_key)?;
+        Ok(derived_key)
+    }
 }
 
 impl JweAlgorithm for Pbes2HmacAeskwJweAlgorithm {
@@ -175,6 +200,18 @@ impl JweAlgorithm for Pbes2HmacAeskwJweAlgorithm {
         }
     }
 
+    fn key_management_mode(&self) -> KeyManagementMode {
+        KeyManagementMode::KeyWrapping
+    }
+
+    fn recommended_content_encryption(&self) -> &'static str {
+        match self {
+            Self::Pbes2Hs256A128kw => "A128GCM",
+            Self::Pbes2Hs384A192kw => "A192GCM",
+            Self::Pbes2Hs512A256kw => "A256GCM",
+        }
+    }
+
     fn box_clone(&self) -> Box<dyn JweAlgorithm> {
         Box::new(self.clone())
     }
@@ -204,16 +241,26 @@ pub struct Pbes2HmacAeskwJweEncrypter {
 }
 
 impl Pbes2HmacAeskwJweEncrypter {
+    /// Set the length of the random salt (p2s) that is generated on encryption.
+    ///
+    /// The default value is 16.
+    ///
+    /// # Arguments
+    ///
+    /// * `salt_len` - a salt length
     pub fn set_salt_len(&mut self, salt_len: usize) {
-        if salt_len < 8 {
-            panic!("salt_len must be 8 or more: {}", salt_len);
+        if salt_len < MIN_SALT_LEN {
+            panic!("salt_len must be {} or more: {}", MIN_SALT_LEN, salt_len);
         }
         self.salt_len = salt_len;
     }
 
     pub fn set_iter_count(&mut self, iter_count: usize) {
-        if iter_count < 1000 {
-            panic!("iter_count must be 1000 or more: {}", iter_count);
+        if iter_count < MIN_ITER_COUNT {
+            panic!(
+                "iter_count must be {} or more: {}",
+                MIN_ITER_COUNT, iter_count
+            );
         }
         self.iter_count = iter_count;
     }
@@ -253,30 +300,41 @@ impl JweEncrypter for Pbes2HmacAeskwJweEncrypter {
         key: &[u8],
         in_header: &JweHeader,
         out_header: &mut JweHeader,
+    ) -> Result<Option<Vec<u8>>, JoseError> {
+        self.encrypt_with_random_source(key, in_header, out_header, &OpensslRandomSource)
+    }
+
+    fn encrypt_with_random_source(
+        &self,
+        key: &[u8],
+        in_header: &JweHeader,
+        out_header: &mut JweHeader,
+        random_source: &dyn RandomSource,
     ) -> Result<Option<Vec<u8>>, JoseError> {
         (|| -> anyhow::Result<Option<Vec<u8>>> {
             let p2s = match in_header.claim("p2s") {
                 Some(Value::String(val)) => {
                     let p2s = util::decode_base64_urlsafe_no_pad(val)?;
-                    if p2s.len() < 8 {
-                        bail!("The decoded value of p2s header claim must be 8 or more.");
+                    if p2s.len() < MIN_SALT_LEN {
+                        bail!(
+                            "The decoded value of p2s header claim must be {} or more: {}",
+                            MIN_SALT_LEN,
+                            p2s.len()
+                        );
                     }
                     p2s
                 }
                 Some(_) => bail!("The p2s header claim must be string."),
                 None => {
-                    let p2s = util::random_bytes(self.salt_len);
+                    let mut p2s = vec![0; self.salt_len];
+                    random_source.fill(&mut p2s)?;
                     let p2s_b64 = util::encode_base64_urlsafe_nopad(&p2s);
                     out_header.set_claim("p2s", Some(Value::String(p2s_b64)))?;
                     p2s
                 }
             };
             let p2c = match in_header.claim("p2c") {
-                Some(Value::Number(val)) => match val.as_u64() {
-                    Some(val) => usize::try_from(val)?,
-                    None => bail!("Overflow u64 value: {}", val),
-                },
-                Some(_) => bail!("The apv header claim must be string."),
+                Some(val) => parse_iter_count(val)?,
                 None => {
                     let p2c = self.iter_count;
                     out_header.set_claim("p2c", Some(Value::Number(Number::from(p2c))))?;
@@ -284,19 +342,7 @@ impl JweEncrypter for Pbes2HmacAeskwJweEncrypter {
                 }
             };
 
-            let mut salt = Vec::with_capacity(self.algorithm().name().len() + 1 + p2s.len());
-            salt.extend_from_slice(self.algorithm().name().as_bytes());
-            salt.push(0);
-            salt.extend_from_slice(&p2s);
-
-            let md = match &self.algorithm.hash_algorithm() {
-                HashAlgorithm::Sha1 => MessageDigest::sha1(),
-                HashAlgorithm::Sha256 => MessageDigest::sha256(),
-                HashAlgorithm::Sha384 => MessageDigest::sha384(),
-                HashAlgorithm::Sha512 => MessageDigest::sha512(),
-            };
-            let mut derived_key = vec![0; self.algorithm.derived_key_len()];
-            pkcs5::pbkdf2_hmac(&self.private_key, &salt, p2c, md, &mut derived_key)?;
+            let derived_key = self.algorithm.derive_key(&self.private_key, &p2s, p2c)?;
 
             let aes = match AesKey::new_encrypt(&derived_key) {
                 Ok(val) => val,
@@ -315,7 +361,10 @@ impl JweEncrypter for Pbes2HmacAeskwJweEncrypter {
 
             Ok(Some(encrypted_key))
         })()
-        .map_err(|err| JoseError::InvalidKeyFormat(err))
+        .map_err(|err| match err.downcast::<JoseError>() {
+            Ok(err) => err,
+            Err(err) => JoseError::InvalidKeyFormat(err),
+        })
     }
 
     fn box_clone(&self) -> Box<dyn JweEncrypter> {
@@ -335,10 +384,33 @@ impl Deref for Pbes2HmacAeskwJweEncrypter {
 pub struct Pbes2HmacAeskwJweDecrypter {
     algorithm: Pbes2HmacAeskwJweAlgorithm,
     private_key: Vec<u8>,
+    max_iter_count: usize,
     key_id: Option<String>,
 }
 
 impl Pbes2HmacAeskwJweDecrypter {
+    /// Set the maximum acceptable value of the p2c header claim.
+    ///
+    /// The default value is 1000000.
+    ///
+    /// # Arguments
+    ///
+    /// * `max_iter_count` - a maximum PBKDF2 iteration count
+    pub fn set_max_iter_count(&mut self, max_iter_count: usize) {
+        if max_iter_count < MIN_ITER_COUNT {
+            panic!(
+                "max_iter_count must be {} or more: {}",
+                MIN_ITER_COUNT, max_iter_count
+            );
+        }
+        self.max_iter_count = max_iter_count;
+    }
+
+    /// Return the maximum acceptable value of the p2c header claim.
+    pub fn max_iter_count(&self) -> usize {
+        self.max_iter_count
+    }
+
     pub fn set_key_id(&mut self, value: impl Into<String>) {
         self.key_id = Some(value.into());
     }
@@ -360,10 +432,14 @@ impl JweDecrypter for Pbes2HmacAeskwJweDecrypter {
         }
     }
 
+    fn expected_encrypted_key_len(&self, cencryption: &dyn JweContentEncryption) -> Option<usize> {
+        Some(cencryption.key_len() + 8)
+    }
+
     fn decrypt(
         &self,
         encrypted_key: Option<&[u8]>,
-        _cencryption: &dyn JweContentEncryption,
+        cencryption: &dyn JweContentEncryption,
         header: &JweHeader,
     ) -> Result<Cow<[u8]>, JoseError> {
         (|| -> anyhow::Result<Cow<[u8]>> {
@@ -372,11 +448,25 @@ impl JweDecrypter for Pbes2HmacAeskwJweDecrypter {
                 None => bail!("A encrypted_key value is required."),
             };
 
+            // Checked before the key derivation not to run PBKDF2 for a broken input.
+            let expected_len = cencryption.key_len() + 8;
+            if encrypted_key.len() != expected_len {
+                bail!(
+                    "The encrypted_key size must be {}: {}",
+                    expected_len,
+                    encrypted_key.len()
+                );
+            }
+
             let p2s = match header.claim("p2s") {
                 Some(Value::String(val)) => {
                     let p2s = util::decode_base64_urlsafe_no_pad(val)?;
-                    if p2s.len() < 8 {
-                        bail!("The decoded value of p2s header claim must be 8 or more.");
+                    if p2s.len() < MIN_SALT_LEN {
+                        bail!(
+                            "The decoded value of p2s header claim must be {} or more: {}",
+                            MIN_SALT_LEN,
+                            p2s.len()
+                        );
                     }
                     p2s
                 }
@@ -384,34 +474,21 @@ impl JweDecrypter for Pbes2HmacAeskwJweDecrypter {
                 None => bail!("The p2s header claim is required."),
             };
             let p2c = match header.claim("p2c") {
-                Some(Value::Number(val)) => match val.as_u64() {
-                    Some(val) => usize::try_from(val)?,
-                    None => bail!("Overflow u64 value: {}", val),
-                },
-                Some(_) => bail!("The p2c header claim must be string."),
+                Some(val) => parse_iter_count(val)?,
                 None => bail!("The p2c header claim is required."),
             };
 
-            if p2c > 1000000 {
+            if p2c < MIN_ITER_COUNT {
+                bail!("The p2c value must be {} or more: {}", MIN_ITER_COUNT, p2c);
+            }
+            if p2c > self.max_iter_count {
                 bail!(
                     "The p2c value is too large. This is a possible DoS attack: {}",
                     p2c
                 );
             }
 
-            let mut salt = Vec::with_capacity(self.algorithm().name().len() + 1 + p2s.len());
-            salt.extend_from_slice(self.algorithm().name().as_bytes());
-            salt.push(0);
-            salt.extend_from_slice(&p2s);
-
-            let md = match &self.algorithm.hash_algorithm() {
-                HashAlgorithm::Sha1 => MessageDigest::sha1(),
-                HashAlgorithm::Sha256 => MessageDigest::sha256(),
-                HashAlgorithm::Sha384 => MessageDigest::sha384(),
-                HashAlgorithm::Sha512 => MessageDigest::sha512(),
-            };
-            let mut derived_key = vec![0; self.algorithm.derived_key_len()];
-            pkcs5::pbkdf2_hmac(&self.private_key, &salt, p2c, md, &mut derived_key)?;
+            let derived_key = self.algorithm.derive_key(&self.private_key, &p2s, p2c)?;
 
             let aes = match AesKey::new_decrypt(&derived_key) {
                 Ok(val) => val,
@@ -446,16 +523,32 @@ impl Deref for Pbes2HmacAeskwJweDecrypter {
     }
 }
 
+/// Parse the p2c header claim value.
+///
+/// A numeric string is accepted for interoperability with some producers.
+fn parse_iter_count(value: &Value) -> anyhow::Result<usize> {
+    let p2c = match value {
+        Value::Number(val) => val.as_u64(),
+        Value::String(val) => val.parse::<u64>().ok(),
+        _ => bail!("The p2c header claim must be a number."),
+    };
+    match p2c.map(u32::try_from) {
+        Some(Ok(val)) => Ok(val as usize),
+        _ => bail!("The p2c header claim must be a u32 value: {}", value),
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use anyhow::Result;
     use serde_json::json;
 
-    use super::Pbes2HmacAeskwJweAlgorithm;
+    use super::{parse_iter_count, Pbes2HmacAeskwJweAlgorithm};
     use crate::jwe::enc::aescbc_hmac::AescbcHmacJweEncryption;
     use crate::jwe::JweHeader;
     use crate::jwk::Jwk;
     use crate::util;
+    use crate::{JoseError, Value};
 
     #[test]
     fn encrypt_and_decrypt_pbes2_hmac() -> Result<()> {
@@ -532,4 +625,193 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn reject_pbes2_hmac_with_truncated_encrypted_key() -> Result<()> {
+        let enc = AescbcHmacJweEncryption::A128cbcHs256;
+
+        for alg in [
+            Pbes2HmacAeskwJweAlgorithm::Pbes2Hs256A128kw,
+            Pbes2HmacAeskwJweAlgorithm::Pbes2Hs384A192kw,
+            Pbes2HmacAeskwJweAlgorithm::Pbes2Hs512A256kw,
+        ] {
+            let mut header = JweHeader::new();
+            header.set_content_encryption(enc.name());
+
+            let key = util::random_bytes(8);
+            let encrypter = alg.encrypter_from_bytes(&key)?;
+            let mut out_header = header.clone();
+            let src_key = util::random_bytes(enc.key_len());
+            let encrypted_key = encrypter
+                .encrypt(&src_key, &header, &mut out_header)?
+                .unwrap();
+
+            // The p2s header claim is dropped to show the size is checked first.
+            out_header.set_claim("p2s", None)?;
+
+            let decrypter = alg.decrypter_from_bytes(&key)?;
+            let err = decrypter
+                .decrypt(Some(&encrypted_key[1..]), &enc, &out_header)
+                .unwrap_err();
+            assert_eq!(
+                format!("{}", err),
+                format!(
+                    "Invalid JWE format: The encrypted_key size must be {}: {}",
+                    enc.key_len() + 8,
+                    enc.key_len() + 7
+                )
+            );
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn reject_pbes2_hmac_with_out_of_range_p2c() -> Result<()> {
+        let enc = AescbcHmacJweEncryption::A128cbcHs256;
+        let alg = Pbes2HmacAeskwJweAlgorithm::Pbes2Hs256A128kw;
+
+        let mut header = JweHeader::new();
+        header.set_content_encryption(enc.name());
+
+        let mut jwk = Jwk::new("oct");
+        jwk.set_key_use("enc");
+        jwk.set_parameter(
+            "k",
+            Some(json!(util::encode_base64_urlsafe_nopad(
+                util::random_bytes(8)
+            ))),
+        )?;
+
+        let mut encrypter = alg.encrypter_from_jwk(&jwk)?;
+        encrypter.set_iter_count(2001);
+        let mut out_header = header.clone();
+        let src_key = util::random_bytes(enc.key_len());
+        let encrypted_key = encrypter.encrypt(&src_key, &header, &mut out_header)?;
+
+        let mut decrypter = alg.decrypter_from_jwk(&jwk)?;
+        assert_eq!(decrypter.max_iter_count(), 1000000);
+        decrypter.decrypt(encrypted_key.as_deref(), &enc, &out_header)?;
+
+        decrypter.set_max_iter_count(2000);
+        let err = decrypter
+            .decrypt(encrypted_key.as_deref(), &enc, &out_header)
+            .unwrap_err();
+        assert!(matches!(err, JoseError::InvalidJweFormat(_)));
+        assert_eq!(
+            format!("{}", err),
+            "Invalid JWE format: The p2c value is too large. This is a possible DoS attack: 2001"
+        );
+
+        out_header.set_claim("p2c", Some(json!(999)))?;
+        let err = decrypter
+            .decrypt(encrypted_key.as_deref(), &enc, &out_header)
+            .unwrap_err();
+        assert_eq!(
+            format!("{}", err),
+            "Invalid JWE format: The p2c value must be 1000 or more: 999"
+        );
+
+        out_header.set_claim("p2c", Some(json!(1u64 << 32)))?;
+        let err = decrypter
+            .decrypt(encrypted_key.as_deref(), &enc, &out_header)
+            .unwrap_err();
+        assert!(matches!(err, JoseError::InvalidJweFormat(_)));
+
+        Ok(())
+    }
+
+    #[test]
+    fn reject_pbes2_hmac_with_short_p2s() -> Result<()> {
+        let enc = AescbcHmacJweEncryption::A128cbcHs256;
+        let alg = Pbes2HmacAeskwJweAlgorithm::Pbes2Hs256A128kw;
+
+        let mut header = JweHeader::new();
+        header.set_content_encryption(enc.name());
+
+        let encrypter = alg.encrypter_from_bytes(b"password")?;
+        let mut out_header = header.clone();
+        let src_key = util::random_bytes(enc.key_len());
+        let encrypted_key = encrypter.encrypt(&src_key, &header, &mut out_header)?;
+        match out_header.claim("p2s") {
+            Some(Value::String(val)) => {
+                assert_eq!(util::decode_base64_urlsafe_no_pad(val)?.len(), 16)
+            }
+            _ => unreachable!(),
+        }
+
+        let decrypter = alg.decrypter_from_bytes(b"password")?;
+        out_header.set_claim(
+            "p2s",
+            Some(json!(util::encode_base64_urlsafe_nopad([0u8; 4]))),
+        )?;
+        let err = decrypter
+            .decrypt(encrypted_key.as_deref(), &enc, &out_header)
+            .unwrap_err();
+        assert_eq!(
+            format!("{}", err),
+            "Invalid JWE format: The decoded value of p2s header claim must be 8 or more: 4"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn derive_pbes2_hmac_key_rfc7517() -> Result<()> {
+        // RFC 7517 Appendix C.2 and C.4
+        let alg = Pbes2HmacAeskwJweAlgorithm::Pbes2Hs256A128kw;
+        let p2s = util::decode_base64_urlsafe_no_pad("2WCTcJZ1Rvd_CJuJripQ1w")?;
+
+        let mut expected_salt = b"PBES2-HS256+A128KW".to_vec();
+        expected_salt.push(0);
+        expected_salt.extend_from_slice(&[
+            217, 96, 147, 112, 150, 117, 70, 247, 127, 8, 155, 137, 174, 42, 80, 215,
+        ]);
+        assert_eq!(alg.salt_input(&p2s), expected_salt);
+
+        let derived_key = alg.derive_key(
+            b"Thus from my lips, by yours, my sin is purged.",
+            &p2s,
+            4096,
+        )?;
+        assert_eq!(
+            derived_key,
+            [110, 171, 169, 92, 129, 92, 109, 117, 233, 242, 116, 233, 170, 14, 24, 75]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn parse_pbes2_hmac_p2c() -> Result<()> {
+        assert_eq!(parse_iter_count(&json!(1000))?, 1000);
+        assert_eq!(parse_iter_count(&json!("1000"))?, 1000);
+        assert!(parse_iter_count(&json!("abc")).is_err());
+        assert!(parse_iter_count(&json!("-1")).is_err());
+        assert!(parse_iter_count(&json!(1.5)).is_err());
+        assert!(parse_iter_count(&json!(true)).is_err());
+
+        let enc = AescbcHmacJweEncryption::A128cbcHs256;
+        let alg = Pbes2HmacAeskwJweAlgorithm::Pbes2Hs256A128kw;
+
+        let mut header = JweHeader::new();
+        header.set_content_encryption(enc.name());
+        header.set_claim("p2c", Some(json!("1000")))?;
+
+        let encrypter = alg.encrypter_from_bytes(b"password")?;
+        let mut out_header = header.clone();
+        let src_key = util::random_bytes(enc.key_len());
+        let encrypted_key = encrypter.encrypt(&src_key, &header, &mut out_header)?;
+
+        let decrypter = alg.decrypter_from_bytes(b"password")?;
+        let dst_key = decrypter.decrypt(encrypted_key.as_deref(), &enc, &out_header)?;
+        assert_eq!(&src_key as &[u8], &dst_key as &[u8]);
+
+        out_header.set_claim("p2c", Some(json!("abc")))?;
+        assert!(decrypter
+            .decrypt(encrypted_key.as_deref(), &enc, &out_header)
+            .is_err());
+
+        Ok(())
+    }
 }
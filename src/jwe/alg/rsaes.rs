@@ -1,24 +1,31 @@
 use std::borrow::Cow;
-use std::fmt::Display;
+use std::fmt::{Debug, Display};
 use std::ops::Deref;
 
-use anyhow::bail;
-use openssl::hash::MessageDigest;
-use openssl::pkey::{PKey, Private, Public};
+use anyhow::{anyhow, bail};
+use openssl::bn::BigNum;
+use openssl::hash::{self, MessageDigest};
+use openssl::pkey::{HasPublic, PKey, Private, Public};
 use openssl::rsa::Padding;
 
-use crate::jwe::{JweAlgorithm, JweContentEncryption, JweDecrypter, JweEncrypter, JweHeader};
+use crate::jwe::{
+    JweAlgorithm, JweContentEncryption, JweDecrypter, JweEncrypter, JweHeader, JweKeyDescriptor,
+    KeyLengthError, KeyManagementMode,
+};
 use crate::jwk::{alg::rsa::RsaKeyPair, Jwk};
-use crate::util;
 use crate::util::der::{DerBuilder, DerType};
-use crate::{JoseError, Value};
+use crate::util::{self, HashAlgorithm, OpensslRandomSource, RandomSource};
+use crate::{alg_names, JoseError, Value};
+
+const MIN_EXPONENT: u32 = 65537;
 
 #[derive(Debug, Eq, PartialEq, Copy, Clone)]
+#[non_exhaustive]
 pub enum RsaesJweAlgorithm {
     /// RSAES-PKCS1-v1_5
     #[deprecated(note = "This algorithm is no longer recommended.")]
     Rsa1_5,
-    /// RSAES OAEP using default parameters
+    /// RSAES OAEP using SHA-1 and MGF1 with SHA-1
     RsaOaep,
     /// RSAES OAEP using SHA-256 and MGF1 with SHA-256
     RsaOaep256,
@@ -26,17 +33,39 @@ pub enum RsaesJweAlgorithm {
     RsaOaep384,
     /// RSAES OAEP using SHA-512 and MGF1 with SHA-512
     RsaOaep512,
+    /// RSAES OAEP using a custom pair of the hash and MGF1 hash algorithms
+    CustomOaep {
+        name: &'static str,
+        hash: HashAlgorithm,
+        mgf1_hash: HashAlgorithm,
+    },
 }
 
 impl RsaesJweAlgorithm {
+    /// Return a RSAES OAEP algorithm with a custom pair of the hash and MGF1 hash algorithms.
+    ///
+    /// This is not a registered algorithm, so the specified name is used as the alg header claim value.
+    ///
+    /// # Arguments
+    /// * `name` - A alg header claim value
+    /// * `hash` - A hash algorithm for OAEP
+    /// * `mgf1_hash` - A hash algorithm for MGF1
+    pub fn custom_oaep(name: &'static str, hash: HashAlgorithm, mgf1_hash: HashAlgorithm) -> Self {
+        Self::CustomOaep {
+            name,
+            hash,
+            mgf1_hash,
+        }
+    }
+
     /// Generate RSA key pair.
     ///
     /// # Arguments
     /// * `bits` - RSA key length
     pub fn generate_key_pair(&self, bits: u32) -> Result<RsaKeyPair, JoseError> {
         (|| -> anyhow::Result<RsaKeyPair> {
-            if bits < 2048 {
-                bail!("key length must be 2048 or more.");
+            if bits < util::rsa::MIN_BITS {
+                bail!("key length must be {} or more.", util::rsa::MIN_BITS);
             }
 
             let mut key_pair = RsaKeyPair::generate(bits)?;
@@ -57,9 +86,7 @@ impl RsaesJweAlgorithm {
         (|| -> anyhow::Result<RsaKeyPair> {
             let mut key_pair = RsaKeyPair::from_der(input)?;
 
-            if key_pair.key_len() * 8 < 2048 {
-                bail!("key length must be 2048 or more.");
-            }
+            util::rsa::check_min_bits(key_pair.private_key(), util::rsa::MIN_BITS)?;
 
             key_pair.set_algorithm(Some(self.name()));
             Ok(key_pair)
@@ -84,9 +111,7 @@ impl RsaesJweAlgorithm {
         (|| -> anyhow::Result<RsaKeyPair> {
             let mut key_pair = RsaKeyPair::from_pem(input.as_ref())?;
 
-            if key_pair.key_len() * 8 < 2048 {
-                bail!("key length must be 2048 or more.");
-            }
+            util::rsa::check_min_bits(key_pair.private_key(), util::rsa::MIN_BITS)?;
 
             key_pair.set_algorithm(Some(self.name()));
             Ok(key_pair)
@@ -100,28 +125,53 @@ impl RsaesJweAlgorithm {
     pub fn encrypter_from_der(
         &self,
         input: impl AsRef<[u8]>,
+    ) -> Result<RsaesJweEncrypter, JoseError> {
+        self.encrypter_from_der_with(input.as_ref(), false)
+    }
+
+    /// Return a encrypter from a public key that is a DER encoded SubjectPublicKeyInfo
+    /// or PKCS#1 RSAPublicKey, allowing a public exponent less than 65537.
+    ///
+    /// # Arguments
+    /// * `input` - A public key that is a DER encoded SubjectPublicKeyInfo or PKCS#1 RSAPublicKey.
+    pub fn encrypter_from_der_allowing_small_exponent(
+        &self,
+        input: impl AsRef<[u8]>,
+    ) -> Result<RsaesJweEncrypter, JoseError> {
+        self.encrypter_from_der_with(input.as_ref(), true)
+    }
+
+    fn encrypter_from_der_with(
+        &self,
+        input: &[u8],
+        allow_small_exponent: bool,
     ) -> Result<RsaesJweEncrypter, JoseError> {
         (|| -> anyhow::Result<RsaesJweEncrypter> {
             let spki_der_vec;
-            let spki_der = match RsaKeyPair::detect_pkcs8(input.as_ref(), true) {
-                Some(_) => input.as_ref(),
+            let spki_der = match RsaKeyPair::detect_pkcs8(input, true) {
+                Some(_) => input,
                 None => {
-                    spki_der_vec = RsaKeyPair::to_pkcs8(input.as_ref(), true);
+                    spki_der_vec = RsaKeyPair::to_pkcs8(input, true);
                     spki_der_vec.as_slice()
                 }
             };
 
             let public_key = PKey::public_key_from_der(spki_der)?;
 
-            let rsa = public_key.rsa()?;
-            if rsa.size() * 8 < 2048 {
-                bail!("key length must be 2048 or more.");
+            let rsa_size = public_key.rsa()?.size() as usize;
+            util::rsa::check_min_bits(&public_key, util::rsa::MIN_BITS)?;
+            if !allow_small_exponent {
+                Self::check_public_exponent(&public_key)?;
             }
 
             Ok(RsaesJweEncrypter {
                 algorithm: self.clone(),
                 public_key,
+                rsa_size,
                 key_id: None,
+                allow_small_exponent,
+                mgf1_hash: None,
+                oaep_label: None,
             })
         })()
         .map_err(|err| JoseError::InvalidKeyFormat(err))
@@ -130,9 +180,29 @@ impl RsaesJweAlgorithm {
     pub fn encrypter_from_pem(
         &self,
         input: impl AsRef<[u8]>,
+    ) -> Result<RsaesJweEncrypter, JoseError> {
+        self.encrypter_from_pem_with(input.as_ref(), false)
+    }
+
+    /// Return a encrypter from a public key of common or traditinal PEM format,
+    /// allowing a public exponent less than 65537.
+    ///
+    /// # Arguments
+    /// * `input` - A public key of common or traditinal PEM format.
+    pub fn encrypter_from_pem_allowing_small_exponent(
+        &self,
+        input: impl AsRef<[u8]>,
+    ) -> Result<RsaesJweEncrypter, JoseError> {
+        self.encrypter_from_pem_with(input.as_ref(), true)
+    }
+
+    fn encrypter_from_pem_with(
+        &self,
+        input: &[u8],
+        allow_small_exponent: bool,
     ) -> Result<RsaesJweEncrypter, JoseError> {
         (|| -> anyhow::Result<RsaesJweEncrypter> {
-            let (alg, data) = util::parse_pem(input.as_ref())?;
+            let (alg, data) = util::parse_pem(input)?;
 
             let public_key = match alg.as_str() {
                 "PUBLIC KEY" => match RsaKeyPair::detect_pkcs8(&data, true) {
@@ -146,26 +216,47 @@ impl RsaesJweAlgorithm {
                 alg => bail!("Inappropriate algorithm: {}", alg),
             };
 
-            let rsa = public_key.rsa()?;
-            if rsa.size() * 8 < 2048 {
-                bail!("key length must be 2048 or more.");
+            let rsa_size = public_key.rsa()?.size() as usize;
+            util::rsa::check_min_bits(&public_key, util::rsa::MIN_BITS)?;
+            if !allow_small_exponent {
+                Self::check_public_exponent(&public_key)?;
             }
 
             Ok(RsaesJweEncrypter {
                 algorithm: self.clone(),
                 public_key,
+                rsa_size,
                 key_id: None,
+                allow_small_exponent,
+                mgf1_hash: None,
+                oaep_label: None,
             })
         })()
         .map_err(|err| JoseError::InvalidKeyFormat(err))
     }
 
     pub fn encrypter_from_jwk(&self, jwk: &Jwk) -> Result<RsaesJweEncrypter, JoseError> {
+        self.encrypter_from_jwk_with(jwk, false)
+    }
+
+    /// Return a encrypter from a JWK of RSA type, allowing a public exponent less than 65537.
+    ///
+    /// # Arguments
+    /// * `jwk` - A JWK of RSA type.
+    pub fn encrypter_from_jwk_allowing_small_exponent(
+        &self,
+        jwk: &Jwk,
+    ) -> Result<RsaesJweEncrypter, JoseError> {
+        self.encrypter_from_jwk_with(jwk, true)
+    }
+
+    fn encrypter_from_jwk_with(
+        &self,
+        jwk: &Jwk,
+        allow_small_exponent: bool,
+    ) -> Result<RsaesJweEncrypter, JoseError> {
+        Self::check_key_type(jwk)?;
         (|| -> anyhow::Result<RsaesJweEncrypter> {
-            match jwk.key_type() {
-                val if val == "RSA" => {}
-                val => bail!("A parameter kty must be RSA: {}", val),
-            }
             match jwk.key_use() {
                 Some(val) if val == "enc" => {}
                 None => {}
@@ -180,14 +271,12 @@ impl RsaesJweAlgorithm {
                 Some(val) => bail!("A parameter alg must be {} but {}", self.name(), val),
             }
 
-            let n = match jwk.parameter("n") {
-                Some(Value::String(val)) => util::decode_base64_urlsafe_no_pad(val)?,
-                Some(_) => bail!("A parameter n must be a string."),
+            let n = match jwk.parameter_bytes("n")? {
+                Some(val) => val,
                 None => bail!("A parameter n is required."),
             };
-            let e = match jwk.parameter("e") {
-                Some(Value::String(val)) => util::decode_base64_urlsafe_no_pad(val)?,
-                Some(_) => bail!("A parameter e must be a string."),
+            let e = match jwk.parameter_bytes("e")? {
+                Some(val) => val,
                 None => bail!("A parameter e is required."),
             };
 
@@ -202,9 +291,10 @@ impl RsaesJweAlgorithm {
             let pkcs8 = RsaKeyPair::to_pkcs8(&builder.build(), true);
             let public_key = PKey::public_key_from_der(&pkcs8)?;
 
-            let rsa = public_key.rsa()?;
-            if rsa.size() * 8 < 2048 {
-                bail!("key length must be 2048 or more.");
+            let rsa_size = public_key.rsa()?.size() as usize;
+            util::rsa::check_min_bits(&public_key, util::rsa::MIN_BITS)?;
+            if !allow_small_exponent {
+                Self::check_public_exponent(&public_key)?;
             }
 
             let key_id = jwk.key_id().map(|val| val.to_string());
@@ -212,7 +302,11 @@ impl RsaesJweAlgorithm {
             Ok(RsaesJweEncrypter {
                 algorithm: self.clone(),
                 public_key,
+                rsa_size,
                 key_id,
+                allow_small_exponent,
+                mgf1_hash: None,
+                oaep_label: None,
             })
         })()
         .map_err(|err| JoseError::InvalidKeyFormat(err))
@@ -227,6 +321,7 @@ impl RsaesJweAlgorithm {
             algorithm: self.clone(),
             private_key: key_pair.into_private_key(),
             key_id: None,
+            oaep_label: None,
         })
     }
 
@@ -239,10 +334,29 @@ impl RsaesJweAlgorithm {
             algorithm: self.clone(),
             private_key: key_pair.into_private_key(),
             key_id: None,
+            oaep_label: None,
         })
     }
 
+    /// Return a decrypter from a private key in a DER encoded PKCS#12 bundle.
+    ///
+    /// # Arguments
+    /// * `input` - A DER encoded PKCS#12 bundle.
+    /// * `password` - The password of the PKCS#12 bundle.
+    pub fn decrypter_from_pkcs12(
+        &self,
+        input: impl AsRef<[u8]>,
+        password: &str,
+    ) -> Result<RsaesJweDecrypter, JoseError> {
+        let (private_key, _) = util::parse_pkcs12(input.as_ref(), password)?;
+        let der = private_key
+            .private_key_to_pkcs8()
+            .map_err(|err| JoseError::InvalidKeyFormat(err.into()))?;
+        self.decrypter_from_der(der)
+    }
+
     pub fn decrypter_from_jwk(&self, jwk: &Jwk) -> Result<RsaesJweDecrypter, JoseError> {
+        Self::check_key_type(jwk)?;
         (|| -> anyhow::Result<RsaesJweDecrypter> {
             match jwk.key_use() {
                 Some(val) if val == "enc" => {}
@@ -259,9 +373,7 @@ impl RsaesJweAlgorithm {
             }
 
             let key_pair = RsaKeyPair::from_jwk(&jwk)?;
-            if key_pair.key_len() * 8 < 2048 {
-                bail!("key length must be 2048 or more.");
-            }
+            util::rsa::check_min_bits(key_pair.private_key(), util::rsa::MIN_BITS)?;
 
             let private_key = key_pair.into_private_key();
             let key_id = jwk.key_id().map(|val| val.to_string());
@@ -270,24 +382,52 @@ impl RsaesJweAlgorithm {
                 algorithm: self.clone(),
                 private_key,
                 key_id,
+                oaep_label: None,
             })
         })()
         .map_err(|err| JoseError::InvalidKeyFormat(err))
     }
+
+    fn check_public_exponent(public_key: &PKey<Public>) -> anyhow::Result<()> {
+        let rsa = public_key.rsa()?;
+        if rsa.e() < BigNum::from_u32(MIN_EXPONENT)?.as_ref() {
+            bail!(
+                "The public exponent must be {} or more: {}",
+                MIN_EXPONENT,
+                rsa.e()
+            );
+        }
+        Ok(())
+    }
+
+    fn check_key_type(jwk: &Jwk) -> Result<(), JoseError> {
+        match jwk.key_type() {
+            "RSA" => Ok(()),
+            val => Err(JoseError::InvalidKeyType {
+                expected: "RSA".to_string(),
+                actual: val.to_string(),
+            }),
+        }
+    }
 }
 
 impl JweAlgorithm for RsaesJweAlgorithm {
     #[allow(deprecated)]
     fn name(&self) -> &str {
         match self {
-            Self::Rsa1_5 => "RSA1_5",
-            Self::RsaOaep => "RSA-OAEP",
-            Self::RsaOaep256 => "RSA-OAEP-256",
-            Self::RsaOaep384 => "RSA-OAEP-384",
-            Self::RsaOaep512 => "RSA-OAEP-512",
+            Self::Rsa1_5 => alg_names::RSA1_5,
+            Self::RsaOaep => alg_names::RSA_OAEP,
+            Self::RsaOaep256 => alg_names::RSA_OAEP_256,
+            Self::RsaOaep384 => alg_names::RSA_OAEP_384,
+            Self::RsaOaep512 => alg_names::RSA_OAEP_512,
+            Self::CustomOaep { name, .. } => name,
         }
     }
 
+    fn key_management_mode(&self) -> KeyManagementMode {
+        KeyManagementMode::KeyEncryption
+    }
+
     fn box_clone(&self) -> Box<dyn JweAlgorithm> {
         Box::new(self.clone())
     }
@@ -307,11 +447,15 @@ impl Deref for RsaesJweAlgorithm {
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Clone)]
 pub struct RsaesJweEncrypter {
     algorithm: RsaesJweAlgorithm,
     public_key: PKey<Public>,
+    rsa_size: usize,
     key_id: Option<String>,
+    allow_small_exponent: bool,
+    mgf1_hash: Option<HashAlgorithm>,
+    oaep_label: Option<Vec<u8>>,
 }
 
 impl RsaesJweEncrypter {
@@ -322,6 +466,116 @@ impl RsaesJweEncrypter {
     pub fn remove_key_id(&mut self) {
         self.key_id = None;
     }
+
+    /// Test whether this encrypter was created by a constructor allowing a public exponent
+    /// less than 65537.
+    pub fn is_small_exponent_allowed(&self) -> bool {
+        self.allow_small_exponent
+    }
+
+    /// Return a encrypter that uses the hash algorithm for MGF1 instead of the one of the algorithm.
+    ///
+    /// Some implementations use MGF1 with SHA-1 even for RSA-OAEP-256,
+    /// so this is only for interoperating with them. RSA1_5 does not use MGF1, so this fails for it.
+    ///
+    /// # Arguments
+    ///
+    /// * `mgf1_hash` - A hash algorithm for MGF1
+    #[allow(deprecated)]
+    pub fn with_mgf1_hash(mut self, mgf1_hash: HashAlgorithm) -> Result<Self, JoseError> {
+        if let RsaesJweAlgorithm::Rsa1_5 = self.algorithm {
+            return Err(JoseError::InvalidKeyFormat(anyhow!(
+                "{} does not use MGF1.",
+                self.algorithm.name()
+            )));
+        }
+        self.mgf1_hash = Some(mgf1_hash);
+        Ok(self)
+    }
+
+    /// Return the hash algorithm for MGF1 if it is set by with_mgf1_hash.
+    pub fn mgf1_hash(&self) -> Option<HashAlgorithm> {
+        self.mgf1_hash
+    }
+
+    /// Return a encrypter that uses the label (pSource) of RSA-OAEP.
+    ///
+    /// The JWA algorithms use an empty label, and the decrypter must use the same label.
+    /// RSA1_5 does not use a label, so this fails for it.
+    ///
+    /// # Arguments
+    ///
+    /// * `label` - A label of RSA-OAEP
+    #[allow(deprecated)]
+    pub fn with_oaep_label(mut self, label: impl Into<Vec<u8>>) -> Result<Self, JoseError> {
+        if let RsaesJweAlgorithm::Rsa1_5 = self.algorithm {
+            return Err(JoseError::InvalidKeyFormat(anyhow!(
+                "{} does not use a OAEP label.",
+                self.algorithm.name()
+            )));
+        }
+        self.oaep_label = Some(label.into());
+        Ok(self)
+    }
+
+    /// Return the label of RSA-OAEP if it is set by with_oaep_label.
+    pub fn oaep_label(&self) -> Option<&[u8]> {
+        self.oaep_label.as_deref()
+    }
+
+    /// Return the public key that this encrypter encrypts to as a JWK of RSA type.
+    ///
+    /// The JWK has the n, e, alg and kid (if set) parameters.
+    pub fn to_jwk(&self) -> Result<Jwk, JoseError> {
+        to_public_jwk(&self.public_key, &self.algorithm, self.key_id.as_deref())
+    }
+
+    /// Return encrypted keys for multiple content encryption keys.
+    ///
+    /// The RSA context is set up once and reused for all keys.
+    ///
+    /// # Arguments
+    ///
+    /// * `keys` - content encryption keys
+    #[allow(deprecated)]
+    pub fn encrypt_many(&self, keys: &[&[u8]]) -> Result<Vec<Vec<u8>>, JoseError> {
+        (|| -> anyhow::Result<Vec<Vec<u8>>> {
+            let (hash, mgf1_hash) = match self.algorithm {
+                RsaesJweAlgorithm::Rsa1_5 => {
+                    let rsa = self.public_key.rsa()?;
+                    let mut encrypted_keys = Vec::with_capacity(keys.len());
+                    for key in keys {
+                        let mut encrypted_key = vec![0; self.rsa_size];
+                        let len = rsa.public_encrypt(key, &mut encrypted_key, Padding::PKCS1)?;
+                        encrypted_key.truncate(len);
+                        encrypted_keys.push(encrypted_key);
+                    }
+                    return Ok(encrypted_keys);
+                }
+                RsaesJweAlgorithm::RsaOaep => (MessageDigest::sha1(), MessageDigest::sha1()),
+                RsaesJweAlgorithm::RsaOaep256 => (MessageDigest::sha256(), MessageDigest::sha256()),
+                RsaesJweAlgorithm::RsaOaep384 => (MessageDigest::sha384(), MessageDigest::sha384()),
+                RsaesJweAlgorithm::RsaOaep512 => (MessageDigest::sha512(), MessageDigest::sha512()),
+                RsaesJweAlgorithm::CustomOaep {
+                    hash, mgf1_hash, ..
+                } => (hash.message_digest()?, mgf1_hash.message_digest()?),
+            };
+            let mgf1_hash = match &self.mgf1_hash {
+                Some(val) => val.message_digest()?,
+                None => mgf1_hash,
+            };
+
+            let encrypted_keys = openssl_rsa_oaep::pkey_public_encrypt_many(
+                &self.public_key,
+                keys,
+                hash,
+                mgf1_hash,
+                self.oaep_label.as_deref(),
+            )?;
+            Ok(encrypted_keys)
+        })()
+        .map_err(JoseError::InvalidKeyFormat)
+    }
 }
 
 impl JweEncrypter for RsaesJweEncrypter {
@@ -345,48 +599,14 @@ impl JweEncrypter for RsaesJweEncrypter {
         Ok(None)
     }
 
-    #[allow(deprecated)]
     fn encrypt(
         &self,
         key: &[u8],
         _in_header: &JweHeader,
         _out_header: &mut JweHeader,
     ) -> Result<Option<Vec<u8>>, JoseError> {
-        (|| -> anyhow::Result<Option<Vec<u8>>> {
-            let rsa = self.public_key.rsa()?;
-            let encrypted_key = match self.algorithm {
-                RsaesJweAlgorithm::Rsa1_5 => {
-                    let mut encrypted_key = vec![0; rsa.size() as usize];
-                    let len = rsa.public_encrypt(&key, &mut encrypted_key, Padding::PKCS1)?;
-                    encrypted_key.truncate(len);
-                    encrypted_key
-                }
-                RsaesJweAlgorithm::RsaOaep => {
-                    let mut encrypted_key = vec![0; rsa.size() as usize];
-                    let len = rsa.public_encrypt(&key, &mut encrypted_key, Padding::PKCS1_OAEP)?;
-                    encrypted_key.truncate(len);
-                    encrypted_key
-                }
-                RsaesJweAlgorithm::RsaOaep256 => openssl_rsa_oaep::pkey_public_encrypt(
-                    &self.public_key,
-                    &key,
-                    MessageDigest::sha256(),
-                )?,
-                RsaesJweAlgorithm::RsaOaep384 => openssl_rsa_oaep::pkey_public_encrypt(
-                    &self.public_key,
-                    &key,
-                    MessageDigest::sha384(),
-                )?,
-                RsaesJweAlgorithm::RsaOaep512 => openssl_rsa_oaep::pkey_public_encrypt(
-                    &self.public_key,
-                    &key,
-                    MessageDigest::sha512(),
-                )?,
-            };
-
-            Ok(Some(encrypted_key))
-        })()
-        .map_err(|err| JoseError::InvalidKeyFormat(err))
+        let mut encrypted_keys = self.encrypt_many(&[key])?;
+        Ok(encrypted_keys.pop())
     }
 
     fn box_clone(&self) -> Box<dyn JweEncrypter> {
@@ -394,6 +614,28 @@ impl JweEncrypter for RsaesJweEncrypter {
     }
 }
 
+impl Debug for RsaesJweEncrypter {
+    fn fmt(&self, fmt: &mut std::fmt::Formatter<'_>) -> Result<(), std::fmt::Error> {
+        // Print a SHA-256 fingerprint of the SubjectPublicKeyInfo instead of the modulus.
+        let fingerprint = self
+            .public_key
+            .public_key_to_der()
+            .and_then(|der| hash::hash(MessageDigest::sha256(), &der))
+            .map(util::encode_base64_urlsafe_nopad)
+            .ok();
+
+        fmt.debug_struct("RsaesJweEncrypter")
+            .field("algorithm", &self.algorithm)
+            .field("public_key_sha256", &fingerprint)
+            .field("rsa_size", &self.rsa_size)
+            .field("key_id", &self.key_id)
+            .field("allow_small_exponent", &self.allow_small_exponent)
+            .field("mgf1_hash", &self.mgf1_hash)
+            .field("oaep_label", &self.oaep_label)
+            .finish()
+    }
+}
+
 impl Deref for RsaesJweEncrypter {
     type Target = dyn JweEncrypter;
 
@@ -402,11 +644,12 @@ impl Deref for RsaesJweEncrypter {
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Clone)]
 pub struct RsaesJweDecrypter {
     algorithm: RsaesJweAlgorithm,
     private_key: PKey<Private>,
     key_id: Option<String>,
+    oaep_label: Option<Vec<u8>>,
 }
 
 impl RsaesJweDecrypter {
@@ -417,6 +660,70 @@ impl RsaesJweDecrypter {
     pub fn remove_key_id(&mut self) {
         self.key_id = None;
     }
+
+    /// Return a decrypter that uses the label (pSource) of RSA-OAEP.
+    ///
+    /// The label must be identical to the one of the encrypter.
+    /// RSA1_5 does not use a label, so this fails for it.
+    ///
+    /// # Arguments
+    ///
+    /// * `label` - A label of RSA-OAEP
+    #[allow(deprecated)]
+    pub fn with_oaep_label(mut self, label: impl Into<Vec<u8>>) -> Result<Self, JoseError> {
+        if let RsaesJweAlgorithm::Rsa1_5 = self.algorithm {
+            return Err(JoseError::InvalidKeyFormat(anyhow!(
+                "{} does not use a OAEP label.",
+                self.algorithm.name()
+            )));
+        }
+        self.oaep_label = Some(label.into());
+        Ok(self)
+    }
+
+    /// Return the label of RSA-OAEP if it is set by with_oaep_label.
+    pub fn oaep_label(&self) -> Option<&[u8]> {
+        self.oaep_label.as_deref()
+    }
+
+    /// Return the algorithm and key ID of this decrypter without the private key.
+    pub fn descriptor(&self) -> JweKeyDescriptor {
+        JweKeyDescriptor::new(self.algorithm.name(), self.key_id.as_deref())
+    }
+
+    /// Return the public key of this decrypter as a JWK of RSA type for publishing to senders.
+    ///
+    /// The JWK has the n, e, use, alg and kid (if set) parameters, but no private components.
+    pub fn to_public_jwk(&self) -> Result<Jwk, JoseError> {
+        let mut jwk = to_public_jwk(&self.private_key, &self.algorithm, self.key_id.as_deref())?;
+        jwk.set_key_use("enc");
+        Ok(jwk)
+    }
+}
+
+fn to_public_jwk<T: HasPublic>(
+    key: &PKey<T>,
+    algorithm: &RsaesJweAlgorithm,
+    key_id: Option<&str>,
+) -> Result<Jwk, JoseError> {
+    (|| -> anyhow::Result<Jwk> {
+        let rsa = key.rsa()?;
+
+        let mut jwk = Jwk::new("RSA");
+        jwk.set_algorithm(algorithm.name());
+        if let Some(val) = key_id {
+            jwk.set_key_id(val);
+        }
+        let n = util::encode_base64_urlsafe_nopad(rsa.n().to_vec());
+        jwk.set_parameter("n", Some(Value::String(n)))?;
+        let e = util::encode_base64_urlsafe_nopad(rsa.e().to_vec());
+        jwk.set_parameter("e", Some(Value::String(e)))?;
+        Ok(jwk)
+    })()
+    .map_err(|err| match err.downcast::<JoseError>() {
+        Ok(err) => err,
+        Err(err) => JoseError::InvalidKeyFormat(err),
+    })
 }
 
 impl JweDecrypter for RsaesJweDecrypter {
@@ -431,13 +738,27 @@ impl JweDecrypter for RsaesJweDecrypter {
         }
     }
 
-    #[allow(deprecated)]
+    fn expected_encrypted_key_len(&self, _cencryption: &dyn JweContentEncryption) -> Option<usize> {
+        Some(self.private_key.size())
+    }
+
     fn decrypt(
         &self,
         encrypted_key: Option<&[u8]>,
-        _cencryption: &dyn JweContentEncryption,
-        _header: &JweHeader,
+        cencryption: &dyn JweContentEncryption,
+        header: &JweHeader,
     ) -> Result<Cow<[u8]>, JoseError> {
+        self.decrypt_with_random_source(encrypted_key, cencryption, header, &OpensslRandomSource)
+    }
+
+    #[allow(deprecated)]
+    fn decrypt_with_random_source(
+        &self,
+        encrypted_key: Option<&[u8]>,
+        cencryption: &dyn JweContentEncryption,
+        _header: &JweHeader,
+        random_source: &dyn RandomSource,
+    ) -> Result<Cow<'_, [u8]>, JoseError> {
         (|| -> anyhow::Result<Cow<[u8]>> {
             let encrypted_key = match encrypted_key {
                 Some(val) => val,
@@ -445,39 +766,64 @@ impl JweDecrypter for RsaesJweDecrypter {
             };
 
             let rsa = self.private_key.rsa()?;
-            let key = match self.algorithm {
+            if encrypted_key.len() != rsa.size() as usize {
+                bail!(
+                    "The encrypted_key size must be {}: {}",
+                    rsa.size(),
+                    encrypted_key.len()
+                );
+            }
+
+            let (hash, mgf1_hash) = match self.algorithm {
                 RsaesJweAlgorithm::Rsa1_5 => {
+                    // RFC 7516 section 11.5: a padding error or a wrong key length is replaced
+                    // with a random key, so that it is reported as the same tag mismatch.
+                    let mut random_key = vec![0; cencryption.key_len()];
+                    random_source.fill(&mut random_key)?;
                     let mut key = vec![0; rsa.size() as usize];
-                    let len = rsa.private_decrypt(&encrypted_key, &mut key, Padding::PKCS1)?;
-                    key.truncate(len);
-                    key
-                }
-                RsaesJweAlgorithm::RsaOaep => {
-                    let mut key = vec![0; rsa.size() as usize];
-                    let len = rsa.private_decrypt(&encrypted_key, &mut key, Padding::PKCS1_OAEP)?;
-                    key.truncate(len);
-                    key
+                    let key = match rsa.private_decrypt(encrypted_key, &mut key, Padding::PKCS1) {
+                        Ok(len) if len == cencryption.key_len() => {
+                            key.truncate(len);
+                            key
+                        }
+                        _ => random_key,
+                    };
+                    return Ok(Cow::Owned(key));
                 }
-                RsaesJweAlgorithm::RsaOaep256 => openssl_rsa_oaep::pkey_private_decrypt(
-                    &self.private_key,
-                    &encrypted_key,
-                    MessageDigest::sha256(),
-                )?,
-                RsaesJweAlgorithm::RsaOaep384 => openssl_rsa_oaep::pkey_private_decrypt(
-                    &self.private_key,
-                    &encrypted_key,
-                    MessageDigest::sha384(),
-                )?,
-                RsaesJweAlgorithm::RsaOaep512 => openssl_rsa_oaep::pkey_private_decrypt(
-                    &self.private_key,
-                    &encrypted_key,
-                    MessageDigest::sha512(),
-                )?,
+                RsaesJweAlgorithm::RsaOaep => (MessageDigest::sha1(), MessageDigest::sha1()),
+                RsaesJweAlgorithm::RsaOaep256 => (MessageDigest::sha256(), MessageDigest::sha256()),
+                RsaesJweAlgorithm::RsaOaep384 => (MessageDigest::sha384(), MessageDigest::sha384()),
+                RsaesJweAlgorithm::RsaOaep512 => (MessageDigest::sha512(), MessageDigest::sha512()),
+                RsaesJweAlgorithm::CustomOaep {
+                    hash, mgf1_hash, ..
+                } => (hash.message_digest()?, mgf1_hash.message_digest()?),
+            };
+
+            let key = match openssl_rsa_oaep::pkey_private_decrypt(
+                &self.private_key,
+                encrypted_key,
+                hash,
+                mgf1_hash,
+                self.oaep_label.as_deref(),
+            ) {
+                Ok(val) => val,
+                Err(err) if self.oaep_label.is_some() => bail!(
+                    "The encrypted_key cannot be decrypted, or the OAEP label is mismatched: {}",
+                    err
+                ),
+                Err(err) => bail!(err),
             };
 
+            if key.len() != cencryption.key_len() {
+                bail!(KeyLengthError::new(cencryption.key_len(), key.len()));
+            }
+
             Ok(Cow::Owned(key))
         })()
-        .map_err(|err| JoseError::InvalidJweFormat(err))
+        .map_err(|err| match err.downcast::<JoseError>() {
+            Ok(err) => err,
+            Err(err) => JoseError::InvalidJweFormat(err),
+        })
     }
 
     fn box_clone(&self) -> Box<dyn JweDecrypter> {
@@ -485,6 +831,17 @@ impl JweDecrypter for RsaesJweDecrypter {
     }
 }
 
+impl Debug for RsaesJweDecrypter {
+    fn fmt(&self, fmt: &mut std::fmt::Formatter<'_>) -> Result<(), std::fmt::Error> {
+        fmt.debug_struct("RsaesJweDecrypter")
+            .field("algorithm", &self.algorithm)
+            .field("private_key", &"[REDACTED]")
+            .field("key_id", &self.key_id)
+            .field("oaep_label", &self.oaep_label)
+            .finish()
+    }
+}
+
 impl Deref for RsaesJweDecrypter {
     type Target = dyn JweDecrypter;
 
@@ -496,14 +853,17 @@ impl Deref for RsaesJweDecrypter {
 #[cfg(test)]
 mod tests {
     use anyhow::Result;
+    use openssl::bn::BigNum;
     use std::fs;
     use std::path::PathBuf;
+    use std::sync::Mutex;
 
     use super::RsaesJweAlgorithm;
     use crate::jwe::enc::aescbc_hmac::AescbcHmacJweEncryption;
-    use crate::jwe::JweHeader;
-    use crate::jwk::Jwk;
-    use crate::util;
+    use crate::jwe::{self, JweContext, JweHeader, KeyLengthError};
+    use crate::jwk::{Jwk, KeyPair};
+    use crate::util::{self, HashAlgorithm, RandomSource};
+    use crate::{alg_names, JoseError, Value};
 
     #[test]
     #[allow(deprecated)]
@@ -540,6 +900,676 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn decrypt_rsa_oaep_with_sha1_ciphertext() -> Result<()> {
+        // Encrypted by pyca/cryptography with OAEP and MGF1 using SHA-1.
+        let encrypted_key = util::decode_base64_urlsafe_no_pad(concat!(
+            "vLEbPRCK43M8JGApn7IbKvj-9rNrLTd0CmnAZBPdmed4n3MCzu5DSJRsn61ul_oQwbwSVtitdpwJXrRMlgnh_ND1",
+            "qgDrL08ocqa_AorXBqGf3jW-G0L9Vt4SfNzsdjAgSfPyTO7_TmwlpS4XyUQXnZKPkZYjYmFmRz0_veepm_6MAvMN",
+            "SMPz8znwmSU2Jl5Jucr_pYxxOLFGww2iZpZg6APX07Zz8E7Zqf5kBn718J5uMj2KL2JgZE_wxmOuQ0-YRI_6SjK7",
+            "plqtP7bNgxiihJkl_ykuVySJGHcrPi5hCDXtg4Vnf0oGCQ7pS_plYKF19ig3BktVYOVPzS2kOE-74w",
+        ))?;
+        let enc = AescbcHmacJweEncryption::A128cbcHs256;
+        let mut header = JweHeader::new();
+        header.set_content_encryption(enc.name());
+
+        let private_key = load_file("pem/RSA_2048bit_private.pem")?;
+        let decrypter = RsaesJweAlgorithm::RsaOaep.decrypter_from_pem(&private_key)?;
+        let key = decrypter.decrypt(Some(&encrypted_key), &enc, &header)?;
+        assert_eq!(&key as &[u8], b"0123456789abcdef0123456789abcdef");
+
+        let decrypter = RsaesJweAlgorithm::RsaOaep256.decrypter_from_pem(&private_key)?;
+        assert!(decrypter
+            .decrypt(Some(&encrypted_key), &enc, &header)
+            .is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn encrypt_and_decrypt_rsaes_with_large_exponent() -> Result<()> {
+        let enc = AescbcHmacJweEncryption::A128cbcHs256;
+
+        // 0xFFFFFFFB is a 4-byte prime whose top bit is set.
+        let exponent = BigNum::from_u32(0xFFFFFFFB)?;
+        let rsa = openssl::rsa::Rsa::generate_with_e(2048, &exponent)?;
+        let n = rsa.n().to_vec();
+        let e = rsa.e().to_vec();
+        assert_eq!(e.len(), 4);
+
+        let mut padded_e = vec![0u8];
+        padded_e.extend_from_slice(&e);
+
+        for e in [e, padded_e] {
+            let mut public_key = Jwk::new("RSA");
+            public_key.set_parameter(
+                "n",
+                Some(Value::String(util::encode_base64_urlsafe_nopad(&n))),
+            )?;
+            public_key.set_parameter(
+                "e",
+                Some(Value::String(util::encode_base64_urlsafe_nopad(&e))),
+            )?;
+
+            let alg = RsaesJweAlgorithm::RsaOaep256;
+            let mut header = JweHeader::new();
+            header.set_content_encryption(enc.name());
+
+            let encrypter = alg.encrypter_from_jwk(&public_key)?;
+            let mut out_header = header.clone();
+            let src_key = util::random_bytes(enc.key_len());
+            let encrypted_key = encrypter.encrypt(&src_key, &header, &mut out_header)?;
+
+            let decrypter = alg.decrypter_from_der(rsa.private_key_to_der()?)?;
+            let dst_key = decrypter.decrypt(encrypted_key.as_deref(), &enc, &out_header)?;
+
+            assert_eq!(&src_key as &[u8], &dst_key as &[u8]);
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    #[allow(deprecated)]
+    fn reject_rsaes_with_wrong_key_len() -> Result<()> {
+        let enc = AescbcHmacJweEncryption::A128cbcHs256;
+        let public_key = load_file("pem/RSA_2048bit_public.pem")?;
+        let private_key = load_file("pem/RSA_2048bit_private.pem")?;
+
+        let mut header = JweHeader::new();
+        header.set_content_encryption(enc.name());
+        let src_key = util::random_bytes(enc.key_len() - 1);
+
+        let alg = RsaesJweAlgorithm::RsaOaep;
+        let encrypter = alg.encrypter_from_pem(&public_key)?;
+        let mut out_header = header.clone();
+        let encrypted_key = encrypter.encrypt(&src_key, &header, &mut out_header)?;
+
+        let decrypter = alg.decrypter_from_pem(&private_key)?;
+        match decrypter.decrypt(encrypted_key.as_deref(), &enc, &out_header) {
+            Err(JoseError::InvalidJweFormat(err)) => {
+                let err = err.downcast_ref::<KeyLengthError>().unwrap();
+                assert_eq!(err.expected(), enc.key_len());
+                assert_eq!(err.actual(), enc.key_len() - 1);
+            }
+            other => panic!("A key of wrong length was accepted: {:?}", other),
+        }
+
+        let alg = RsaesJweAlgorithm::Rsa1_5;
+        let encrypter = alg.encrypter_from_pem(&public_key)?;
+        let mut out_header = header.clone();
+        let encrypted_key = encrypter.encrypt(&src_key, &header, &mut out_header)?;
+
+        let decrypter = alg.decrypter_from_pem(&private_key)?;
+        let key = decrypter.decrypt(encrypted_key.as_deref(), &enc, &out_header)?;
+        assert_eq!(key.len(), enc.key_len());
+        assert_ne!(&key[..src_key.len()], &src_key[..]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn encrypt_rsa_oaep_256_with_sha1_mgf1() -> Result<()> {
+        let enc = AescbcHmacJweEncryption::A128cbcHs256;
+        let public_key = load_file("pem/RSA_2048bit_public.pem")?;
+        let private_key = load_file("pem/RSA_2048bit_private.pem")?;
+
+        let mut header = JweHeader::new();
+        header.set_content_encryption(enc.name());
+        let src_key = util::random_bytes(enc.key_len());
+
+        let encrypter = RsaesJweAlgorithm::RsaOaep256
+            .encrypter_from_pem(&public_key)?
+            .with_mgf1_hash(HashAlgorithm::Sha1)?;
+        assert_eq!(encrypter.mgf1_hash(), Some(HashAlgorithm::Sha1));
+        let mut out_header = header.clone();
+        let encrypted_key = encrypter.encrypt(&src_key, &header, &mut out_header)?;
+
+        let decrypter = RsaesJweAlgorithm::RsaOaep256.decrypter_from_pem(&private_key)?;
+        assert!(decrypter
+            .decrypt(encrypted_key.as_deref(), &enc, &out_header)
+            .is_err());
+
+        let alg = RsaesJweAlgorithm::custom_oaep(
+            "RSA-OAEP-256",
+            HashAlgorithm::Sha256,
+            HashAlgorithm::Sha1,
+        );
+        let decrypter = alg.decrypter_from_pem(&private_key)?;
+        let dst_key = decrypter.decrypt(encrypted_key.as_deref(), &enc, &out_header)?;
+        assert_eq!(&src_key as &[u8], &dst_key as &[u8]);
+
+        #[allow(deprecated)]
+        let encrypter = RsaesJweAlgorithm::Rsa1_5.encrypter_from_pem(&public_key)?;
+        assert!(encrypter.with_mgf1_hash(HashAlgorithm::Sha1).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn encrypt_and_decrypt_rsa_oaep_with_label() -> Result<()> {
+        let enc = AescbcHmacJweEncryption::A128cbcHs256;
+        let public_key = load_file("pem/RSA_2048bit_public.pem")?;
+        let private_key = load_file("pem/RSA_2048bit_private.pem")?;
+
+        let mut header = JweHeader::new();
+        header.set_content_encryption(enc.name());
+        let src_key = util::random_bytes(enc.key_len());
+
+        let encrypter = RsaesJweAlgorithm::RsaOaep256
+            .encrypter_from_pem(&public_key)?
+            .with_oaep_label(b"label".to_vec())?;
+        assert_eq!(encrypter.oaep_label(), Some(&b"label"[..]));
+        let mut out_header = header.clone();
+        let encrypted_key = encrypter.encrypt(&src_key, &header, &mut out_header)?;
+
+        let decrypter = RsaesJweAlgorithm::RsaOaep256
+            .decrypter_from_pem(&private_key)?
+            .with_oaep_label(b"label".to_vec())?;
+        let dst_key = decrypter.decrypt(encrypted_key.as_deref(), &enc, &out_header)?;
+        assert_eq!(&src_key as &[u8], &dst_key as &[u8]);
+
+        let decrypter = RsaesJweAlgorithm::RsaOaep256
+            .decrypter_from_pem(&private_key)?
+            .with_oaep_label(b"other".to_vec())?;
+        match decrypter.decrypt(encrypted_key.as_deref(), &enc, &out_header) {
+            Err(JoseError::InvalidJweFormat(err)) => assert!(err.to_string().starts_with(
+                "The encrypted_key cannot be decrypted, or the OAEP label is mismatched"
+            )),
+            other => panic!("A mismatched OAEP label was accepted: {:?}", other),
+        }
+
+        let decrypter = RsaesJweAlgorithm::RsaOaep256.decrypter_from_pem(&private_key)?;
+        assert!(decrypter
+            .decrypt(encrypted_key.as_deref(), &enc, &out_header)
+            .is_err());
+
+        #[allow(deprecated)]
+        let alg = RsaesJweAlgorithm::Rsa1_5;
+        assert!(alg
+            .encrypter_from_pem(&public_key)?
+            .with_oaep_label(b"label".to_vec())
+            .is_err());
+        assert!(alg
+            .decrypter_from_pem(&private_key)?
+            .with_oaep_label(b"label".to_vec())
+            .is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn reject_rsaes_with_non_rsa_jwk() -> Result<()> {
+        let jwk = Jwk::from_bytes(load_file("jwk/EC_P-256_private.jwk")?)?;
+
+        match RsaesJweAlgorithm::RsaOaep.encrypter_from_jwk(&jwk) {
+            Err(JoseError::InvalidKeyType { expected, actual }) => {
+                assert_eq!(expected, "RSA");
+                assert_eq!(actual, "EC");
+            }
+            other => panic!("A non-RSA key was accepted: {:?}", other),
+        }
+
+        match RsaesJweAlgorithm::RsaOaep.decrypter_from_jwk(&jwk) {
+            Err(err @ JoseError::InvalidKeyType { .. }) => {
+                assert_eq!(err.to_string(), "Invalid key type: EC (expected RSA)");
+            }
+            other => panic!("A non-RSA key was accepted: {:?}", other),
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn reject_rsaes_with_small_exponent() -> Result<()> {
+        let enc = AescbcHmacJweEncryption::A128cbcHs256;
+
+        let exponent = BigNum::from_u32(3)?;
+        let rsa = openssl::rsa::Rsa::generate_with_e(2048, &exponent)?;
+        let mut public_key = Jwk::new("RSA");
+        public_key.set_parameter(
+            "n",
+            Some(Value::String(util::encode_base64_urlsafe_nopad(
+                rsa.n().to_vec(),
+            ))),
+        )?;
+        public_key.set_parameter("e", Some(Value::String("Aw".to_string())))?;
+
+        let alg = RsaesJweAlgorithm::RsaOaep256;
+        let mut header = JweHeader::new();
+        header.set_content_encryption(enc.name());
+        let src_key = util::random_bytes(enc.key_len());
+
+        match alg.encrypter_from_jwk(&public_key) {
+            Err(JoseError::InvalidKeyFormat(err)) => assert_eq!(
+                err.to_string(),
+                "The public exponent must be 65537 or more: 3"
+            ),
+            other => panic!("A small public exponent was accepted: {:?}", other),
+        }
+        let public_key_der = rsa.public_key_to_der()?;
+        assert!(alg.encrypter_from_der(&public_key_der).is_err());
+        assert!(alg.encrypter_from_pem(rsa.public_key_to_pem()?).is_err());
+        assert!(alg
+            .encrypter_from_der_allowing_small_exponent(&public_key_der)?
+            .is_small_exponent_allowed());
+
+        let encrypter = alg.encrypter_from_jwk_allowing_small_exponent(&public_key)?;
+        assert!(encrypter.is_small_exponent_allowed());
+        let mut out_header = header.clone();
+        let encrypted_key = encrypter.encrypt(&src_key, &header, &mut out_header)?;
+
+        let decrypter = alg.decrypter_from_der(rsa.private_key_to_der()?)?;
+        let dst_key = decrypter.decrypt(encrypted_key.as_deref(), &enc, &out_header)?;
+        assert_eq!(&src_key as &[u8], &dst_key as &[u8]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn rsaes_encrypter_to_jwk() -> Result<()> {
+        let enc = AescbcHmacJweEncryption::A128cbcHs256;
+
+        let public_key = load_file("pem/RSA_2048bit_public.pem")?;
+        let private_key = load_file("pem/RSA_2048bit_private.pem")?;
+
+        let alg = RsaesJweAlgorithm::RsaOaep256;
+        let mut encrypter = alg.encrypter_from_pem(&public_key)?;
+        encrypter.set_key_id("key-1");
+
+        let jwk = encrypter.to_jwk()?;
+        assert_eq!(jwk.key_type(), "RSA");
+        assert_eq!(jwk.algorithm(), Some("RSA-OAEP-256"));
+        assert_eq!(jwk.key_id(), Some("key-1"));
+        assert_eq!(jwk.parameter("d"), None);
+
+        let reconstructed = alg.encrypter_from_jwk(&jwk)?;
+        assert_eq!(reconstructed.to_jwk()?, jwk);
+
+        let mut header = JweHeader::new();
+        header.set_content_encryption(enc.name());
+        let mut out_header = header.clone();
+        let src_key = util::random_bytes(enc.key_len());
+        let encrypted_key = reconstructed.encrypt(&src_key, &header, &mut out_header)?;
+
+        let decrypter = alg.decrypter_from_pem(&private_key)?;
+        let dst_key = decrypter.decrypt(encrypted_key.as_deref(), &enc, &out_header)?;
+        assert_eq!(&src_key as &[u8], &dst_key as &[u8]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn rsaes_decrypter_to_public_jwk() -> Result<()> {
+        let enc = AescbcHmacJweEncryption::A128cbcHs256;
+
+        let private_key = load_file("jwk/RSA_private.jwk")?;
+        let private_key = Jwk::from_bytes(&private_key)?;
+
+        let alg = RsaesJweAlgorithm::RsaOaep;
+        let mut decrypter = alg.decrypter_from_jwk(&private_key)?;
+        decrypter.set_key_id("key-1");
+
+        let jwk = decrypter.to_public_jwk()?;
+        assert_eq!(jwk.key_use(), Some("enc"));
+        assert_eq!(jwk.algorithm(), Some("RSA-OAEP"));
+        assert_eq!(jwk.key_id(), Some("key-1"));
+        assert_eq!(jwk.parameter("n"), private_key.parameter("n"));
+        assert_eq!(jwk.parameter("e"), private_key.parameter("e"));
+        for name in ["d", "p", "q", "dp", "dq", "qi"] {
+            assert_eq!(jwk.parameter(name), None);
+        }
+
+        let encrypter = alg.encrypter_from_jwk(&jwk)?;
+        let mut header = JweHeader::new();
+        header.set_content_encryption(enc.name());
+        let mut out_header = header.clone();
+        let src_key = util::random_bytes(enc.key_len());
+        let encrypted_key = encrypter.encrypt(&src_key, &header, &mut out_header)?;
+
+        let dst_key = decrypter.decrypt(encrypted_key.as_deref(), &enc, &out_header)?;
+        assert_eq!(&src_key as &[u8], &dst_key as &[u8]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn describe_rsaes_decrypter() -> Result<()> {
+        let private_key = load_file("pem/RSA_2048bit_private.pem")?;
+
+        let mut decrypter = RsaesJweAlgorithm::RsaOaep256.decrypter_from_pem(&private_key)?;
+        let descriptor = decrypter.descriptor();
+        assert_eq!(descriptor.algorithm(), "RSA-OAEP-256");
+        assert_eq!(descriptor.key_id(), None);
+
+        decrypter.set_key_id("key-1");
+        let descriptor = decrypter.descriptor();
+        assert_eq!(descriptor.algorithm(), "RSA-OAEP-256");
+        assert_eq!(descriptor.key_id(), Some("key-1"));
+        assert!(!format!("{:?}", descriptor).contains("PKey"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn expected_rsaes_encrypted_key_len() -> Result<()> {
+        let enc = AescbcHmacJweEncryption::A128cbcHs256;
+        let private_key = load_file("pem/RSA_2048bit_private.pem")?;
+        let public_key = load_file("pem/RSA_2048bit_public.pem")?;
+
+        let alg = RsaesJweAlgorithm::RsaOaep256;
+        let decrypter = alg.decrypter_from_pem(&private_key)?;
+        assert_eq!(decrypter.expected_encrypted_key_len(&enc), Some(256));
+
+        let encrypter = alg.encrypter_from_pem(&public_key)?;
+        let mut header = JweHeader::new();
+        header.set_content_encryption(enc.name());
+        let jwe = jwe::serialize_compact(b"abcde12345", &header, &encrypter)?;
+        let (payload, _) = jwe::deserialize_compact(&jwe, &decrypter)?;
+        assert_eq!(payload, b"abcde12345");
+
+        let parts: Vec<&str> = jwe.split('.').collect();
+        let truncated = format!("{}.{}", parts[0], &parts[1][4..]) + "." + &parts[2..].join(".");
+        match jwe::deserialize_compact(&truncated, &decrypter) {
+            Err(JoseError::InvalidJweFormat(err)) => {
+                assert_eq!(err.to_string(), "The encrypted_key size must be 256: 253")
+            }
+            other => panic!("A truncated encrypted_key was accepted: {:?}", other),
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    #[allow(deprecated)]
+    fn encrypt_rsaes_with_cached_key_size() -> Result<()> {
+        let enc = AescbcHmacJweEncryption::A128cbcHs256;
+
+        let private_key = load_file("pem/RSA_2048bit_private.pem")?;
+        let public_key = load_file("pem/RSA_2048bit_public.pem")?;
+
+        for alg in [
+            RsaesJweAlgorithm::Rsa1_5,
+            RsaesJweAlgorithm::RsaOaep,
+            RsaesJweAlgorithm::RsaOaep256,
+            RsaesJweAlgorithm::RsaOaep384,
+            RsaesJweAlgorithm::RsaOaep512,
+        ] {
+            let encrypter = alg.encrypter_from_pem(&public_key)?;
+            assert_eq!(encrypter.rsa_size, 256);
+
+            let decrypter = alg.decrypter_from_pem(&private_key)?;
+            for _ in 0..3 {
+                let header = JweHeader::new();
+                let mut out_header = header.clone();
+                let src_key = util::random_bytes(enc.key_len());
+                let encrypted_key = encrypter.encrypt(&src_key, &header, &mut out_header)?;
+                assert_eq!(encrypted_key.as_ref().map(|val| val.len()), Some(256));
+
+                let dst_key = decrypter.decrypt(encrypted_key.as_deref(), &enc, &out_header)?;
+                assert_eq!(&src_key as &[u8], &dst_key as &[u8]);
+            }
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    #[allow(deprecated)]
+    fn decrypt_rsaes_with_truncated_encrypted_key() -> Result<()> {
+        let enc = AescbcHmacJweEncryption::A128cbcHs256;
+
+        let private_key = load_file("pem/RSA_2048bit_private.pem")?;
+        let public_key = load_file("pem/RSA_2048bit_public.pem")?;
+
+        for alg in [
+            RsaesJweAlgorithm::Rsa1_5,
+            RsaesJweAlgorithm::RsaOaep,
+            RsaesJweAlgorithm::RsaOaep256,
+            RsaesJweAlgorithm::RsaOaep384,
+            RsaesJweAlgorithm::RsaOaep512,
+        ] {
+            let encrypter = alg.encrypter_from_pem(&public_key)?;
+            let header = JweHeader::new();
+            let mut out_header = header.clone();
+            let src_key = util::random_bytes(enc.key_len());
+            let encrypted_key = encrypter
+                .encrypt(&src_key, &header, &mut out_header)?
+                .unwrap();
+
+            let decrypter = alg.decrypter_from_pem(&private_key)?;
+            match decrypter.decrypt(Some(&encrypted_key[1..]), &enc, &out_header) {
+                Err(JoseError::InvalidJweFormat(_)) => {}
+                other => panic!("Truncated encrypted_key was accepted: {:?}", other),
+            }
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    #[allow(deprecated)]
+    fn encrypt_and_decrypt_rsaes_with_pkcs12() -> Result<()> {
+        let enc = AescbcHmacJweEncryption::A128cbcHs256;
+
+        let pkcs12 = load_file("p12/RSA_2048bit.p12")?;
+        let public_key = load_file("pem/RSA_2048bit_public.pem")?;
+
+        let (_, certs) = util::parse_pkcs12(&pkcs12, "password")?;
+        assert_eq!(certs.len(), 1);
+        assert!(util::parse_pkcs12(&pkcs12, "wrong").is_err());
+
+        for alg in [
+            RsaesJweAlgorithm::Rsa1_5,
+            RsaesJweAlgorithm::RsaOaep,
+            RsaesJweAlgorithm::RsaOaep256,
+            RsaesJweAlgorithm::RsaOaep384,
+            RsaesJweAlgorithm::RsaOaep512,
+        ] {
+            let header = JweHeader::new();
+            let mut out_header = header.clone();
+            let encrypter = alg.encrypter_from_pem(&public_key)?;
+            let src_key = util::random_bytes(enc.key_len());
+            let encrypted_key = encrypter.encrypt(&src_key, &header, &mut out_header)?;
+
+            let decrypter = alg.decrypter_from_pkcs12(&pkcs12, "password")?;
+            let dst_key = decrypter.decrypt(encrypted_key.as_deref(), &enc, &out_header)?;
+            assert_eq!(&src_key as &[u8], &dst_key as &[u8]);
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn name_equals_alg_names_constant() {
+        assert_eq!(
+            RsaesJweAlgorithm::RsaOaep256.name(),
+            alg_names::RSA_OAEP_256
+        );
+        assert_eq!(RsaesJweAlgorithm::RsaOaep.name(), alg_names::RSA_OAEP);
+    }
+
+    #[test]
+    fn encrypt_and_decrypt_rsaes_with_custom_oaep() -> Result<()> {
+        let enc = AescbcHmacJweEncryption::A128cbcHs256;
+
+        let private_key = load_file("pem/RSA_2048bit_private.pem")?;
+        let public_key = load_file("pem/RSA_2048bit_public.pem")?;
+
+        for alg in [
+            RsaesJweAlgorithm::custom_oaep(
+                "RSA-OAEP-256-MGF1-SHA1",
+                HashAlgorithm::Sha256,
+                HashAlgorithm::Sha1,
+            ),
+            RsaesJweAlgorithm::custom_oaep(
+                "RSA-OAEP-512/256",
+                HashAlgorithm::Sha512_256,
+                HashAlgorithm::Sha512_256,
+            ),
+        ] {
+            assert!(alg.name().starts_with("RSA-OAEP-"));
+
+            let header = JweHeader::new();
+            let mut out_header = header.clone();
+            let encrypter = alg.encrypter_from_pem(&public_key)?;
+            let src_key = util::random_bytes(enc.key_len());
+            let encrypted_key = encrypter.encrypt(&src_key, &header, &mut out_header)?;
+
+            let decrypter = alg.decrypter_from_pem(&private_key)?;
+            let dst_key = decrypter.decrypt(encrypted_key.as_deref(), &enc, &out_header)?;
+            assert_eq!(&src_key as &[u8], &dst_key as &[u8]);
+        }
+
+        let encrypter = RsaesJweAlgorithm::custom_oaep(
+            "RSA-OAEP-256-MGF1-SHA1",
+            HashAlgorithm::Sha256,
+            HashAlgorithm::Sha1,
+        )
+        .encrypter_from_pem(public_key)?;
+        let header = JweHeader::new();
+        let mut out_header = header.clone();
+        let src_key = util::random_bytes(enc.key_len());
+        let encrypted_key = encrypter.encrypt(&src_key, &header, &mut out_header)?;
+
+        let decrypter = RsaesJweAlgorithm::RsaOaep256.decrypter_from_pem(private_key)?;
+        assert!(decrypter
+            .decrypt(encrypted_key.as_deref(), &enc, &out_header)
+            .is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    #[allow(deprecated)]
+    fn encrypt_many_rsaes() -> Result<()> {
+        let enc = AescbcHmacJweEncryption::A128cbcHs256;
+
+        let private_key = load_file("pem/RSA_2048bit_private.pem")?;
+        let public_key = load_file("pem/RSA_2048bit_public.pem")?;
+
+        for alg in [
+            RsaesJweAlgorithm::Rsa1_5,
+            RsaesJweAlgorithm::RsaOaep,
+            RsaesJweAlgorithm::RsaOaep256,
+            RsaesJweAlgorithm::RsaOaep384,
+            RsaesJweAlgorithm::RsaOaep512,
+        ] {
+            let encrypter = alg.encrypter_from_pem(&public_key)?;
+            let decrypter = alg.decrypter_from_pem(&private_key)?;
+            let header = JweHeader::new();
+
+            let src_keys: Vec<Vec<u8>> =
+                (0..4).map(|_| util::random_bytes(enc.key_len())).collect();
+            let src_key_refs: Vec<&[u8]> = src_keys.iter().map(|val| val.as_slice()).collect();
+            let encrypted_keys = encrypter.encrypt_many(&src_key_refs)?;
+            assert_eq!(encrypted_keys.len(), src_keys.len());
+
+            // The outputs are randomized, so compare them by the decrypted keys.
+            for (src_key, encrypted_key) in src_keys.iter().zip(&encrypted_keys) {
+                let mut out_header = header.clone();
+                let single = encrypter.encrypt(src_key, &header, &mut out_header)?;
+                let single = decrypter.decrypt(single.as_deref(), &enc, &header)?;
+                let batch = decrypter.decrypt(Some(encrypted_key), &enc, &header)?;
+                assert_eq!(&single as &[u8], src_key.as_slice());
+                assert_eq!(&batch as &[u8], src_key.as_slice());
+            }
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn debug_rsaes_without_key_material() -> Result<()> {
+        let alg = RsaesJweAlgorithm::RsaOaep;
+        let key_pair = alg.generate_key_pair(2048)?;
+        let jwk = key_pair.to_jwk_key_pair();
+
+        let mut encrypter = alg.encrypter_from_der(key_pair.to_der_public_key())?;
+        encrypter.set_key_id("kid-1");
+        let mut decrypter = alg.decrypter_from_der(key_pair.to_der_private_key())?;
+        decrypter.set_key_id("kid-1");
+
+        let debug = format!("{:?}\n{:?}", encrypter, decrypter);
+        assert!(debug.contains("RsaOaep"));
+        assert!(debug.contains("kid-1"));
+        assert!(debug.contains("[REDACTED]"));
+        for name in ["n", "d", "p", "q"] {
+            let value = match jwk.parameter(name) {
+                Some(Value::String(val)) => util::decode_base64_urlsafe_no_pad(val)?,
+                _ => unreachable!(),
+            };
+            let hex = BigNum::from_slice(&value)?.to_hex_str()?.to_string();
+            assert!(!debug.contains(&util::encode_base64_urlsafe_nopad(&value)[..16]));
+            assert!(!debug.to_uppercase().contains(&hex.to_uppercase()[..16]));
+        }
+
+        Ok(())
+    }
+
+    #[derive(Debug)]
+    struct FixedRandomSource(Mutex<Vec<Vec<u8>>>);
+
+    impl RandomSource for FixedRandomSource {
+        fn fill(&self, buf: &mut [u8]) -> Result<(), JoseError> {
+            let val = self.0.lock().unwrap().remove(0);
+            buf.copy_from_slice(&val);
+            Ok(())
+        }
+
+        fn box_clone(&self) -> Box<dyn RandomSource> {
+            Box::new(FixedRandomSource(Mutex::new(
+                self.0.lock().unwrap().clone(),
+            )))
+        }
+    }
+
+    #[test]
+    fn rsa_oaep_with_rfc7516_a1_vector() -> Result<()> {
+        let jwk = Jwk::from_bytes(load_file("jwk/RSA_RFC7516_A1_private.jwk")?)?;
+        let plaintext = b"The true sign of intelligence is not knowledge but imagination.";
+        let cek = [
+            177, 161, 244, 128, 84, 143, 225, 115, 63, 180, 3, 255, 107, 154, 212, 246, 138, 7,
+            110, 91, 112, 46, 34, 105, 47, 130, 203, 46, 122, 234, 64, 252,
+        ];
+        let iv = [227, 197, 117, 252, 2, 219, 233, 68, 180, 225, 77, 219];
+        let expected = [
+            "eyJhbGciOiJSU0EtT0FFUCIsImVuYyI6IkEyNTZHQ00ifQ",
+            "OKOawDo13gRp2ojaHV7LFpZcgV7T6DVZKTyKOMTYUmKoTCVJRgckCL9kiMT03JGeipsEdY3mx_etLbbWSrFr05kLzcSr4qKAq7YN7e9jwQRb23nfa6c9d-StnImGyFDbSv04uVuxIp5Zms1gNxKKK2Da14B8S4rzVRltdYwam_lDp5XnZAYpQdb76FdIKLaVmqgfwX7XWRxv2322i-vDxRfqNzo_tETKzpVLzfiwQyeyPGLBIO56YJ7eObdv0je81860ppamavo35UgoRdbYaBcoh9QcfylQr66oc6vFWXRcZ_ZT2LawVCWTIy3brGPi6UklfCpIMfIjf7iGdXKHzg",
+            "48V1_ALb6US04U3b",
+            "5eym8TW_c8SuK0ltJ3rpYIzOeDQz7TALvtu6UG9oMo4vpzs9tX_EFShS8iB7j6jiSdiwkIr3ajwQzaBtQD_A",
+            "XFBoMYUZodetZdvTiFvSkQ",
+        ];
+
+        let decrypter = RsaesJweAlgorithm::RsaOaep.decrypter_from_jwk(&jwk)?;
+        let (payload, header) = jwe::deserialize_compact(&expected.join("."), &decrypter)?;
+        assert_eq!(&payload[..], &plaintext[..]);
+        assert_eq!(header.algorithm(), Some("RSA-OAEP"));
+        assert_eq!(header.content_encryption(), Some("A256GCM"));
+
+        // RSAES-OAEP is randomized, so only the encrypted key differs from the vector.
+        let mut context = JweContext::new();
+        context.set_random_source(Box::new(FixedRandomSource(Mutex::new(vec![
+            cek.to_vec(),
+            iv.to_vec(),
+        ]))));
+        let mut header = JweHeader::new();
+        header.set_algorithm("RSA-OAEP");
+        header.set_content_encryption("A256GCM");
+        let encrypter = RsaesJweAlgorithm::RsaOaep.encrypter_from_jwk(&jwk.to_public_key()?)?;
+        let jwe = context.serialize_compact(plaintext, &header, &encrypter)?;
+
+        let parts: Vec<&str> = jwe.split('.').collect();
+        assert_eq!(parts[0], expected[0]);
+        assert_ne!(parts[1], expected[1]);
+        assert_eq!(&parts[2..], &expected[2..]);
+
+        let (payload, _) = jwe::deserialize_compact(&jwe, &decrypter)?;
+        assert_eq!(&payload[..], &plaintext[..]);
+
+        Ok(())
+    }
+
     fn load_file(path: &str) -> Result<Vec<u8>> {
         let mut pb = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
         pb.push("data");
@@ -559,32 +1589,46 @@ mod openssl_rsa_oaep {
         rsa::Padding,
     };
 
-    pub(crate) fn pkey_public_encrypt(
+    pub(crate) fn pkey_public_encrypt_many(
         pkey: &PKey<Public>,
-        input: &[u8],
+        inputs: &[&[u8]],
         md: MessageDigest,
-    ) -> Result<Vec<u8>, ErrorStack> {
+        mgf1_md: MessageDigest,
+        label: Option<&[u8]>,
+    ) -> Result<Vec<Vec<u8>>, ErrorStack> {
         let mut encrypter = Encrypter::new(pkey)?;
         encrypter.set_rsa_padding(Padding::PKCS1_OAEP)?;
         encrypter.set_rsa_oaep_md(md)?;
-        encrypter.set_rsa_mgf1_md(md)?;
+        encrypter.set_rsa_mgf1_md(mgf1_md)?;
+        if let Some(val) = label {
+            encrypter.set_rsa_oaep_label(val)?;
+        }
 
-        let outlen = encrypter.encrypt_len(input)?;
-        let mut output = vec![0; outlen];
-        let outlen = encrypter.encrypt(input, &mut output)?;
-        output.truncate(outlen);
-        Ok(output)
+        let mut outputs = Vec::with_capacity(inputs.len());
+        for input in inputs {
+            let outlen = encrypter.encrypt_len(input)?;
+            let mut output = vec![0; outlen];
+            let outlen = encrypter.encrypt(input, &mut output)?;
+            output.truncate(outlen);
+            outputs.push(output);
+        }
+        Ok(outputs)
     }
 
     pub(crate) fn pkey_private_decrypt(
         pkey: &PKey<Private>,
         input: &[u8],
         md: MessageDigest,
+        mgf1_md: MessageDigest,
+        label: Option<&[u8]>,
     ) -> Result<Vec<u8>, ErrorStack> {
         let mut decrypter = Decrypter::new(pkey)?;
         decrypter.set_rsa_padding(Padding::PKCS1_OAEP)?;
         decrypter.set_rsa_oaep_md(md)?;
-        decrypter.set_rsa_mgf1_md(md)?;
+        decrypter.set_rsa_mgf1_md(mgf1_md)?;
+        if let Some(val) = label {
+            decrypter.set_rsa_oaep_label(val)?;
+        }
 
         let outlen = decrypter.decrypt_len(input)?;
         let mut output = vec![0; outlen];
@@ -28,10 +28,10 @@ impl AescbcHmacJweEncryption {
         ciphertext: &[u8],
         mac_key: &[u8],
     ) -> Result<Vec<u8>, JoseError> {
-        let (message_digest, tlen) = match self {
-            Self::A128cbcHs256 => (MessageDigest::sha256(), 16),
-            Self::A192cbcHs384 => (MessageDigest::sha384(), 24),
-            Self::A256cbcHs512 => (MessageDigest::sha512(), 32),
+        let message_digest = match self {
+            Self::A128cbcHs256 => MessageDigest::sha256(),
+            Self::A192cbcHs384 => MessageDigest::sha384(),
+            Self::A256cbcHs512 => MessageDigest::sha512(),
         };
 
         let pkey = (|| -> anyhow::Result<PKey<Private>> {
@@ -51,7 +51,7 @@ impl AescbcHmacJweEncryption {
             signer.update(ciphertext)?;
             signer.update(&aad_bits)?;
             let mut signature = signer.sign_to_vec()?;
-            signature.truncate(tlen);
+            signature.truncate(self.tag_len());
             Ok(signature)
         })()
         .map_err(|err| JoseError::InvalidSignature(err))?;
@@ -81,6 +81,14 @@ impl JweContentEncryption for AescbcHmacJweEncryption {
         16
     }
 
+    fn tag_len(&self) -> usize {
+        match self {
+            Self::A128cbcHs256 => 16,
+            Self::A192cbcHs384 => 24,
+            Self::A256cbcHs512 => 32,
+        }
+    }
+
     fn encrypt(
         &self,
         key: &[u8],
@@ -218,4 +226,22 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn tag_len_aes_cbc_hmac() -> Result<()> {
+        for (enc, expected) in [
+            (AescbcHmacJweEncryption::A128cbcHs256, 16),
+            (AescbcHmacJweEncryption::A192cbcHs384, 24),
+            (AescbcHmacJweEncryption::A256cbcHs512, 32),
+        ] {
+            assert_eq!(enc.tag_len(), expected);
+
+            let key = util::random_bytes(enc.key_len());
+            let iv = util::random_bytes(enc.iv_len());
+            let (_, tag) = enc.encrypt(&key, Some(&iv), b"abcde12345", b"test")?;
+            assert_eq!(tag.map(|val| val.len()), Some(expected));
+        }
+
+        Ok(())
+    }
 }
@@ -1,11 +1,15 @@
+use std::collections::BTreeSet;
 use std::fmt::Display;
+use std::io::{Read, Write};
 use std::ops::Deref;
+use std::sync::{Arc, Mutex};
 
-use anyhow::bail;
-use openssl::symm::{self, Cipher};
+use anyhow::{anyhow, bail};
+use openssl::hash::{self, MessageDigest};
+use openssl::symm::{self, Cipher, Crypter, Mode};
 
 use crate::jwe::JweContentEncryption;
-use crate::JoseError;
+use crate::{alg_names, JoseError};
 
 #[derive(Debug, Eq, PartialEq, Copy, Clone)]
 pub enum AesgcmJweEncryption {
@@ -17,33 +21,73 @@ pub enum AesgcmJweEncryption {
     A256gcm,
 }
 
-impl JweContentEncryption for AesgcmJweEncryption {
-    fn name(&self) -> &str {
-        match self {
-            Self::A128gcm => "A128GCM",
-            Self::A192gcm => "A192GCM",
-            Self::A256gcm => "A256GCM",
+impl AesgcmJweEncryption {
+    /// Return a content encryption using the truncated authentication tag.
+    ///
+    /// **This is not interoperable with JOSE.** RFC 7518 requires 128-bit tags, so this is
+    /// only for bridging protocols that use shorter tags. To keep such tokens apart from
+    /// the compliant ones, a truncated tag uses a private enc header claim name
+    /// that has the tag bit length as suffix (e.g. A128GCM-96), and a JweContext rejects it
+    /// unless the content encryption is registered by `JweContext::add_content_encryption`
+    /// explicitly. The 128-bit tag keeps the standard name.
+    ///
+    /// # Arguments
+    ///
+    /// * `bits` - A bit length of the authentication tag (96, 104, 112, 120 or 128)
+    pub fn with_tag_len(self, bits: usize) -> Result<AesgcmTagLenJweEncryption, JoseError> {
+        match bits {
+            96 | 104 | 112 | 120 | 128 => {}
+            _ => {
+                return Err(JoseError::InvalidJweFormat(anyhow!(
+                    "bits must be 96, 104, 112, 120 or 128: {}",
+                    bits
+                )))
+            }
         }
+
+        Ok(AesgcmTagLenJweEncryption {
+            inner: self,
+            tag_len: bits / 8,
+        })
     }
 
-    fn key_len(&self) -> usize {
-        match self {
-            Self::A128gcm => 16,
-            Self::A192gcm => 24,
-            Self::A256gcm => 32,
+    /// Return a content encryption that rejects an explicit initialization vector
+    /// used before with the same key.
+    ///
+    /// Reusing an initialization vector with the same key is fatal for AES GCM.
+    /// The used pairs are remembered as SHA-256 digests, and they are shared by the clones.
+    ///
+    /// Each remembered pair costs a 32 bytes digest and the set overhead (about 100 bytes),
+    /// so the memory grows up to `capacity` times that. When the capacity is reached,
+    /// the encryption with an explicit initialization vector fails until `clear` is called,
+    /// for example after rotating the key.
+    ///
+    /// # Arguments
+    ///
+    /// * `capacity` - The maximum number of remembered pairs of a key and an initialization vector.
+    pub fn with_iv_reuse_check(self, capacity: usize) -> AesgcmIvCheckJweEncryption {
+        AesgcmIvCheckJweEncryption {
+            inner: self,
+            capacity,
+            used: Arc::new(Mutex::new(BTreeSet::new())),
         }
     }
 
-    fn iv_len(&self) -> usize {
-        12
+    fn cipher(&self) -> Cipher {
+        match self {
+            AesgcmJweEncryption::A128gcm => Cipher::aes_128_gcm(),
+            AesgcmJweEncryption::A192gcm => Cipher::aes_192_gcm(),
+            AesgcmJweEncryption::A256gcm => Cipher::aes_256_gcm(),
+        }
     }
 
-    fn encrypt(
+    fn encrypt_with_tag_len(
         &self,
         key: &[u8],
         iv: Option<&[u8]>,
         message: &[u8],
         aad: &[u8],
+        tag_len: usize,
     ) -> Result<(Vec<u8>, Option<Vec<u8>>), JoseError> {
         (|| -> anyhow::Result<(Vec<u8>, Option<Vec<u8>>)> {
             let expected_len = self.key_len();
@@ -55,25 +99,22 @@ impl JweContentEncryption for AesgcmJweEncryption {
                 );
             }
 
-            let cipher = match self {
-                AesgcmJweEncryption::A128gcm => Cipher::aes_128_gcm(),
-                AesgcmJweEncryption::A192gcm => Cipher::aes_192_gcm(),
-                AesgcmJweEncryption::A256gcm => Cipher::aes_256_gcm(),
-            };
-            let mut tag = [0; 16];
-            let encrypted_message = symm::encrypt_aead(cipher, key, iv, aad, message, &mut tag)?;
-            Ok((encrypted_message, Some(tag.to_vec())))
+            let mut tag = vec![0; tag_len];
+            let encrypted_message =
+                symm::encrypt_aead(self.cipher(), key, iv, aad, message, &mut tag)?;
+            Ok((encrypted_message, Some(tag)))
         })()
-        .map_err(|err| JoseError::InvalidKeyFormat(err))
+        .map_err(JoseError::InvalidKeyFormat)
     }
 
-    fn decrypt(
+    fn decrypt_with_tag_len(
         &self,
         key: &[u8],
         iv: Option<&[u8]>,
         encrypted_message: &[u8],
         aad: &[u8],
         tag: Option<&[u8]>,
+        tag_len: usize,
     ) -> Result<Vec<u8>, JoseError> {
         (|| -> anyhow::Result<Vec<u8>> {
             let expected_len = self.key_len();
@@ -90,15 +131,112 @@ impl JweContentEncryption for AesgcmJweEncryption {
                 None => bail!("A tag value is required."),
             };
 
-            let cipher = match self {
-                AesgcmJweEncryption::A128gcm => Cipher::aes_128_gcm(),
-                AesgcmJweEncryption::A192gcm => Cipher::aes_192_gcm(),
-                AesgcmJweEncryption::A256gcm => Cipher::aes_256_gcm(),
-            };
-            let message = symm::decrypt_aead(cipher, key, iv, aad, encrypted_message, tag)?;
+            if tag.len() != tag_len {
+                bail!("The length of tag must be {}: {}", tag_len, tag.len());
+            }
+
+            let message = symm::decrypt_aead(self.cipher(), key, iv, aad, encrypted_message, tag)?;
             Ok(message)
         })()
-        .map_err(|err| JoseError::InvalidJweFormat(err))
+        .map_err(JoseError::InvalidJweFormat)
+    }
+}
+
+impl JweContentEncryption for AesgcmJweEncryption {
+    fn name(&self) -> &str {
+        match self {
+            Self::A128gcm => alg_names::A128GCM,
+            Self::A192gcm => alg_names::A192GCM,
+            Self::A256gcm => alg_names::A256GCM,
+        }
+    }
+
+    fn key_len(&self) -> usize {
+        match self {
+            Self::A128gcm => 16,
+            Self::A192gcm => 24,
+            Self::A256gcm => 32,
+        }
+    }
+
+    fn iv_len(&self) -> usize {
+        12
+    }
+
+    fn tag_len(&self) -> usize {
+        16
+    }
+
+    fn encrypt(
+        &self,
+        key: &[u8],
+        iv: Option<&[u8]>,
+        message: &[u8],
+        aad: &[u8],
+    ) -> Result<(Vec<u8>, Option<Vec<u8>>), JoseError> {
+        self.encrypt_with_tag_len(key, iv, message, aad, self.tag_len())
+    }
+
+    fn encrypt_to_writer(
+        &self,
+        key: &[u8],
+        iv: Option<&[u8]>,
+        input: &mut dyn Read,
+        aad: &[u8],
+        output: &mut dyn Write,
+    ) -> Result<Option<Vec<u8>>, JoseError> {
+        (|| -> anyhow::Result<Option<Vec<u8>>> {
+            let expected_len = self.key_len();
+            if key.len() != expected_len {
+                bail!(
+                    "The length of content encryption key must be {}: {}",
+                    expected_len,
+                    key.len()
+                );
+            }
+
+            let cipher = self.cipher();
+            let mut crypter = Crypter::new(cipher, Mode::Encrypt, key, iv)?;
+            crypter.aad_update(aad)?;
+
+            let mut buf = vec![0; 64 * 1024];
+            let mut encrypted = vec![0; buf.len() + cipher.block_size()];
+            loop {
+                let len = input
+                    .read(&mut buf)
+                    .map_err(|err| JoseError::InvalidJweFormat(err.into()))?;
+                if len == 0 {
+                    break;
+                }
+                let len = crypter.update(&buf[..len], &mut encrypted)?;
+                output
+                    .write_all(&encrypted[..len])
+                    .map_err(|err| JoseError::InvalidJweFormat(err.into()))?;
+            }
+            let len = crypter.finalize(&mut encrypted)?;
+            output
+                .write_all(&encrypted[..len])
+                .map_err(|err| JoseError::InvalidJweFormat(err.into()))?;
+
+            let mut tag = vec![0; self.tag_len()];
+            crypter.get_tag(&mut tag)?;
+            Ok(Some(tag))
+        })()
+        .map_err(|err| match err.downcast::<JoseError>() {
+            Ok(err) => err,
+            Err(err) => JoseError::InvalidKeyFormat(err),
+        })
+    }
+
+    fn decrypt(
+        &self,
+        key: &[u8],
+        iv: Option<&[u8]>,
+        encrypted_message: &[u8],
+        aad: &[u8],
+        tag: Option<&[u8]>,
+    ) -> Result<Vec<u8>, JoseError> {
+        self.decrypt_with_tag_len(key, iv, encrypted_message, aad, tag, self.tag_len())
     }
 
     fn box_clone(&self) -> Box<dyn JweContentEncryption> {
@@ -120,12 +258,206 @@ impl Deref for AesgcmJweEncryption {
     }
 }
 
+/// AES GCM content encryption with a non-default authentication tag length.
+///
+/// See `AesgcmJweEncryption::with_tag_len` for the private enc header claim name.
+#[derive(Debug, Eq, PartialEq, Copy, Clone)]
+pub struct AesgcmTagLenJweEncryption {
+    inner: AesgcmJweEncryption,
+    tag_len: usize,
+}
+
+impl JweContentEncryption for AesgcmTagLenJweEncryption {
+    fn name(&self) -> &str {
+        match (self.inner, self.tag_len * 8) {
+            (AesgcmJweEncryption::A128gcm, 96) => "A128GCM-96",
+            (AesgcmJweEncryption::A128gcm, 104) => "A128GCM-104",
+            (AesgcmJweEncryption::A128gcm, 112) => "A128GCM-112",
+            (AesgcmJweEncryption::A128gcm, 120) => "A128GCM-120",
+            (AesgcmJweEncryption::A192gcm, 96) => "A192GCM-96",
+            (AesgcmJweEncryption::A192gcm, 104) => "A192GCM-104",
+            (AesgcmJweEncryption::A192gcm, 112) => "A192GCM-112",
+            (AesgcmJweEncryption::A192gcm, 120) => "A192GCM-120",
+            (AesgcmJweEncryption::A256gcm, 96) => "A256GCM-96",
+            (AesgcmJweEncryption::A256gcm, 104) => "A256GCM-104",
+            (AesgcmJweEncryption::A256gcm, 112) => "A256GCM-112",
+            (AesgcmJweEncryption::A256gcm, 120) => "A256GCM-120",
+            _ => self.inner.name(),
+        }
+    }
+
+    fn key_len(&self) -> usize {
+        self.inner.key_len()
+    }
+
+    fn iv_len(&self) -> usize {
+        self.inner.iv_len()
+    }
+
+    fn tag_len(&self) -> usize {
+        self.tag_len
+    }
+
+    fn encrypt(
+        &self,
+        key: &[u8],
+        iv: Option<&[u8]>,
+        message: &[u8],
+        aad: &[u8],
+    ) -> Result<(Vec<u8>, Option<Vec<u8>>), JoseError> {
+        self.inner
+            .encrypt_with_tag_len(key, iv, message, aad, self.tag_len)
+    }
+
+    fn decrypt(
+        &self,
+        key: &[u8],
+        iv: Option<&[u8]>,
+        encrypted_message: &[u8],
+        aad: &[u8],
+        tag: Option<&[u8]>,
+    ) -> Result<Vec<u8>, JoseError> {
+        self.inner
+            .decrypt_with_tag_len(key, iv, encrypted_message, aad, tag, self.tag_len)
+    }
+
+    fn box_clone(&self) -> Box<dyn JweContentEncryption> {
+        Box::new(*self)
+    }
+}
+
+impl Display for AesgcmTagLenJweEncryption {
+    fn fmt(&self, fmt: &mut std::fmt::Formatter<'_>) -> Result<(), std::fmt::Error> {
+        fmt.write_str(self.name())
+    }
+}
+
+impl Deref for AesgcmTagLenJweEncryption {
+    type Target = dyn JweContentEncryption;
+
+    fn deref(&self) -> &Self::Target {
+        self
+    }
+}
+
+/// AES GCM content encryption that rejects a reused initialization vector for the same key.
+#[derive(Debug, Clone)]
+pub struct AesgcmIvCheckJweEncryption {
+    inner: AesgcmJweEncryption,
+    capacity: usize,
+    used: Arc<Mutex<BTreeSet<Vec<u8>>>>,
+}
+
+impl AesgcmIvCheckJweEncryption {
+    /// Forget the remembered pairs of a key and an initialization vector.
+    ///
+    /// The reuse is not detected across this call, so call it only when the used keys are
+    /// never used again.
+    pub fn clear(&self) {
+        match self.used.lock() {
+            Ok(mut val) => val.clear(),
+            Err(err) => err.into_inner().clear(),
+        }
+        self.used.clear_poison();
+    }
+
+    fn check_iv(&self, key: &[u8], iv: &[u8]) -> Result<(), JoseError> {
+        (|| -> anyhow::Result<()> {
+            let mut input = Vec::with_capacity(4 + key.len() + iv.len());
+            input.extend_from_slice(&(key.len() as u32).to_be_bytes());
+            input.extend_from_slice(key);
+            input.extend_from_slice(iv);
+            let digest = hash::hash(MessageDigest::sha256(), &input)?.to_vec();
+
+            let mut used = match self.used.lock() {
+                Ok(val) => val,
+                Err(_) => bail!("The used initialization vectors are poisoned."),
+            };
+            if used.contains(&digest) {
+                bail!("The initialization vector is reused with the same key.");
+            }
+            if used.len() >= self.capacity {
+                bail!(
+                    "The number of remembered initialization vectors reached the capacity: {}",
+                    self.capacity
+                );
+            }
+            used.insert(digest);
+            Ok(())
+        })()
+        .map_err(JoseError::InvalidJweFormat)
+    }
+}
+
+impl JweContentEncryption for AesgcmIvCheckJweEncryption {
+    fn name(&self) -> &str {
+        self.inner.name()
+    }
+
+    fn key_len(&self) -> usize {
+        self.inner.key_len()
+    }
+
+    fn iv_len(&self) -> usize {
+        self.inner.iv_len()
+    }
+
+    fn tag_len(&self) -> usize {
+        self.inner.tag_len()
+    }
+
+    fn encrypt(
+        &self,
+        key: &[u8],
+        iv: Option<&[u8]>,
+        message: &[u8],
+        aad: &[u8],
+    ) -> Result<(Vec<u8>, Option<Vec<u8>>), JoseError> {
+        if let Some(iv) = iv {
+            self.check_iv(key, iv)?;
+        }
+        self.inner.encrypt(key, iv, message, aad)
+    }
+
+    fn decrypt(
+        &self,
+        key: &[u8],
+        iv: Option<&[u8]>,
+        encrypted_message: &[u8],
+        aad: &[u8],
+        tag: Option<&[u8]>,
+    ) -> Result<Vec<u8>, JoseError> {
+        self.inner.decrypt(key, iv, encrypted_message, aad, tag)
+    }
+
+    fn box_clone(&self) -> Box<dyn JweContentEncryption> {
+        Box::new(self.clone())
+    }
+}
+
+impl Display for AesgcmIvCheckJweEncryption {
+    fn fmt(&self, fmt: &mut std::fmt::Formatter<'_>) -> Result<(), std::fmt::Error> {
+        fmt.write_str(self.name())
+    }
+}
+
+impl Deref for AesgcmIvCheckJweEncryption {
+    type Target = dyn JweContentEncryption;
+
+    fn deref(&self) -> &Self::Target {
+        self
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use anyhow::Result;
+    use std::io::{self, Write};
 
     use super::AesgcmJweEncryption;
+    use crate::jwe::{Dir, JweContext, JweHeader};
     use crate::util;
+    use crate::JoseError;
 
     #[test]
     fn encrypt_and_decrypt_aes_gcm() -> Result<()> {
@@ -154,4 +486,228 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn decrypt_in_place_aes_gcm() -> Result<()> {
+        let message = b"abcde12345";
+        let aad = b"test";
+
+        for enc in [
+            AesgcmJweEncryption::A128gcm,
+            AesgcmJweEncryption::A192gcm,
+            AesgcmJweEncryption::A256gcm,
+        ] {
+            let key = util::random_bytes(enc.key_len());
+            let iv = util::random_bytes(enc.iv_len());
+
+            let (encrypted_message, tag) = enc.encrypt(&key, Some(&iv), message, aad)?;
+            let decrypted_message =
+                enc.decrypt(&key, Some(&iv), &encrypted_message, aad, tag.as_deref())?;
+
+            let mut buf = encrypted_message.clone();
+            enc.decrypt_in_place(&key, Some(&iv), &mut buf, aad, tag.as_deref())?;
+            assert_eq!(buf, decrypted_message);
+
+            let mut buf = encrypted_message.clone();
+            assert!(enc
+                .decrypt_in_place(&key, Some(&iv), &mut buf, b"tampered", tag.as_deref())
+                .is_err());
+            assert_eq!(buf, encrypted_message);
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn reject_aes_gcm_with_reused_iv() -> Result<()> {
+        let message = b"abcde12345";
+        let aad = b"test";
+
+        let enc = AesgcmJweEncryption::A128gcm.with_iv_reuse_check(3);
+        let key = util::random_bytes(enc.key_len());
+        let iv = util::random_bytes(enc.iv_len());
+
+        let (encrypted_message, tag) = enc.encrypt(&key, Some(&iv), message, aad)?;
+        let decrypted_message =
+            enc.decrypt(&key, Some(&iv), &encrypted_message, aad, tag.as_deref())?;
+        assert_eq!(&message[..], &decrypted_message[..]);
+
+        let cloned = enc.clone();
+        assert!(cloned.encrypt(&key, Some(&iv), message, aad).is_err());
+
+        let other_key = util::random_bytes(enc.key_len());
+        enc.encrypt(&other_key, Some(&iv), message, aad)?;
+        enc.encrypt(&key, Some(&util::random_bytes(enc.iv_len())), message, aad)?;
+
+        match enc.encrypt(&key, Some(&util::random_bytes(enc.iv_len())), message, aad) {
+            Err(JoseError::InvalidJweFormat(err)) => assert_eq!(
+                err.to_string(),
+                "The number of remembered initialization vectors reached the capacity: 3"
+            ),
+            other => panic!("The capacity was exceeded: {:?}", other),
+        }
+
+        cloned.clear();
+        enc.encrypt(&key, Some(&iv), message, aad)?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn tag_len_aes_gcm() {
+        for enc in [
+            AesgcmJweEncryption::A128gcm,
+            AesgcmJweEncryption::A192gcm,
+            AesgcmJweEncryption::A256gcm,
+        ] {
+            assert_eq!(enc.tag_len(), 16);
+        }
+    }
+
+    #[test]
+    fn encrypt_and_decrypt_aes_gcm_with_96bit_tag() -> Result<()> {
+        let message = b"abcde12345";
+        let aad = b"test";
+
+        for (enc, name) in [
+            (AesgcmJweEncryption::A128gcm.with_tag_len(96)?, "A128GCM-96"),
+            (AesgcmJweEncryption::A192gcm.with_tag_len(96)?, "A192GCM-96"),
+            (AesgcmJweEncryption::A256gcm.with_tag_len(96)?, "A256GCM-96"),
+        ] {
+            assert_eq!(enc.tag_len(), 12);
+            assert_eq!(enc.name(), name);
+
+            let key = util::random_bytes(enc.key_len());
+            let iv = util::random_bytes(enc.iv_len());
+
+            let (encrypted_message, tag) = enc.encrypt(&key, Some(&iv), message, aad)?;
+            assert_eq!(tag.as_ref().map(|val| val.len()), Some(12));
+
+            let decrypted_message = enc.decrypt(
+                &key,
+                Some(&iv),
+                &encrypted_message,
+                &aad[..],
+                tag.as_deref(),
+            )?;
+            assert_eq!(&message[..], &decrypted_message[..]);
+
+            assert!(enc
+                .inner
+                .decrypt(
+                    &key,
+                    Some(&iv),
+                    &encrypted_message,
+                    &aad[..],
+                    tag.as_deref()
+                )
+                .is_err());
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn aes_gcm_with_96bit_tag_requires_registration() -> Result<()> {
+        let enc = AesgcmJweEncryption::A128gcm.with_tag_len(96)?;
+        assert_eq!(
+            AesgcmJweEncryption::A128gcm.with_tag_len(128)?.name(),
+            "A128GCM"
+        );
+
+        let key = util::random_bytes(enc.key_len());
+        let encrypter = Dir.encrypter_from_bytes(&key)?;
+        let decrypter = Dir.decrypter_from_bytes(&key)?;
+        let mut header = JweHeader::new();
+        header.set_content_encryption(enc.name());
+
+        let mut context = JweContext::new();
+        assert!(context
+            .serialize_compact(b"abcde12345", &header, &encrypter)
+            .is_err());
+
+        context.add_content_encryption(Box::new(enc));
+        let jwe = context.serialize_compact(b"abcde12345", &header, &encrypter)?;
+        let (payload, header) = context.deserialize_compact(&jwe, &decrypter)?;
+        assert_eq!(payload, b"abcde12345");
+        assert_eq!(header.content_encryption(), Some("A128GCM-96"));
+
+        assert!(JweContext::new()
+            .deserialize_compact(&jwe, &decrypter)
+            .is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn reject_tampered_aes_gcm() -> Result<()> {
+        let enc = AesgcmJweEncryption::A128gcm;
+        let key = [0x01u8; 16];
+        let iv = [0x02u8; 12];
+        let message = b"abcde12345";
+        let aad = b"test";
+
+        let (encrypted_message, tag) = enc.encrypt(&key, Some(&iv), message, aad)?;
+        let tag = tag.unwrap();
+        let decrypted_message =
+            enc.decrypt(&key, Some(&iv), &encrypted_message, aad, Some(&tag))?;
+        assert_eq!(&message[..], &decrypted_message[..]);
+
+        let mut tampered_message = encrypted_message.clone();
+        tampered_message[0] ^= 0x01;
+        let mut tampered_tag = tag.clone();
+        tampered_tag[0] ^= 0x01;
+        let mut tampered_aad = aad.to_vec();
+        tampered_aad[0] ^= 0x01;
+
+        for (encrypted_message, aad, tag) in [
+            (&tampered_message, &aad[..], &tag),
+            (&encrypted_message, &aad[..], &tampered_tag),
+            (&encrypted_message, &tampered_aad[..], &tag),
+        ] {
+            match enc.decrypt(&key, Some(&iv), encrypted_message, aad, Some(tag)) {
+                Err(JoseError::InvalidJweFormat(_)) => {}
+                other => panic!("A tampered input was accepted: {:?}", other),
+            }
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn encrypt_aes_gcm_to_failing_writer() -> Result<()> {
+        struct FailingWriter;
+
+        impl Write for FailingWriter {
+            fn write(&mut self, _buf: &[u8]) -> io::Result<usize> {
+                Err(io::Error::other("broken pipe"))
+            }
+
+            fn flush(&mut self) -> io::Result<()> {
+                Ok(())
+            }
+        }
+
+        let enc = AesgcmJweEncryption::A128gcm;
+        let key = util::random_bytes(enc.key_len());
+        let iv = util::random_bytes(enc.iv_len());
+        let mut input: &[u8] = b"abcde12345";
+
+        match enc.encrypt_to_writer(&key, Some(&iv), &mut input, b"", &mut FailingWriter) {
+            Err(JoseError::InvalidJweFormat(err)) => assert_eq!(err.to_string(), "broken pipe"),
+            other => panic!("The write error was not reported: {:?}", other),
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn aes_gcm_with_invalid_tag_len() {
+        match AesgcmJweEncryption::A128gcm.with_tag_len(64) {
+            Err(JoseError::InvalidJweFormat(err)) => {
+                assert_eq!(err.to_string(), "bits must be 96, 104, 112, 120 or 128: 64")
+            }
+            other => panic!("An invalid tag length was accepted: {:?}", other),
+        }
+    }
 }
@@ -1,7 +1,10 @@
 use std::borrow::Cow;
 use std::fmt::Debug;
 
+use anyhow::anyhow;
+
 use crate::jwe::{JweContentEncryption, JweHeader};
+use crate::util::RandomSource;
 use crate::JoseError;
 
 /// Represent a algorithm of JWE alg header claim.
@@ -9,6 +12,22 @@ pub trait JweAlgorithm: Debug + Send + Sync {
     /// Return the "alg" (algorithm) header parameter value of JWE.
     fn name(&self) -> &str;
 
+    /// Return the key management mode used to determine the content encryption key.
+    ///
+    /// The default is `KeyManagementMode::KeyEncryption`, so a random content encryption key is
+    /// generated unless the encrypter determines one.
+    fn key_management_mode(&self) -> KeyManagementMode {
+        KeyManagementMode::KeyEncryption
+    }
+
+    /// Return the recommended "enc" (encryption) header parameter value for this algorithm.
+    ///
+    /// The default is A256GCM. The algorithms that wrap keys with a specific key size
+    /// recommend the AES GCM content encryption of the same key size.
+    fn recommended_content_encryption(&self) -> &'static str {
+        "A256GCM"
+    }
+
     fn box_clone(&self) -> Box<dyn JweAlgorithm>;
 }
 
@@ -26,6 +45,29 @@ impl Clone for Box<dyn JweAlgorithm> {
     }
 }
 
+/// Represent a key management mode of JWE defined in RFC 7516 section 2.
+#[derive(Debug, Eq, PartialEq, Copy, Clone)]
+pub enum KeyManagementMode {
+    /// A random content encryption key is encrypted to the recipient with an asymmetric algorithm.
+    KeyEncryption,
+    /// A random content encryption key is encrypted to the recipient with a symmetric key wrapping algorithm.
+    KeyWrapping,
+    /// A key agreement algorithm determines the content encryption key directly.
+    DirectKeyAgreement,
+    /// A key agreement algorithm determines a key to wrap a random content encryption key.
+    KeyAgreementWithKeyWrapping,
+    /// A shared symmetric key is used as the content encryption key directly.
+    DirectEncryption,
+}
+
+impl KeyManagementMode {
+    /// Return true if the content encryption key is determined by the algorithm
+    /// instead of being generated randomly.
+    pub fn is_direct(&self) -> bool {
+        matches!(self, Self::DirectKeyAgreement | Self::DirectEncryption)
+    }
+}
+
 pub trait JweEncrypter: Debug + Send + Sync {
     /// Return the source algorithm instance.
     fn algorithm(&self) -> &dyn JweAlgorithm;
@@ -62,6 +104,26 @@ pub trait JweEncrypter: Debug + Send + Sync {
         out_header: &mut JweHeader,
     ) -> Result<Option<Vec<u8>>, JoseError>;
 
+    /// Return a encypted key, taking random values like salts or IVs from the random source.
+    ///
+    /// The default ignores the random source and calls `encrypt`.
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - The content encryption key
+    /// * `in_header` - the input header
+    /// * `out_header` - the output header
+    /// * `random_source` - the random source
+    fn encrypt_with_random_source(
+        &self,
+        key: &[u8],
+        in_header: &JweHeader,
+        out_header: &mut JweHeader,
+        _random_source: &dyn RandomSource,
+    ) -> Result<Option<Vec<u8>>, JoseError> {
+        self.encrypt(key, in_header, out_header)
+    }
+
     fn box_clone(&self) -> Box<dyn JweEncrypter>;
 }
 
@@ -79,6 +141,16 @@ pub trait JweDecrypter: Debug + Send + Sync {
     /// The default value is a value of kid parameter in JWK.
     fn key_id(&self) -> Option<&str>;
 
+    /// Return the expected length of the encrypted key for the content encryption method,
+    /// or None if the length is variable or the encrypted key is absent.
+    ///
+    /// # Arguments
+    ///
+    /// * `cencryption` - The content encryption method.
+    fn expected_encrypted_key_len(&self, _cencryption: &dyn JweContentEncryption) -> Option<usize> {
+        None
+    }
+
     /// Return a decrypted key.
     ///
     /// # Arguments
@@ -93,6 +165,46 @@ pub trait JweDecrypter: Debug + Send + Sync {
         header: &JweHeader,
     ) -> Result<Cow<[u8]>, JoseError>;
 
+    /// Return a decrypted key, taking random values from the random source.
+    ///
+    /// The default ignores the random source and calls `decrypt`.
+    ///
+    /// # Arguments
+    ///
+    /// * `encrypted_key` - The encrypted key.
+    /// * `cencryption` - The content encryption method.
+    /// * `header` - The header
+    /// * `random_source` - The random source
+    fn decrypt_with_random_source(
+        &self,
+        encrypted_key: Option<&[u8]>,
+        cencryption: &dyn JweContentEncryption,
+        header: &JweHeader,
+        _random_source: &dyn RandomSource,
+    ) -> Result<Cow<'_, [u8]>, JoseError> {
+        self.decrypt(encrypted_key, cencryption, header)
+    }
+
+    /// Return a decrypted key, assuming the algorithm of this decrypter when the header lacks alg.
+    ///
+    /// This is a compatibility shim for non-compliant producers that omit the alg header claim
+    /// and should not be used unless it is required.
+    ///
+    /// # Arguments
+    ///
+    /// * `encrypted_key` - The encrypted key.
+    /// * `cencryption` - The content encryption method.
+    /// * `header` - The header
+    fn decrypt_assuming_alg(
+        &self,
+        encrypted_key: Option<&[u8]>,
+        cencryption: &dyn JweContentEncryption,
+        header: &JweHeader,
+    ) -> Result<Cow<'_, [u8]>, JoseError> {
+        let header = header_assuming_alg(self.algorithm().name(), header)?;
+        self.decrypt(encrypted_key, cencryption, &header)
+    }
+
     fn box_clone(&self) -> Box<dyn JweDecrypter>;
 }
 
@@ -101,3 +213,23 @@ impl Clone for Box<dyn JweDecrypter> {
         self.box_clone()
     }
 }
+
+/// Return the header with the expected alg header claim when it lacks alg.
+pub(crate) fn header_assuming_alg<'a>(
+    expected_alg: &str,
+    header: &'a JweHeader,
+) -> Result<Cow<'a, JweHeader>, JoseError> {
+    match header.algorithm() {
+        Some(val) if val == expected_alg => Ok(Cow::Borrowed(header)),
+        Some(val) => Err(JoseError::InvalidJweFormat(anyhow!(
+            "The JWE alg header claim is not {}: {}",
+            expected_alg,
+            val
+        ))),
+        None => {
+            let mut header = header.clone();
+            header.set_algorithm(expected_alg);
+            Ok(Cow::Owned(header))
+        }
+    }
+}
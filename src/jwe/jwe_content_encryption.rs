@@ -1,5 +1,6 @@
 use std::cmp::Eq;
 use std::fmt::Debug;
+use std::io::{Read, Write};
 
 use crate::JoseError;
 
@@ -12,6 +13,13 @@ pub trait JweContentEncryption: Debug + Send + Sync {
 
     fn iv_len(&self) -> usize;
 
+    /// Return the length of the authentication tag in bytes.
+    ///
+    /// The default is 16 bytes, the tag length of A128GCM, A192GCM, A256GCM and A128CBC-HS256.
+    fn tag_len(&self) -> usize {
+        16
+    }
+
     fn encrypt(
         &self,
         key: &[u8],
@@ -29,6 +37,63 @@ pub trait JweContentEncryption: Debug + Send + Sync {
         tag: Option<&[u8]>,
     ) -> Result<Vec<u8>, JoseError>;
 
+    /// Decrypt the buffer in place where the backend allows.
+    ///
+    /// The default implementation decrypts by `decrypt` and replaces the contents of the buffer.
+    /// The buffer is left unchanged when failed.
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - The content encryption key.
+    /// * `iv` - The initialization vector.
+    /// * `buf` - The encrypted message that is replaced by the decrypted message.
+    /// * `aad` - The additional authenticated data.
+    /// * `tag` - The authentication tag.
+    fn decrypt_in_place(
+        &self,
+        key: &[u8],
+        iv: Option<&[u8]>,
+        buf: &mut Vec<u8>,
+        aad: &[u8],
+        tag: Option<&[u8]>,
+    ) -> Result<(), JoseError> {
+        let message = self.decrypt(key, iv, buf, aad, tag)?;
+        buf.clear();
+        buf.extend_from_slice(&message);
+        Ok(())
+    }
+
+    /// Encrypt the message read from the reader, write the encrypted message to the writer
+    /// and return the authentication tag.
+    ///
+    /// The default implementation reads the whole message and encrypts it by `encrypt`.
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - The content encryption key.
+    /// * `iv` - The initialization vector.
+    /// * `input` - The reader of the message.
+    /// * `aad` - The additional authenticated data.
+    /// * `output` - The writer of the encrypted message.
+    fn encrypt_to_writer(
+        &self,
+        key: &[u8],
+        iv: Option<&[u8]>,
+        input: &mut dyn Read,
+        aad: &[u8],
+        output: &mut dyn Write,
+    ) -> Result<Option<Vec<u8>>, JoseError> {
+        let mut message = Vec::new();
+        input
+            .read_to_end(&mut message)
+            .map_err(|err| JoseError::InvalidJweFormat(err.into()))?;
+        let (encrypted_message, tag) = self.encrypt(key, iv, &message, aad)?;
+        output
+            .write_all(&encrypted_message)
+            .map_err(|err| JoseError::InvalidJweFormat(err.into()))?;
+        Ok(tag)
+    }
+
     fn box_clone(&self) -> Box<dyn JweContentEncryption>;
 }
 
@@ -2,28 +2,80 @@ use std::borrow::Cow;
 use std::cmp::Eq;
 use std::collections::{BTreeMap, BTreeSet};
 use std::fmt::Debug;
+use std::io::{Read, Write};
 
-use anyhow::bail;
+use anyhow::{anyhow, bail};
 
 use crate::jwe::enc::{A128CBC_HS256, A128GCM, A192CBC_HS384, A192GCM, A256CBC_HS512, A256GCM};
+use crate::jwe::jwe_algorithm::header_assuming_alg;
+use crate::jwe::jwe_json_aad::JsonAad;
 use crate::jwe::zip::Def;
 use crate::jwe::{
-    JweCompression, JweContentEncryption, JweDecrypter, JweEncrypter, JweHeader, JweHeaderSet,
+    self, JweCompression, JweContentEncryption, JweDecrypter, JweEncrypter, JweHeader,
+    JweHeaderSet, KeyLengthError,
 };
-use crate::util;
+use crate::jwk::JwkSet;
+use crate::util::{self, CompactError, OpensslRandomSource, RandomSource};
 use crate::{JoseError, JoseHeader, Map, Value};
 
-#[derive(Debug, Eq, PartialEq, Clone)]
+/// Header parameter names registered in the IANA "JSON Web Signature and Encryption Header
+/// Parameters" registry that are meaningful in a JWE.
+const REGISTERED_HEADER_CLAIMS: &[&str] = &[
+    "alg", "enc", "zip", "jku", "jwk", "kid", "x5u", "x5c", "x5t", "x5t#S256", "typ", "cty",
+    "crit", "epk", "apu", "apv", "iv", "tag", "p2s", "p2c", "url", "nonce", "iss", "sub", "aud",
+    "b64", "ppt",
+];
+
+/// The IV, the ciphertext and the authentication tag.
+pub(crate) type EncryptedContent = (Vec<u8>, Vec<u8>, Vec<u8>);
+
+/// The content encryption, the output header, the content encryption key and the encrypted key.
+type CompactParts<'a> = (
+    &'a dyn JweContentEncryption,
+    JweHeader,
+    Cow<'a, [u8]>,
+    Option<Vec<u8>>,
+);
+
+#[derive(Debug, Clone)]
 pub struct JweContext {
     acceptable_criticals: BTreeSet<String>,
+    assumes_missing_algorithm: bool,
+    strict_header: bool,
+    skip_ineffective_compression: bool,
+    uses_recommended_content_encryption: bool,
+    max_recipients: usize,
+    random_source: Option<Box<dyn RandomSource>>,
     compressions: BTreeMap<String, Box<dyn JweCompression>>,
     content_encryptions: BTreeMap<String, Box<dyn JweContentEncryption>>,
 }
 
+/// The random source is not compared, because it has no notion of equality.
+impl PartialEq for JweContext {
+    fn eq(&self, other: &Self) -> bool {
+        self.acceptable_criticals == other.acceptable_criticals
+            && self.assumes_missing_algorithm == other.assumes_missing_algorithm
+            && self.strict_header == other.strict_header
+            && self.skip_ineffective_compression == other.skip_ineffective_compression
+            && self.uses_recommended_content_encryption == other.uses_recommended_content_encryption
+            && self.max_recipients == other.max_recipients
+            && self.compressions == other.compressions
+            && self.content_encryptions == other.content_encryptions
+    }
+}
+
+impl Eq for JweContext {}
+
 impl JweContext {
     pub fn new() -> Self {
         Self {
             acceptable_criticals: BTreeSet::new(),
+            assumes_missing_algorithm: false,
+            strict_header: false,
+            skip_ineffective_compression: false,
+            uses_recommended_content_encryption: false,
+            max_recipients: 16,
+            random_source: None,
             compressions: {
                 let compressions: Vec<Box<dyn JweCompression>> = vec![Box::new(Def)];
 
@@ -79,6 +131,160 @@ impl JweContext {
         self.acceptable_criticals.remove(name);
     }
 
+    /// Test the decrypter's algorithm is assumed when the alg header claim is missing.
+    pub fn is_assumes_missing_algorithm(&self) -> bool {
+        self.assumes_missing_algorithm
+    }
+
+    /// Set whether the decrypter's algorithm is assumed when the alg header claim is missing.
+    ///
+    /// This is a compatibility option for non-compliant producers and is disabled by default.
+    ///
+    /// # Arguments
+    ///
+    /// * `value` - true if the missing alg header claim is allowed
+    pub fn set_assumes_missing_algorithm(&mut self, value: bool) {
+        self.assumes_missing_algorithm = value;
+    }
+
+    /// Test unknown header claims in the registered name space are rejected.
+    pub fn is_strict_header(&self) -> bool {
+        self.strict_header
+    }
+
+    /// Set whether unknown header claims in the registered name space are rejected.
+    ///
+    /// A header claim name consisting of up to three lowercase ASCII letters or digits is treated
+    /// as the registered name space, so draft-era parameters like "int" or "kdf" are rejected.
+    /// Other names are treated as private extensions and are always accepted.
+    ///
+    /// # Arguments
+    ///
+    /// * `value` - true if unknown registered header claims are rejected
+    pub fn set_strict_header(&mut self, value: bool) {
+        self.strict_header = value;
+    }
+
+    /// Test the compression is skipped when it doesn't reduce the payload size.
+    pub fn is_skip_ineffective_compression(&self) -> bool {
+        self.skip_ineffective_compression
+    }
+
+    /// Set whether the compression is skipped when it doesn't reduce the payload size.
+    ///
+    /// When skipped, the payload is encrypted uncompressed and the zip header claim is removed.
+    ///
+    /// # Arguments
+    ///
+    /// * `value` - true if the ineffective compression is skipped
+    pub fn set_skip_ineffective_compression(&mut self, value: bool) {
+        self.skip_ineffective_compression = value;
+    }
+
+    /// Test the recommended content encryption of the algorithm is used when the enc header
+    /// claim is missing.
+    pub fn is_uses_recommended_content_encryption(&self) -> bool {
+        self.uses_recommended_content_encryption
+    }
+
+    /// Set whether the recommended content encryption of the algorithm is used
+    /// when the enc header claim is missing in compact serialization.
+    ///
+    /// This is disabled by default, so the enc header claim is required.
+    ///
+    /// # Arguments
+    ///
+    /// * `value` - true if the recommended content encryption is used
+    pub fn set_uses_recommended_content_encryption(&mut self, value: bool) {
+        self.uses_recommended_content_encryption = value;
+    }
+
+    /// Return the maximum number of recipients in json serialization.
+    pub fn max_recipients(&self) -> usize {
+        self.max_recipients
+    }
+
+    /// Set the maximum number of recipients in json serialization.
+    ///
+    /// The input that has more recipients is rejected before any decryption attempt.
+    /// The default is 16.
+    ///
+    /// # Arguments
+    ///
+    /// * `value` - a maximum number of recipients
+    pub fn set_max_recipients(&mut self, value: usize) {
+        self.max_recipients = value;
+    }
+
+    /// Return the random source for content encryption keys and initialization vectors.
+    pub fn random_source(&self) -> &dyn RandomSource {
+        match &self.random_source {
+            Some(val) => val.as_ref(),
+            None => &OpensslRandomSource,
+        }
+    }
+
+    /// Set the random source for content encryption keys and initialization vectors.
+    ///
+    /// The default is a OpenSSL random source. It is also passed to the encrypters and
+    /// decrypters for their random values, like the PBES2 salts or the AES GCM key wrapping IVs.
+    /// The ECDH-ES ephemeral keys and the RSA paddings are generated by OpenSSL and not affected.
+    ///
+    /// # Arguments
+    ///
+    /// * `random_source` - a random source
+    pub fn set_random_source(&mut self, random_source: Box<dyn RandomSource>) {
+        self.random_source = Some(random_source);
+    }
+
+    fn random_bytes(&self, len: usize) -> Result<Vec<u8>, JoseError> {
+        let mut vec = vec![0; len];
+        self.random_source().fill(&mut vec)?;
+        Ok(vec)
+    }
+
+    fn compress_payload(
+        &self,
+        compression: Option<&dyn JweCompression>,
+        payload: &[u8],
+    ) -> anyhow::Result<Option<Vec<u8>>> {
+        let compression = match compression {
+            Some(val) => val,
+            None => return Ok(None),
+        };
+
+        let compressed = compression.compress(payload)?;
+        if self.skip_ineffective_compression && compressed.len() >= payload.len() {
+            return Ok(None);
+        }
+        Ok(Some(compressed))
+    }
+
+    fn check_header_claims(&self, header: &JweHeader) -> anyhow::Result<()> {
+        for key in ["alg", "enc"] {
+            if let Some(Value::String(val)) = header.claim(key) {
+                if val == "none" {
+                    bail!("The JWE {} header claim must not be none.", key);
+                }
+            }
+        }
+
+        if !self.strict_header {
+            return Ok(());
+        }
+
+        for key in header.claims_set().keys() {
+            let is_registered_space = key.len() <= 3
+                && key
+                    .bytes()
+                    .all(|b| b.is_ascii_lowercase() || b.is_ascii_digit());
+            if is_registered_space && !REGISTERED_HEADER_CLAIMS.contains(&key.as_str()) {
+                bail!("An unknown registered header claim is not allowed: {}", key);
+            }
+        }
+        Ok(())
+    }
+
     /// Get a compression algorithm for zip header claim value.
     ///
     /// # Arguments
@@ -122,6 +328,20 @@ impl JweContext {
         }
     }
 
+    fn get_content_encryption_for(
+        &self,
+        header: &JweHeader,
+    ) -> anyhow::Result<&dyn JweContentEncryption> {
+        match header.claim("enc") {
+            Some(Value::String(val)) => match self.get_content_encryption(val) {
+                Some(val2) => Ok(val2),
+                None => bail!("A content encryption is not registered: {}", val),
+            },
+            Some(_) => bail!("A enc header claim must be a string."),
+            None => bail!("A enc header claim is required."),
+        }
+    }
+
     /// Add a content encryption algorithm for enc header claim name.
     ///
     /// # Arguments
@@ -179,14 +399,6 @@ impl JweContext {
                 None => bail!("A encrypter is not found."),
             };
 
-            let cencryption = match header.content_encryption() {
-                Some(enc) => match self.get_content_encryption(enc) {
-                    Some(val) => val,
-                    None => bail!("A content encryption is not registered: {}", enc),
-                },
-                None => bail!("A enc header claim is required."),
-            };
-
             let compression = match header.compression() {
                 Some(zip) => match self.get_compression(zip) {
                     Some(val) => Some(val),
@@ -195,41 +407,21 @@ impl JweContext {
                 None => None,
             };
 
-            let mut out_header = header.clone();
+            let (cencryption, mut out_header, key, encrypted_key) =
+                self.prepare_compact(header, encrypter)?;
 
-            let key_len = cencryption.key_len();
-            let key = match encrypter.compute_content_encryption_key(
-                cencryption,
-                &header,
-                &mut out_header,
-            )? {
-                Some(val) => val,
-                None => Cow::Owned(util::random_bytes(key_len)),
-            };
-
-            let encrypted_key = encrypter.encrypt(&key, &header, &mut out_header)?;
-            if let None = header.claim("kid") {
-                if let Some(key_id) = encrypter.key_id() {
-                    out_header.set_key_id(key_id);
-                }
+            let compressed = self.compress_payload(compression, payload)?;
+            if compression.is_some() && compressed.is_none() {
+                out_header.set_claim("zip", None)?;
             }
-
-            out_header.set_algorithm(encrypter.algorithm().name());
+            let content = compressed.as_deref().unwrap_or(payload);
 
             let header_bytes = serde_json::to_vec(out_header.claims_set())?;
             let header_b64 = util::encode_base64_urlsafe_nopad(header_bytes);
 
-            let compressed;
-            let content = if let Some(compression) = compression {
-                compressed = compression.compress(payload)?;
-                &compressed
-            } else {
-                payload
-            };
-
             let iv_vec;
             let iv = if cencryption.iv_len() > 0 {
-                iv_vec = util::random_bytes(cencryption.iv_len());
+                iv_vec = self.random_bytes(cencryption.iv_len())?;
                 Some(iv_vec.as_slice())
             } else {
                 None
@@ -276,6 +468,149 @@ impl JweContext {
         })
     }
 
+    /// Return a representation of the data that is formatted by compact serialization
+    /// to the writer.
+    ///
+    /// The payload is read and encrypted by chunks where the content encryption allows,
+    /// unless the zip header claim is present.
+    ///
+    /// # Arguments
+    ///
+    /// * `payload` - The reader of the payload data.
+    /// * `header` - The JWE header claims.
+    /// * `encrypter` - The JWE encrypter.
+    /// * `out` - The writer of the representation.
+    pub fn serialize_compact_to_writer(
+        &self,
+        mut payload: impl Read,
+        header: &JweHeader,
+        encrypter: &dyn JweEncrypter,
+        mut out: impl Write,
+    ) -> Result<(), JoseError> {
+        if header.compression().is_some() {
+            let mut vec = Vec::new();
+            payload
+                .read_to_end(&mut vec)
+                .map_err(|err| JoseError::InvalidJweFormat(err.into()))?;
+            let message = self.serialize_compact(&vec, header, encrypter)?;
+            return out
+                .write_all(message.as_bytes())
+                .map_err(|err| JoseError::InvalidJweFormat(err.into()));
+        }
+
+        (|| -> anyhow::Result<()> {
+            let (cencryption, out_header, key, encrypted_key) =
+                self.prepare_compact(header, encrypter)?;
+
+            let header_bytes = serde_json::to_vec(out_header.claims_set())?;
+            let header_b64 = util::encode_base64_urlsafe_nopad(header_bytes);
+
+            let iv_vec;
+            let iv = if cencryption.iv_len() > 0 {
+                iv_vec = self.random_bytes(cencryption.iv_len())?;
+                Some(iv_vec.as_slice())
+            } else {
+                None
+            };
+
+            out.write_all(header_b64.as_bytes())?;
+            out.write_all(b".")?;
+            if let Some(val) = &encrypted_key {
+                out.write_all(util::encode_base64_urlsafe_nopad(val).as_bytes())?;
+            }
+            out.write_all(b".")?;
+            if let Some(val) = iv {
+                out.write_all(util::encode_base64_urlsafe_nopad(val).as_bytes())?;
+            }
+            out.write_all(b".")?;
+
+            let tag = {
+                let mut writer = util::Base64UrlWriter::new(&mut out);
+                let tag = cencryption.encrypt_to_writer(
+                    &key,
+                    iv,
+                    &mut payload,
+                    header_b64.as_bytes(),
+                    &mut writer,
+                )?;
+                writer.finish()?;
+                tag
+            };
+
+            out.write_all(b".")?;
+            if let Some(val) = &tag {
+                out.write_all(util::encode_base64_urlsafe_nopad(val).as_bytes())?;
+            }
+            out.flush()?;
+            Ok(())
+        })()
+        .map_err(|err| match err.downcast::<JoseError>() {
+            Ok(err) => err,
+            Err(err) => JoseError::InvalidJweFormat(err),
+        })
+    }
+
+    /// Return the content encryption, the output header, the content encryption key
+    /// and the encrypted key for compact serialization.
+    fn prepare_compact<'a>(
+        &'a self,
+        header: &JweHeader,
+        encrypter: &'a dyn JweEncrypter,
+    ) -> anyhow::Result<CompactParts<'a>> {
+        let enc = match header.content_encryption() {
+            Some(val) => val,
+            None if self.uses_recommended_content_encryption => {
+                encrypter.algorithm().recommended_content_encryption()
+            }
+            None => bail!("A enc header claim is required."),
+        };
+        let cencryption = match self.get_content_encryption(enc) {
+            Some(val) => val,
+            None => bail!("A content encryption is not registered: {}", enc),
+        };
+
+        match header.algorithm() {
+            Some(val) if val != encrypter.algorithm().name() => bail!(
+                "The JWE alg header claim is not {}: {}",
+                encrypter.algorithm().name(),
+                val
+            ),
+            _ => {}
+        }
+
+        let mut out_header = header.clone();
+        if header.content_encryption().is_none() {
+            out_header.set_content_encryption(enc);
+        }
+
+        let key_len = cencryption.key_len();
+        let key =
+            match encrypter.compute_content_encryption_key(cencryption, header, &mut out_header)? {
+                Some(val) => val,
+                None if encrypter.algorithm().key_management_mode().is_direct() => bail!(
+                    "The {} algorithm must determine a content encryption key.",
+                    encrypter.algorithm().name()
+                ),
+                None => Cow::Owned(self.random_bytes(key_len)?),
+            };
+
+        let encrypted_key = encrypter.encrypt_with_random_source(
+            &key,
+            header,
+            &mut out_header,
+            self.random_source(),
+        )?;
+        if header.claim("kid").is_none() {
+            if let Some(key_id) = encrypter.key_id() {
+                out_header.set_key_id(key_id);
+            }
+        }
+
+        out_header.set_algorithm(encrypter.algorithm().name());
+
+        Ok((cencryption, out_header, key, encrypted_key))
+    }
+
     /// Return a representation of the data that is formatted by general json serialization.
     ///
     /// # Arguments
@@ -346,6 +681,26 @@ impl JweContext {
                 }
             };
 
+            let compressed = self.compress_payload(compression, payload)?;
+            let uncompressed_header;
+            let header = match header {
+                Some(val) if compression.is_some() && compressed.is_none() => {
+                    let mut val = val.clone();
+                    val.set_claim("zip", None, true)?;
+                    uncompressed_header = val;
+                    Some(&uncompressed_header)
+                }
+                _ => header,
+            };
+
+            if let Some(val) = header {
+                for key in ["alg", "enc"] {
+                    if val.claims_set(false).contains_key(key) {
+                        bail!("The JWE {} header claim must be protected.", key);
+                    }
+                }
+            }
+
             let merged_map = match header {
                 Some(val) => val.to_map(),
                 None => Map::new(),
@@ -362,6 +717,9 @@ impl JweContext {
 
                 if let Some(val) = recipient_header {
                     for (key, value) in val.claims_set() {
+                        if key == "crit" || key == "zip" || key == "enc" {
+                            bail!("The JWE {} header claim must be protected.", key);
+                        }
                         if merged_map.contains_key(key) {
                             bail!("Duplicate key exists: {}", key);
                         }
@@ -419,7 +777,11 @@ impl JweContext {
 
                 match merged.algorithm() {
                     Some(val) if val == encrypter.algorithm().name() => {}
-                    Some(_) => bail!("A signer is unmatched."),
+                    Some(val) => bail!(
+                        "The JWE alg header claim is not {}: {}",
+                        encrypter.algorithm().name(),
+                        val
+                    ),
                     None => {
                         recipient_header.set_algorithm(encrypter.algorithm().name().to_string());
                     }
@@ -443,11 +805,11 @@ impl JweContext {
 
             let key = match &selected_key {
                 Some(val) => Cow::Borrowed(val.as_ref()),
-                None => Cow::Owned(util::random_bytes(cencryption.key_len())),
+                None => Cow::Owned(self.random_bytes(cencryption.key_len())?),
             };
 
             let iv = if cencryption.iv_len() > 0 {
-                Some(util::random_bytes(cencryption.iv_len()))
+                Some(self.random_bytes(cencryption.iv_len())?)
             } else {
                 None
             };
@@ -467,35 +829,16 @@ impl JweContext {
             };
 
             let aad_b64 = match aad {
-                Some(val) => Some(util::encode_base64_urlsafe_nopad(val)),
-                None => None,
+                Some(val) if !val.is_empty() => Some(util::encode_base64_urlsafe_nopad(val)),
+                _ => None,
             };
 
-            let mut full_aad = String::with_capacity({
-                let mut full_aad_capacity = 1;
-                if let Some(val) = &protected_b64 {
-                    full_aad_capacity += val.len();
-                }
-                if let Some(val) = &aad_b64 {
-                    full_aad_capacity += val.len();
-                }
-                full_aad_capacity
-            });
-            if let Some(val) = &protected_b64 {
-                full_aad.push_str(&val);
-            }
-            if let Some(val) = &aad_b64 {
-                full_aad.push_str(".");
-                full_aad.push_str(&val);
-            }
+            let full_aad = JsonAad::new()
+                .set_protected(protected_b64.as_deref())
+                .set_aad(aad_b64.as_deref())
+                .build();
 
-            let compressed;
-            let content = if let Some(compression) = compression {
-                compressed = compression.compress(payload)?;
-                &compressed
-            } else {
-                payload
-            };
+            let content = compressed.as_deref().unwrap_or(payload);
 
             let (ciphertext, tag) =
                 cencryption.encrypt(&key, iv.as_deref(), content, full_aad.as_bytes())?;
@@ -520,19 +863,32 @@ impl JweContext {
                 }
             }
 
+            #[cfg(feature = "rayon")]
+            let encrypted_keys = wrap_keys_parallel(
+                &key,
+                &merged_list,
+                &mut recipient_header_list,
+                &encrypter_list,
+                self.random_source(),
+            )?;
+            #[cfg(not(feature = "rayon"))]
+            let encrypted_keys = wrap_keys_serial(
+                &key,
+                &merged_list,
+                &mut recipient_header_list,
+                &encrypter_list,
+                self.random_source(),
+            )?;
+
             json.push_str(if writed { "," } else { "{" });
             json.push_str("\"recipients\":[");
-            for i in 0..recipient_headers.len() {
+            for (i, (header, encrypted_key)) in
+                recipient_header_list.iter().zip(encrypted_keys).enumerate()
+            {
                 if i > 0 {
                     json.push_str(",");
                 }
 
-                let merged = &merged_list[i];
-                let mut header = &mut recipient_header_list[i];
-                let encrypter = encrypter_list[i];
-
-                let encrypted_key = encrypter.encrypt(&key, &merged, &mut header)?;
-
                 if header.len() == 0 {
                     bail!("The per-recipient header must not be empty");
                 }
@@ -642,6 +998,26 @@ impl JweContext {
                 }
             };
 
+            let compressed = self.compress_payload(compression, payload)?;
+            let uncompressed_header;
+            let header = match header {
+                Some(val) if compression.is_some() && compressed.is_none() => {
+                    let mut val = val.clone();
+                    val.set_claim("zip", None, true)?;
+                    uncompressed_header = val;
+                    Some(&uncompressed_header)
+                }
+                _ => header,
+            };
+
+            if let Some(val) = header {
+                for key in ["alg", "enc"] {
+                    if val.claims_set(false).contains_key(key) {
+                        bail!("The JWE {} header claim must be protected.", key);
+                    }
+                }
+            }
+
             let mut merged_map = match header {
                 Some(val) => val.to_map(),
                 None => Map::new(),
@@ -649,6 +1025,9 @@ impl JweContext {
 
             if let Some(val) = recipient_header {
                 for (key, value) in val.claims_set() {
+                    if key == "crit" || key == "zip" || key == "enc" {
+                        bail!("The JWE {} header claim must be protected.", key);
+                    }
                     if merged_map.contains_key(key) {
                         bail!("Duplicate key exists: {}", key);
                     }
@@ -683,14 +1062,27 @@ impl JweContext {
                 &mut protected,
             )? {
                 Some(val) => val,
-                None => Cow::Owned(util::random_bytes(cencryption.key_len())),
+                None if encrypter.algorithm().key_management_mode().is_direct() => bail!(
+                    "The {} algorithm must determine a content encryption key.",
+                    encrypter.algorithm().name()
+                ),
+                None => Cow::Owned(self.random_bytes(cencryption.key_len())?),
             };
 
-            let encrypted_key = encrypter.encrypt(&key, &merged, &mut protected)?;
+            let encrypted_key = encrypter.encrypt_with_random_source(
+                &key,
+                &merged,
+                &mut protected,
+                self.random_source(),
+            )?;
 
             match merged.algorithm() {
                 Some(val) if val == encrypter.algorithm().name() => {}
-                Some(_) => bail!("A signer is unmatched."),
+                Some(val) => bail!(
+                    "The JWE alg header claim is not {}: {}",
+                    encrypter.algorithm().name(),
+                    val
+                ),
                 None => {
                     protected.set_algorithm(encrypter.algorithm().name().to_string());
                 }
@@ -704,7 +1096,7 @@ impl JweContext {
 
             let iv_vec;
             let iv = if cencryption.iv_len() > 0 {
-                iv_vec = util::random_bytes(cencryption.iv_len());
+                iv_vec = self.random_bytes(cencryption.iv_len())?;
                 Some(iv_vec.as_slice())
             } else {
                 None
@@ -719,35 +1111,16 @@ impl JweContext {
             };
 
             let aad_b64 = match aad {
-                Some(val) => Some(util::encode_base64_urlsafe_nopad(val)),
-                None => None,
+                Some(val) if !val.is_empty() => Some(util::encode_base64_urlsafe_nopad(val)),
+                _ => None,
             };
 
-            let mut full_aad = String::with_capacity({
-                let mut full_aad_capacity = 1;
-                if let Some(val) = &protected_b64 {
-                    full_aad_capacity += val.len();
-                }
-                if let Some(val) = &aad_b64 {
-                    full_aad_capacity += val.len();
-                }
-                full_aad_capacity
-            });
-            if let Some(val) = &protected_b64 {
-                full_aad.push_str(&val);
-            }
-            if let Some(val) = &aad_b64 {
-                full_aad.push_str(".");
-                full_aad.push_str(&val);
-            }
+            let full_aad = JsonAad::new()
+                .set_protected(protected_b64.as_deref())
+                .set_aad(aad_b64.as_deref())
+                .build();
 
-            let compressed;
-            let content = if let Some(compression) = compression {
-                compressed = compression.compress(payload)?;
-                &compressed
-            } else {
-                payload
-            };
+            let content = compressed.as_deref().unwrap_or(payload);
 
             let (ciphertext, tag) = cencryption.encrypt(&key, iv, content, full_aad.as_bytes())?;
 
@@ -817,6 +1190,27 @@ impl JweContext {
         })
     }
 
+    /// Deserialize the input that is formatted by compact or json serialization.
+    ///
+    /// The input is treated as json serialization when it starts with "{" except for
+    /// leading whitespaces, and as compact serialization otherwise.
+    ///
+    /// # Arguments
+    ///
+    /// * `input` - The input data.
+    /// * `decrypter` - The JWE decrypter.
+    pub fn deserialize(
+        &self,
+        input: impl AsRef<[u8]>,
+        decrypter: &dyn JweDecrypter,
+    ) -> Result<(Vec<u8>, JweHeader), JoseError> {
+        let input = input.as_ref();
+        match input.iter().find(|b| !b.is_ascii_whitespace()) {
+            Some(b'{') => self.deserialize_json(input, decrypter),
+            _ => self.deserialize_compact(input, decrypter),
+        }
+    }
+
     /// Deserialize the input that is formatted by compact serialization.
     ///
     /// # Arguments
@@ -846,17 +1240,26 @@ impl JweContext {
         F: Fn(&JweHeader) -> Result<Option<&'a dyn JweDecrypter>, JoseError>,
     {
         (|| -> anyhow::Result<(Vec<u8>, JweHeader)> {
-            let input = input.as_ref();
+            let input = input.as_ref().trim_ascii();
+            if input.iter().any(|b| b.is_ascii_whitespace()) {
+                bail!("The compact serialization form of JWE must not contain whitespaces.");
+            }
+
             let indexies: Vec<usize> = input
                 .iter()
                 .enumerate()
                 .filter(|(_, b)| **b == b'.' as u8)
                 .map(|(pos, _)| pos)
                 .collect();
-            if indexies.len() != 4 {
-                bail!(
-                    "The compact serialization form of JWE must be five parts separated by colon."
-                );
+            match indexies.len() {
+                4 => {}
+                2 => bail!(
+                    "The compact serialization form of JWE must be five parts separated by colon, but it has three parts like JWS."
+                ),
+                len => bail!(
+                    "The compact serialization form of JWE must be five parts separated by colon: {} parts",
+                    len + 1
+                ),
             }
 
             let header_b64 = &input[0..indexies[0]];
@@ -864,7 +1267,8 @@ impl JweContext {
             let encrypted_key_b64 = &input[(indexies[0] + 1)..(indexies[1])];
             let encrypted_key_vec;
             let encrypted_key = if encrypted_key_b64.len() > 0 {
-                encrypted_key_vec = util::decode_base64_urlsafe_no_pad(encrypted_key_b64)?;
+                encrypted_key_vec =
+                    CompactError::decode_segment(encrypted_key_b64, 2, "encrypted key")?;
                 Some(encrypted_key_vec.as_slice())
             } else {
                 None
@@ -873,41 +1277,44 @@ impl JweContext {
             let iv_b64 = &input[(indexies[1] + 1)..(indexies[2])];
             let iv_vec;
             let iv = if iv_b64.len() > 0 {
-                iv_vec = util::decode_base64_urlsafe_no_pad(iv_b64)?;
+                iv_vec = CompactError::decode_segment(iv_b64, 3, "iv")?;
                 Some(iv_vec.as_slice())
             } else {
                 None
             };
 
             let ciphertext_b64 = &input[(indexies[2] + 1)..(indexies[3])];
-            let ciphertext = util::decode_base64_urlsafe_no_pad(ciphertext_b64)?;
+            let ciphertext = CompactError::decode_segment(ciphertext_b64, 4, "ciphertext")?;
 
             let tag_b64 = &input[(indexies[3] + 1)..];
             let tag_vec;
             let tag = if tag_b64.len() > 0 {
-                tag_vec = util::decode_base64_urlsafe_no_pad(tag_b64)?;
+                tag_vec = CompactError::decode_segment(tag_b64, 5, "tag")?;
                 Some(tag_vec.as_slice())
             } else {
                 None
             };
 
-            let header = util::decode_base64_urlsafe_no_pad(header_b64)?;
-            let merged: Map<String, Value> = serde_json::from_slice(&header)?;
+            let header = CompactError::decode_segment(header_b64, 1, "header")?;
+            let merged: Map<String, Value> = CompactError::parse_segment(&header, 1, "header")?;
             let merged = JweHeader::from_map(merged)?;
+            self.check_header_claims(&merged)?;
 
             let decrypter = match selector(&merged)? {
                 Some(val) => val,
                 None => bail!("A decrypter is not found."),
             };
 
-            let cencryption = match merged.claim("enc") {
-                Some(Value::String(val)) => match self.get_content_encryption(val) {
-                    Some(val2) => val2,
-                    None => bail!("A content encryption is not registered: {}", val),
-                },
-                Some(_) => bail!("A enc header claim must be a string."),
-                None => bail!("A enc header claim is required."),
-            };
+            let cencryption = self.get_content_encryption_for(&merged)?;
+            if let Some(val) = tag {
+                if val.len() != cencryption.tag_len() {
+                    bail!(
+                        "The tag size must be {}: {}",
+                        cencryption.tag_len(),
+                        val.len()
+                    );
+                }
+            }
 
             let compression = match merged.claim("zip") {
                 Some(Value::String(val)) => match self.get_compression(val) {
@@ -926,25 +1333,50 @@ impl JweContext {
                     }
                 }
                 Some(_) => bail!("A alg header claim must be a string."),
+                None if self.assumes_missing_algorithm => {}
                 None => bail!("The JWE alg header claim is required."),
             }
 
-            match decrypter.key_id() {
-                Some(expected) => match merged.key_id() {
-                    Some(actual) if expected == actual => {}
-                    Some(actual) => bail!("The JWE kid header claim is mismatched: {}", actual),
-                    None => bail!("The JWE kid header claim is required."),
-                },
-                None => {}
+            match (decrypter.key_id(), merged.key_id()) {
+                (Some(expected), Some(actual)) if expected != actual => bail!(
+                    "The JWE kid header claim is mismatched with the decrypter {}: {}",
+                    expected,
+                    actual
+                ),
+                _ => {}
             }
 
-            let key = decrypter.decrypt(encrypted_key, cencryption, &merged)?;
+            if let (Some(expected), Some(actual)) = (
+                decrypter.expected_encrypted_key_len(cencryption),
+                encrypted_key,
+            ) {
+                if actual.len() != expected {
+                    bail!(
+                        "The encrypted_key size must be {}: {}",
+                        expected,
+                        actual.len()
+                    );
+                }
+            }
+
+            let key = if self.assumes_missing_algorithm {
+                let header = header_assuming_alg(decrypter.algorithm().name(), &merged)?;
+                decrypter.decrypt_with_random_source(
+                    encrypted_key,
+                    cencryption,
+                    &header,
+                    self.random_source(),
+                )?
+            } else {
+                decrypter.decrypt_with_random_source(
+                    encrypted_key,
+                    cencryption,
+                    &merged,
+                    self.random_source(),
+                )?
+            };
             if key.len() != cencryption.key_len() {
-                bail!(
-                    "The key size is expected to be {}: {}",
-                    cencryption.key_len(),
-                    key.len()
-                );
+                bail!(KeyLengthError::new(cencryption.key_len(), key.len()));
             }
 
             let content = cencryption.decrypt(&key, iv, &ciphertext, header_b64, tag)?;
@@ -961,6 +1393,92 @@ impl JweContext {
         })
     }
 
+    /// Decrypt the input that is formatted by compact serialization by using a JWK set,
+    /// and return the payload and the header.
+    ///
+    /// The candidates are the keys that have the kid header claim as the key ID,
+    /// or all keys in the JWK set when the kid header claim is absent.
+    ///
+    /// # Arguments
+    ///
+    /// * `input` - The input data.
+    /// * `jwk_set` - a JWK set.
+    /// * `allowed` - acceptable values for JWE algorithm header claim (alg).
+    pub fn decrypt_with_key_set(
+        &self,
+        input: impl AsRef<[u8]>,
+        jwk_set: &JwkSet,
+        allowed: &[&str],
+    ) -> Result<(Vec<u8>, JweHeader), JoseError> {
+        let input = input.as_ref();
+        let header = (|| -> anyhow::Result<JweHeader> {
+            let header = match input.iter().position(|b| *b == b'.') {
+                Some(pos) => &input[..pos],
+                None => bail!(
+                    "The compact serialization form of JWE must be five parts separated by colon."
+                ),
+            };
+            let header = CompactError::decode_segment(header, 1, "header")?;
+            let header: Map<String, Value> = CompactError::parse_segment(&header, 1, "header")?;
+            let header = JweHeader::from_map(header)?;
+            Ok(header)
+        })()
+        .map_err(|err| match err.downcast::<JoseError>() {
+            Ok(err) => err,
+            Err(err) => JoseError::InvalidJweFormat(err),
+        })?;
+
+        let alg = match header.algorithm() {
+            Some(val) => val,
+            None => {
+                return Err(JoseError::InvalidJweFormat(anyhow!(
+                    "The JWE alg header claim is required."
+                )))
+            }
+        };
+        if !allowed.contains(&alg) {
+            return Err(JoseError::InvalidJweFormat(anyhow!(
+                "The JWE alg header claim is not allowed: {}",
+                alg
+            )));
+        }
+        let candidates = match header.key_id() {
+            Some(val) => jwk_set.get(val),
+            None => jwk_set.keys(),
+        };
+
+        let mut last_error = None;
+        for jwk in candidates {
+            match jwk.algorithm() {
+                Some(val) if val != alg => continue,
+                _ => {}
+            }
+
+            let decrypter = match jwe::decrypter_from_jwk(alg, jwk) {
+                Ok(Some(val)) => val,
+                Ok(None) => {
+                    return Err(JoseError::InvalidJweFormat(anyhow!(
+                        "The algorithm is not supported for a JWK set: {}",
+                        alg
+                    )))
+                }
+                Err(err) => {
+                    last_error = Some(err);
+                    continue;
+                }
+            };
+
+            match self.deserialize_compact(input, decrypter.as_ref()) {
+                Ok(val) => return Ok(val),
+                Err(err) => last_error = Some(err),
+            }
+        }
+
+        Err(last_error.unwrap_or_else(|| {
+            JoseError::InvalidJweFormat(anyhow!("A key in the JWK set is not found."))
+        }))
+    }
+
     /// Deserialize the input that is formatted by json serialization.
     ///
     /// # Arguments
@@ -980,7 +1498,8 @@ impl JweContext {
                         return Ok(None);
                     }
                 }
-                _ => return Ok(None),
+                None if self.assumes_missing_algorithm => {}
+                None => return Ok(None),
             }
 
             match decrypter.key_id() {
@@ -1025,6 +1544,11 @@ impl JweContext {
                 Some(_) => bail!("The protected field must be a string."),
                 None => (None, None),
             };
+            // The content encryption is shared by all recipients, so it must be authenticated.
+            match &protected {
+                Some(val) if val.contains_key("enc") => {}
+                _ => bail!("The JWE enc header claim must be protected."),
+            }
             let unprotected = match map.remove("unprotected") {
                 Some(Value::Object(val)) => {
                     if val.len() == 0 {
@@ -1037,9 +1561,7 @@ impl JweContext {
             };
             let aad_b64 = match map.remove("aad") {
                 Some(Value::String(val)) => {
-                    if val.len() == 0 {
-                        bail!("The JWE aad field must be empty.");
-                    } else if !util::is_base64_urlsafe_nopad(&val) {
+                    if !util::is_base64_urlsafe_nopad(&val) {
                         bail!("The JWE aad field must be a base64 string.");
                     }
                     Some(val)
@@ -1087,6 +1609,13 @@ impl JweContext {
                     if vals.len() == 0 {
                         bail!("The recipients field must be empty.");
                     }
+                    if vals.len() > self.max_recipients {
+                        bail!(
+                            "The number of recipients must be {} or less: {}",
+                            self.max_recipients,
+                            vals.len()
+                        );
+                    }
                     let mut vec = Vec::with_capacity(vals.len());
                     for val in vals {
                         if let Value::Object(val) = val {
@@ -1148,20 +1677,23 @@ impl JweContext {
                 }
 
                 let merged = JweHeader::from_map(merged)?;
+                self.check_header_claims(&merged)?;
 
                 let decrypter = match selector(&merged)? {
                     Some(val) => val,
                     None => continue,
                 };
 
-                let cencryption = match merged.claim("enc") {
-                    Some(Value::String(val)) => match self.get_content_encryption(val) {
-                        Some(val2) => val2,
-                        None => bail!("A content encryption is not registered: {}", val),
-                    },
-                    Some(_) => bail!("A enc header claim must be string."),
-                    None => bail!("A enc header claim is required."),
-                };
+                let cencryption = self.get_content_encryption_for(&merged)?;
+                if let Some(val) = tag {
+                    if val.len() != cencryption.tag_len() {
+                        bail!(
+                            "The tag size must be {}: {}",
+                            cencryption.tag_len(),
+                            val.len()
+                        );
+                    }
+                }
 
                 let compression = match merged.claim("zip") {
                     Some(Value::String(val)) => match self.get_compression(val) {
@@ -1179,34 +1711,55 @@ impl JweContext {
                             bail!("The JWE alg header claim is not {}: {}", expected_alg, val);
                         }
                     }
+                    None if self.assumes_missing_algorithm => {}
                     None => bail!("The JWE alg header claim is required."),
                 }
 
-                match decrypter.key_id() {
-                    Some(expected) => match merged.key_id() {
-                        Some(actual) if expected == actual => {}
-                        Some(actual) => bail!("The JWE kid header claim is mismatched: {}", actual),
-                        None => bail!("The JWE kid header claim is required."),
-                    },
-                    None => {}
+                match (decrypter.key_id(), merged.key_id()) {
+                    (Some(expected), Some(actual)) if expected != actual => bail!(
+                        "The JWE kid header claim is mismatched with the decrypter {}: {}",
+                        expected,
+                        actual
+                    ),
+                    _ => {}
                 }
 
-                let mut full_aad = match protected_b64 {
-                    Some(val) => val,
-                    None => String::new(),
-                };
-                if let Some(val) = aad_b64 {
-                    full_aad.push_str(".");
-                    full_aad.push_str(&val);
+                let full_aad = JsonAad::new()
+                    .set_protected(protected_b64.as_deref())
+                    .set_aad(aad_b64.as_deref())
+                    .build();
+
+                if let (Some(expected), Some(actual)) = (
+                    decrypter.expected_encrypted_key_len(cencryption),
+                    encrypted_key,
+                ) {
+                    if actual.len() != expected {
+                        bail!(
+                            "The encrypted_key size must be {}: {}",
+                            expected,
+                            actual.len()
+                        );
+                    }
                 }
 
-                let key = decrypter.decrypt(encrypted_key, cencryption, &merged)?;
+                let key = if self.assumes_missing_algorithm {
+                    let header = header_assuming_alg(decrypter.algorithm().name(), &merged)?;
+                    decrypter.decrypt_with_random_source(
+                        encrypted_key,
+                        cencryption,
+                        &header,
+                        self.random_source(),
+                    )?
+                } else {
+                    decrypter.decrypt_with_random_source(
+                        encrypted_key,
+                        cencryption,
+                        &merged,
+                        self.random_source(),
+                    )?
+                };
                 if key.len() != cencryption.key_len() {
-                    bail!(
-                        "The key size is expected to be {}: {}",
-                        cencryption.key_len(),
-                        key.len()
-                    );
+                    bail!(KeyLengthError::new(cencryption.key_len(), key.len()));
                 }
 
                 let content =
@@ -1226,15 +1779,117 @@ impl JweContext {
             Err(err) => JoseError::InvalidJweFormat(err),
         })
     }
+
+    /// Encrypt the payload using the protected header as the additional authenticated data.
+    ///
+    /// A random IV is taken from the random source when the content encryption requires it.
+    /// The returned values are the IV, the ciphertext and the authentication tag; the IV and
+    /// the tag are empty when the content encryption does not use them.
+    ///
+    /// # Arguments
+    ///
+    /// * `cencryption` - The content encryption algorithm.
+    /// * `key` - The content encryption key.
+    /// * `header` - The JWE protected header claims.
+    /// * `payload` - The payload data.
+    pub fn encrypt_content(
+        &self,
+        cencryption: &dyn JweContentEncryption,
+        key: &[u8],
+        header: &JweHeader,
+        payload: &[u8],
+    ) -> Result<EncryptedContent, JoseError> {
+        let header_bytes = serde_json::to_vec(header.claims_set())
+            .map_err(|err| JoseError::InvalidJson(err.into()))?;
+        let header_b64 = util::encode_base64_urlsafe_nopad(header_bytes);
+
+        let iv = self.random_bytes(cencryption.iv_len())?;
+        let iv_opt = if iv.is_empty() {
+            None
+        } else {
+            Some(iv.as_slice())
+        };
+
+        let (ciphertext, tag) = cencryption.encrypt(key, iv_opt, payload, header_b64.as_bytes())?;
+        Ok((iv, ciphertext, tag.unwrap_or_default()))
+    }
+}
+
+/// Encrypt the content encryption key for each recipient in order.
+fn wrap_keys_serial(
+    key: &[u8],
+    merged_list: &[JweHeader],
+    recipient_header_list: &mut [JweHeader],
+    encrypter_list: &[&dyn JweEncrypter],
+    random_source: &dyn RandomSource,
+) -> Result<Vec<Option<Vec<u8>>>, JoseError> {
+    let mut encrypted_keys = Vec::with_capacity(merged_list.len());
+    for ((merged, header), encrypter) in merged_list
+        .iter()
+        .zip(recipient_header_list.iter_mut())
+        .zip(encrypter_list)
+    {
+        encrypted_keys.push(encrypter.encrypt_with_random_source(
+            key,
+            merged,
+            header,
+            random_source,
+        )?);
+    }
+    Ok(encrypted_keys)
+}
+
+/// Encrypt the content encryption key for each recipient concurrently.
+///
+/// The result is in the same order as the recipients.
+#[cfg(feature = "rayon")]
+fn wrap_keys_parallel(
+    key: &[u8],
+    merged_list: &[JweHeader],
+    recipient_header_list: &mut [JweHeader],
+    encrypter_list: &[&dyn JweEncrypter],
+    random_source: &dyn RandomSource,
+) -> Result<Vec<Option<Vec<u8>>>, JoseError> {
+    use rayon::prelude::*;
+
+    if merged_list.len() < 2 {
+        return wrap_keys_serial(
+            key,
+            merged_list,
+            recipient_header_list,
+            encrypter_list,
+            random_source,
+        );
+    }
+
+    merged_list
+        .par_iter()
+        .zip(recipient_header_list.par_iter_mut())
+        .zip(encrypter_list.par_iter())
+        .map(|((merged, header), encrypter)| {
+            encrypter.encrypt_with_random_source(key, merged, header, random_source)
+        })
+        .collect()
 }
 
 #[cfg(test)]
 mod tests {
+    use super::JweContext;
+    #[cfg(feature = "rayon")]
+    use super::{wrap_keys_parallel, wrap_keys_serial};
+    use crate::jwe::enc::A128GCM;
     use crate::jwe::{
         alg::direct::DirectJweAlgorithm, deserialize_compact, deserialize_json, serialize_compact,
-        serialize_flattened_json, serialize_general_json, JweHeader, JweHeaderSet,
+        serialize_flattened_json, serialize_general_json, JweAlgorithm, JweContentEncryption,
+        JweEncrypter, JweHeader, JweHeaderSet, KeyLengthError, A128GCMKW, A128KW,
+        PBES2_HS256_A128KW, RSA_OAEP_256,
     };
+    #[cfg(feature = "rayon")]
+    use crate::util::OpensslRandomSource;
+    use crate::util::{self, RandomSource};
+    use crate::{JoseError, Map, Value};
     use anyhow::Result;
+    use serde_json::json;
 
     const CONTENT_CIPHERS: [(&str, usize); 6] = [
         ("A128CBC-HS256", 32),
@@ -1301,4 +1956,781 @@ mod tests {
         }
         Ok(())
     }
+
+    #[test]
+    fn compact_without_alg() -> Result<()> {
+        let payload = b"hello world";
+        let key = util::random_bytes(16);
+        let jwe = encrypt_compact(r#"{"enc":"A128GCM"}"#, &A128GCM, &key, payload)?;
+
+        let decrypter = DirectJweAlgorithm::Dir.decrypter_from_bytes(&key)?;
+
+        let context = JweContext::new();
+        assert!(!context.is_assumes_missing_algorithm());
+        assert!(context.deserialize_compact(&jwe, &decrypter).is_err());
+
+        let mut context = JweContext::new();
+        context.set_assumes_missing_algorithm(true);
+        let (data, header) = context.deserialize_compact(&jwe, &decrypter)?;
+        assert_eq!(data, payload);
+        assert_eq!(header.algorithm(), None);
+
+        Ok(())
+    }
+
+    #[test]
+    fn compact_with_mismatched_enc() -> Result<()> {
+        let payload = b"hello world";
+        let key = util::random_bytes(16);
+        let jwe = encrypt_compact(r#"{"alg":"dir","enc":"A256GCM"}"#, &A128GCM, &key, payload)?;
+
+        let decrypter = DirectJweAlgorithm::Dir.decrypter_from_bytes(&key)?;
+        match deserialize_compact(&jwe, &decrypter) {
+            Err(JoseError::InvalidJweFormat(err)) => {
+                assert_eq!(err.to_string(), "The key size is expected to be 32: 16");
+                let err = err.downcast_ref::<KeyLengthError>().unwrap();
+                assert_eq!(err.expected(), 32);
+                assert_eq!(err.actual(), 16);
+            }
+            other => panic!("Mismatched enc was accepted: {:?}", other),
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn general_json_with_many_recipients() -> Result<()> {
+        let payload = b"hello world";
+        let keys: Vec<Vec<u8>> = (0..8).map(|_| util::random_bytes(16)).collect();
+
+        let mut encrypters = Vec::new();
+        let mut decrypters = Vec::new();
+        for (i, key) in keys.iter().enumerate() {
+            let mut encrypter = A128KW.encrypter_from_bytes(key)?;
+            encrypter.set_key_id(format!("kid-{}", i));
+            encrypters.push(encrypter);
+
+            let mut decrypter = A128KW.decrypter_from_bytes(key)?;
+            decrypter.set_key_id(format!("kid-{}", i));
+            decrypters.push(decrypter);
+        }
+
+        let mut header = JweHeaderSet::new();
+        header.set_content_encryption("A128GCM", true);
+        let recipients: Vec<(Option<&JweHeader>, &dyn JweEncrypter)> = encrypters
+            .iter()
+            .map(|val| (None, val as &dyn JweEncrypter))
+            .collect();
+        let json = serialize_general_json(payload, Some(&header), &recipients, None)?;
+
+        for (i, decrypter) in decrypters.iter().enumerate() {
+            let (dst_payload, dst_header) = deserialize_json(&json, decrypter)?;
+            assert_eq!(dst_payload, payload);
+            assert_eq!(dst_header.key_id(), Some(format!("kid-{}", i).as_str()));
+        }
+
+        // The parallel key wrapping must produce the same result as the serial one.
+        #[cfg(feature = "rayon")]
+        {
+            let cek = util::random_bytes(16);
+            let merged_list = vec![JweHeader::new(); encrypters.len()];
+            let encrypter_list: Vec<&dyn JweEncrypter> =
+                recipients.iter().map(|val| val.1).collect();
+
+            let mut serial_headers = merged_list.clone();
+            let serial = wrap_keys_serial(
+                &cek,
+                &merged_list,
+                &mut serial_headers,
+                &encrypter_list,
+                &OpensslRandomSource,
+            )?;
+
+            let mut parallel_headers = merged_list.clone();
+            let parallel = wrap_keys_parallel(
+                &cek,
+                &merged_list,
+                &mut parallel_headers,
+                &encrypter_list,
+                &OpensslRandomSource,
+            )?;
+
+            assert_eq!(serial, parallel);
+            assert_eq!(serial_headers, parallel_headers);
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn general_json_with_too_many_recipients() -> Result<()> {
+        let key = util::random_bytes(16);
+        let encrypter = A128KW.encrypter_from_bytes(&key)?;
+        let decrypter = A128KW.decrypter_from_bytes(&key)?;
+
+        let mut header = JweHeaderSet::new();
+        header.set_content_encryption("A128GCM", true);
+        let json =
+            serialize_general_json(b"hello world", Some(&header), &[(None, &encrypter)], None)?;
+
+        let mut map: Map<String, Value> = serde_json::from_str(&json)?;
+        let recipient = match map.get("recipients") {
+            Some(Value::Array(vals)) => vals[0].clone(),
+            other => panic!("The recipients field is not found: {:?}", other),
+        };
+        map.insert("recipients".to_string(), Value::Array(vec![recipient; 100]));
+        let json = serde_json::to_string(&map)?;
+
+        let context = JweContext::new();
+        assert_eq!(context.max_recipients(), 16);
+        match context.deserialize_json(&json, &decrypter) {
+            Err(JoseError::InvalidJweFormat(err)) => assert_eq!(
+                err.to_string(),
+                "The number of recipients must be 16 or less: 100"
+            ),
+            other => panic!("Too many recipients were accepted: {:?}", other),
+        }
+
+        let mut context = JweContext::new();
+        context.set_max_recipients(100);
+        let (payload, _) = context.deserialize_json(&json, &decrypter)?;
+        assert_eq!(payload, b"hello world");
+
+        Ok(())
+    }
+
+    fn encrypt_compact(
+        header: &str,
+        cencryption: &dyn JweContentEncryption,
+        key: &[u8],
+        payload: &[u8],
+    ) -> Result<String> {
+        let header_b64 = util::encode_base64_urlsafe_nopad(header);
+        let iv = util::random_bytes(cencryption.iv_len());
+        let (ciphertext, tag) =
+            cencryption.encrypt(key, Some(&iv), payload, header_b64.as_bytes())?;
+        Ok(format!(
+            "{}..{}.{}.{}",
+            header_b64,
+            util::encode_base64_urlsafe_nopad(&iv),
+            util::encode_base64_urlsafe_nopad(&ciphertext),
+            util::encode_base64_urlsafe_nopad(tag.unwrap_or_default()),
+        ))
+    }
+
+    #[test]
+    fn compact_with_strict_header() -> Result<()> {
+        let payload = b"hello world";
+        let key = util::random_bytes(16);
+        let decrypter = DirectJweAlgorithm::Dir.decrypter_from_bytes(&key)?;
+
+        let legacy = encrypt_compact(
+            r#"{"alg":"dir","enc":"A128GCM","int":"HS256"}"#,
+            &A128GCM,
+            &key,
+            payload,
+        )?;
+        let vendor = encrypt_compact(
+            r#"{"alg":"dir","enc":"A128GCM","x-vendor":"value"}"#,
+            &A128GCM,
+            &key,
+            payload,
+        )?;
+
+        let mut context = JweContext::new();
+        assert!(!context.is_strict_header());
+        let (dst_payload, _) = context.deserialize_compact(&legacy, &decrypter)?;
+        assert_eq!(dst_payload, payload);
+
+        context.set_strict_header(true);
+        match context.deserialize_compact(&legacy, &decrypter) {
+            Err(JoseError::InvalidJweFormat(_)) => {}
+            other => panic!("Legacy header claim was accepted: {:?}", other),
+        }
+        let (dst_payload, _) = context.deserialize_compact(&vendor, &decrypter)?;
+        assert_eq!(dst_payload, payload);
+
+        Ok(())
+    }
+
+    #[test]
+    fn compact_with_skip_ineffective_compression() -> Result<()> {
+        let payload = util::random_bytes(4096);
+        let key = util::random_bytes(16);
+        let encrypter = DirectJweAlgorithm::Dir.encrypter_from_bytes(&key)?;
+        let decrypter = DirectJweAlgorithm::Dir.decrypter_from_bytes(&key)?;
+
+        let mut header = JweHeader::new();
+        header.set_content_encryption("A128GCM");
+        let uncompressed = serialize_compact(&payload, &header, &encrypter)?;
+
+        header.set_compression("DEF");
+        let mut context = JweContext::new();
+        assert!(!context.is_skip_ineffective_compression());
+        let compressed = context.serialize_compact(&payload, &header, &encrypter)?;
+        assert!(compressed.len() > uncompressed.len());
+
+        context.set_skip_ineffective_compression(true);
+        let jwe = context.serialize_compact(&payload, &header, &encrypter)?;
+        assert!(jwe.len() <= uncompressed.len());
+        let (dst_payload, dst_header) = context.deserialize_compact(&jwe, &decrypter)?;
+        assert_eq!(dst_payload, payload);
+        assert_eq!(dst_header.compression(), None);
+
+        let mut header_set = JweHeaderSet::new();
+        header_set.set_content_encryption("A128GCM", true);
+        header_set.set_compression("DEF");
+        let jwe = context.serialize_flattened_json(
+            &payload,
+            Some(&header_set),
+            None,
+            None,
+            &encrypter,
+        )?;
+        let (dst_payload, dst_header) = context.deserialize_json(&jwe, &decrypter)?;
+        assert_eq!(dst_payload, payload);
+        assert_eq!(dst_header.compression(), None);
+
+        let payload = vec![b'a'; 4096];
+        let jwe = context.serialize_compact(&payload, &header, &encrypter)?;
+        let (dst_payload, dst_header) = context.deserialize_compact(&jwe, &decrypter)?;
+        assert_eq!(dst_payload, payload);
+        assert_eq!(dst_header.compression(), Some("DEF"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn flattened_json_with_unprotected_header() -> Result<()> {
+        let payload = b"hello world";
+        let key = util::random_bytes(16);
+        let encrypter = DirectJweAlgorithm::Dir.encrypter_from_bytes(&key)?;
+        let decrypter = DirectJweAlgorithm::Dir.decrypter_from_bytes(&key)?;
+
+        let mut header = JweHeaderSet::new();
+        header.set_algorithm("dir", true);
+        header.set_content_encryption("A128GCM", true);
+        header.set_key_id("key-1", false);
+        let jwe = serialize_flattened_json(payload, Some(&header), None, None, &encrypter)?;
+
+        let mut json: Map<String, Value> = serde_json::from_str(&jwe)?;
+        let protected = match json.get("protected") {
+            Some(Value::String(val)) => util::decode_base64_urlsafe_no_pad(val)?,
+            _ => unreachable!(),
+        };
+        let protected: Map<String, Value> = serde_json::from_slice(&protected)?;
+        assert_eq!(protected.get("alg"), Some(&Value::from("dir")));
+        assert_eq!(protected.get("enc"), Some(&Value::from("A128GCM")));
+        assert_eq!(protected.get("kid"), None);
+
+        // The unprotected header is not covered by the additional authenticated data.
+        json.insert("unprotected".to_string(), json!({ "kid": "key-2" }));
+        let (dst_payload, dst_header) =
+            deserialize_json(&serde_json::to_string(&json)?, &decrypter)?;
+        assert_eq!(dst_payload, payload);
+        assert_eq!(dst_header.key_id(), Some("key-2"));
+
+        let protected = r#"{"alg":"dir","enc":"A128GCM","kid":"key-2"}"#;
+        json.insert(
+            "protected".to_string(),
+            Value::String(util::encode_base64_urlsafe_nopad(protected)),
+        );
+        assert!(deserialize_json(&serde_json::to_string(&json)?, &decrypter).is_err());
+
+        assert!(header
+            .set_claim("crit", Some(json!(["exp"])), false)
+            .is_err());
+        assert!(header.set_claim("zip", Some(json!("DEF")), false).is_err());
+        assert!(header.set_claim("alg", Some(json!("dir")), false).is_err());
+        assert!(header
+            .set_claim("enc", Some(json!("A128GCM")), false)
+            .is_err());
+
+        let mut unprotected_alg = header.clone();
+        unprotected_alg.set_algorithm("dir", false);
+        match serialize_flattened_json(payload, Some(&unprotected_alg), None, None, &encrypter) {
+            Err(JoseError::InvalidJweFormat(err)) => assert_eq!(
+                err.to_string(),
+                "The JWE alg header claim must be protected."
+            ),
+            other => panic!("An unprotected alg header claim was accepted: {:?}", other),
+        }
+        assert!(serialize_general_json(
+            payload,
+            Some(&unprotected_alg),
+            &[(None, &encrypter as &dyn JweEncrypter)],
+            None
+        )
+        .is_err());
+
+        let mut recipient_header = JweHeader::new();
+        recipient_header.set_compression("DEF");
+        assert!(serialize_flattened_json(
+            payload,
+            Some(&header),
+            Some(&recipient_header),
+            None,
+            &encrypter
+        )
+        .is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn json_with_unprotected_enc() -> Result<()> {
+        let payload = b"hello world";
+        let key = util::random_bytes(16);
+        let encrypter = DirectJweAlgorithm::Dir.encrypter_from_bytes(&key)?;
+        let decrypter = DirectJweAlgorithm::Dir.decrypter_from_bytes(&key)?;
+
+        let mut header = JweHeaderSet::new();
+        header.set_content_encryption("A128GCM", true);
+        let jwe = serialize_flattened_json(payload, Some(&header), None, None, &encrypter)?;
+
+        let mut json: Map<String, Value> = serde_json::from_str(&jwe)?;
+        json.insert(
+            "protected".to_string(),
+            Value::String(util::encode_base64_urlsafe_nopad(r#"{"alg":"dir"}"#)),
+        );
+        json.insert("unprotected".to_string(), json!({ "enc": "A128GCM" }));
+        match deserialize_json(&serde_json::to_string(&json)?, &decrypter) {
+            Err(JoseError::InvalidJweFormat(err)) => assert_eq!(
+                err.to_string(),
+                "The JWE enc header claim must be protected."
+            ),
+            other => panic!("An unprotected enc header claim was accepted: {:?}", other),
+        }
+
+        let mut header = JweHeaderSet::new();
+        header.set_content_encryption("A128GCM", false);
+        assert!(serialize_flattened_json(payload, Some(&header), None, None, &encrypter).is_err());
+
+        let mut recipient_header = JweHeader::new();
+        recipient_header.set_content_encryption("A128GCM");
+        assert!(
+            serialize_flattened_json(payload, None, Some(&recipient_header), None, &encrypter)
+                .is_err()
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn json_with_empty_aad() -> Result<()> {
+        let payload = b"hello world";
+        let key = util::random_bytes(16);
+        let encrypter = DirectJweAlgorithm::Dir.encrypter_from_bytes(&key)?;
+        let decrypter = DirectJweAlgorithm::Dir.decrypter_from_bytes(&key)?;
+
+        let mut header = JweHeaderSet::new();
+        header.set_content_encryption("A128GCM", true);
+
+        let jwe = serialize_flattened_json(payload, Some(&header), None, Some(b""), &encrypter)?;
+        let mut json: Map<String, Value> = serde_json::from_str(&jwe)?;
+        assert!(!json.contains_key("aad"));
+        let (dst_payload, _) = deserialize_json(&jwe, &decrypter)?;
+        assert_eq!(dst_payload, payload);
+
+        json.insert("aad".to_string(), json!(""));
+        assert!(deserialize_json(&serde_json::to_string(&json)?, &decrypter).is_err());
+
+        // A present but empty aad member still adds the period to the AAD.
+        let protected_b64 = util::encode_base64_urlsafe_nopad(r#"{"alg":"dir","enc":"A128GCM"}"#);
+        let iv = util::random_bytes(A128GCM.iv_len());
+        let full_aad = format!("{}.", protected_b64);
+        let (ciphertext, tag) = A128GCM.encrypt(&key, Some(&iv), payload, full_aad.as_bytes())?;
+        let mut json = Map::new();
+        json.insert("protected".to_string(), json!(protected_b64));
+        json.insert("aad".to_string(), json!(""));
+        json.insert(
+            "iv".to_string(),
+            json!(util::encode_base64_urlsafe_nopad(&iv)),
+        );
+        json.insert(
+            "ciphertext".to_string(),
+            json!(util::encode_base64_urlsafe_nopad(&ciphertext)),
+        );
+        json.insert(
+            "tag".to_string(),
+            json!(util::encode_base64_urlsafe_nopad(tag.unwrap())),
+        );
+        let (dst_payload, _) = deserialize_json(&serde_json::to_string(&json)?, &decrypter)?;
+        assert_eq!(dst_payload, payload);
+
+        json.remove("aad");
+        assert!(deserialize_json(&serde_json::to_string(&json)?, &decrypter).is_err());
+
+        let jwe = serialize_flattened_json(payload, Some(&header), None, Some(b"aad"), &encrypter)?;
+        let mut json: Map<String, Value> = serde_json::from_str(&jwe)?;
+        assert_eq!(json.get("aad"), Some(&json!("YWFk")));
+        let (dst_payload, _) = deserialize_json(&jwe, &decrypter)?;
+        assert_eq!(dst_payload, payload);
+
+        json.remove("aad");
+        assert!(deserialize_json(&serde_json::to_string(&json)?, &decrypter).is_err());
+
+        Ok(())
+    }
+
+    #[derive(Debug)]
+    struct SequenceRandomSource;
+
+    impl RandomSource for SequenceRandomSource {
+        fn fill(&self, buf: &mut [u8]) -> Result<(), JoseError> {
+            for (i, b) in buf.iter_mut().enumerate() {
+                *b = i as u8;
+            }
+            Ok(())
+        }
+
+        fn box_clone(&self) -> Box<dyn RandomSource> {
+            Box::new(SequenceRandomSource)
+        }
+    }
+
+    #[test]
+    fn compact_with_random_source() -> Result<()> {
+        let payload = b"hello world";
+        let key = util::random_bytes(16);
+        let encrypter = A128KW.encrypter_from_bytes(&key)?;
+        let decrypter = A128KW.decrypter_from_bytes(&key)?;
+
+        let mut header = JweHeader::new();
+        header.set_content_encryption("A128GCM");
+
+        let mut context = JweContext::new();
+        context.set_random_source(Box::new(SequenceRandomSource));
+        let jwe = context.serialize_compact(payload, &header, &encrypter)?;
+        assert_eq!(
+            jwe,
+            context.serialize_compact(payload, &header, &encrypter)?
+        );
+
+        let parts: Vec<&str> = jwe.split('.').collect();
+        let expected_cek: Vec<u8> = (0..16).collect();
+        let expected_iv: Vec<u8> = (0..12).collect();
+        assert_eq!(util::decode_base64_urlsafe_no_pad(parts[2])?, expected_iv);
+
+        let (_, dst_header) = context.deserialize_compact(&jwe, &decrypter)?;
+        let encrypted_key = util::decode_base64_urlsafe_no_pad(parts[1])?;
+        let cek = decrypter.decrypt(Some(&encrypted_key), &A128GCM, &dst_header)?;
+        assert_eq!(cek.as_ref(), expected_cek.as_slice());
+
+        let (dst_payload, _) = deserialize_compact(&jwe, &decrypter)?;
+        assert_eq!(dst_payload, payload);
+
+        Ok(())
+    }
+
+    #[test]
+    fn compact_with_random_source_for_encrypters() -> Result<()> {
+        let payload = b"hello world";
+        let mut context = JweContext::new();
+        context.set_random_source(Box::new(SequenceRandomSource));
+
+        let mut header = JweHeader::new();
+        header.set_content_encryption("A128GCM");
+
+        let key = util::random_bytes(16);
+        let encrypter = A128GCMKW.encrypter_from_bytes(&key)?;
+        let decrypter = A128GCMKW.decrypter_from_bytes(&key)?;
+        let jwe = context.serialize_compact(payload, &header, &encrypter)?;
+        let (dst_payload, dst_header) = context.deserialize_compact(&jwe, &decrypter)?;
+        assert_eq!(dst_payload, payload);
+        let expected_iv: Vec<u8> = (0..12).collect();
+        assert_eq!(
+            dst_header.claim("iv"),
+            Some(&json!(util::encode_base64_urlsafe_nopad(expected_iv)))
+        );
+
+        let encrypter = PBES2_HS256_A128KW.encrypter_from_bytes(b"password")?;
+        let decrypter = PBES2_HS256_A128KW.decrypter_from_bytes(b"password")?;
+        let jwe = context.serialize_compact(payload, &header, &encrypter)?;
+        let (dst_payload, dst_header) = context.deserialize_compact(&jwe, &decrypter)?;
+        assert_eq!(dst_payload, payload);
+        let expected_p2s: Vec<u8> = (0..16).collect();
+        assert_eq!(
+            dst_header.claim("p2s"),
+            Some(&json!(util::encode_base64_urlsafe_nopad(expected_p2s)))
+        );
+
+        let cencryption = context.get_content_encryption("A128GCM").unwrap();
+        let (iv, _, _) = context.encrypt_content(cencryption, &key, &header, payload)?;
+        assert_eq!(iv, (0..12).collect::<Vec<u8>>());
+
+        Ok(())
+    }
+
+    #[test]
+    fn compact_to_writer() -> Result<()> {
+        let payload: Vec<u8> = (0..3 * 1024 * 1024).map(|i| i as u8).collect();
+        let key = util::random_bytes(16);
+        let encrypter = A128KW.encrypter_from_bytes(&key)?;
+        let decrypter = A128KW.decrypter_from_bytes(&key)?;
+
+        let mut context = JweContext::new();
+        context.set_random_source(Box::new(SequenceRandomSource));
+        for enc in ["A128GCM", "A128CBC-HS256"] {
+            let mut header = JweHeader::new();
+            header.set_content_encryption(enc);
+
+            let mut out = Vec::new();
+            context.serialize_compact_to_writer(
+                payload.as_slice(),
+                &header,
+                &encrypter,
+                &mut out,
+            )?;
+            let expected = context.serialize_compact(&payload, &header, &encrypter)?;
+            assert_eq!(String::from_utf8(out)?, expected);
+
+            let (dst_payload, _) = context.deserialize_compact(&expected, &decrypter)?;
+            assert_eq!(dst_payload, payload);
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn compact_with_iv_reuse_check() -> Result<()> {
+        let payload = b"hello world";
+        let key = util::random_bytes(16);
+        let encrypter = DirectJweAlgorithm::Dir.encrypter_from_bytes(&key)?;
+
+        let mut header = JweHeader::new();
+        header.set_content_encryption("A128GCM");
+
+        let mut context = JweContext::new();
+        context.add_content_encryption(Box::new(A128GCM.with_iv_reuse_check(16)));
+        context.serialize_compact(payload, &header, &encrypter)?;
+        context.serialize_compact(payload, &header, &encrypter)?;
+
+        context.set_random_source(Box::new(SequenceRandomSource));
+        context.serialize_compact(payload, &header, &encrypter)?;
+        match context.serialize_compact(payload, &header, &encrypter) {
+            Err(JoseError::InvalidJweFormat(err)) => assert_eq!(
+                err.to_string(),
+                "The initialization vector is reused with the same key."
+            ),
+            other => panic!("A reused initialization vector was accepted: {:?}", other),
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn compact_with_recommended_content_encryption() -> Result<()> {
+        assert_eq!(RSA_OAEP_256.recommended_content_encryption(), "A256GCM");
+        assert_eq!(A128KW.recommended_content_encryption(), "A128GCM");
+
+        let key = util::random_bytes(16);
+        let encrypter = A128KW.encrypter_from_bytes(&key)?;
+        let decrypter = A128KW.decrypter_from_bytes(&key)?;
+
+        let mut context = JweContext::new();
+        assert!(!context.is_uses_recommended_content_encryption());
+        assert!(context
+            .serialize_compact(b"hello world", &JweHeader::new(), &encrypter)
+            .is_err());
+
+        context.set_uses_recommended_content_encryption(true);
+        let jwe = context.serialize_compact(b"hello world", &JweHeader::new(), &encrypter)?;
+        let (payload, header) = context.deserialize_compact(&jwe, &decrypter)?;
+        assert_eq!(payload, b"hello world");
+        assert_eq!(header.content_encryption(), Some("A128GCM"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn compact_with_wrong_segment_count() -> Result<()> {
+        let key = util::random_bytes(16);
+        let encrypter = A128KW.encrypter_from_bytes(&key)?;
+        let decrypter = A128KW.decrypter_from_bytes(&key)?;
+
+        let mut header = JweHeader::new();
+        header.set_content_encryption("A128GCM");
+        let jwe = serialize_compact(b"hello world", &header, &encrypter)?;
+        let parts: Vec<&str> = jwe.split('.').collect();
+
+        let (payload, _) = deserialize_compact(&parts.join("."), &decrypter)?;
+        assert_eq!(payload, b"hello world");
+
+        for (input, expected) in [
+            (
+                parts[..3].join("."),
+                "The compact serialization form of JWE must be five parts separated by colon, but it has three parts like JWS.",
+            ),
+            (
+                parts[..4].join("."),
+                "The compact serialization form of JWE must be five parts separated by colon: 4 parts",
+            ),
+            (
+                format!("{}.", jwe),
+                "The compact serialization form of JWE must be five parts separated by colon: 6 parts",
+            ),
+        ] {
+            match deserialize_compact(&input, &decrypter) {
+                Err(JoseError::InvalidJweFormat(err)) => assert_eq!(err.to_string(), expected),
+                other => panic!("A wrong segment count was accepted: {:?}", other),
+            }
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn serialize_with_mismatched_alg() -> Result<()> {
+        let key = util::random_bytes(16);
+        let encrypter = A128KW.encrypter_from_bytes(&key)?;
+
+        let mut header = JweHeader::new();
+        header.set_content_encryption("A128GCM");
+        header.set_algorithm("A256KW");
+        match serialize_compact(b"hello world", &header, &encrypter) {
+            Err(JoseError::InvalidJweFormat(err)) => assert_eq!(
+                format!("{}", err),
+                "The JWE alg header claim is not A128KW: A256KW"
+            ),
+            other => panic!("Mismatched alg header claim was accepted: {:?}", other),
+        }
+
+        let mut header = JweHeaderSet::new();
+        header.set_content_encryption("A128GCM", true);
+        header.set_algorithm("A256KW", true);
+        match serialize_flattened_json(b"hello world", Some(&header), None, None, &encrypter) {
+            Err(JoseError::InvalidJweFormat(err)) => assert_eq!(
+                format!("{}", err),
+                "The JWE alg header claim is not A128KW: A256KW"
+            ),
+            other => panic!("Mismatched alg header claim was accepted: {:?}", other),
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn compact_with_whitespace() -> Result<()> {
+        let payload = b"hello world";
+        let key = util::random_bytes(16);
+        let decrypter = DirectJweAlgorithm::Dir.decrypter_from_bytes(&key)?;
+        let jwe = encrypt_compact(r#"{"alg":"dir","enc":"A128GCM"}"#, &A128GCM, &key, payload)?;
+
+        let (dst_payload, _) = deserialize_compact(&format!(" {}\n", jwe), &decrypter)?;
+        assert_eq!(dst_payload, payload);
+
+        let pos = jwe.find('.').unwrap() + 1;
+        let jwe_with_space = format!("{} {}", &jwe[..pos], &jwe[pos..]);
+        match deserialize_compact(&jwe_with_space, &decrypter) {
+            Err(JoseError::InvalidJweFormat(_)) => {}
+            other => panic!("Internal whitespace was accepted: {:?}", other),
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn compact_with_malformed_segment() -> Result<()> {
+        let payload = b"hello world";
+        let key = util::random_bytes(16);
+        let decrypter = DirectJweAlgorithm::Dir.decrypter_from_bytes(&key)?;
+        let jwe = encrypt_compact(r#"{"alg":"dir","enc":"A128GCM"}"#, &A128GCM, &key, payload)?;
+        let parts: Vec<&str> = jwe.split('.').collect();
+
+        for (index, expected) in [
+            (0, "segment 1 (header) is not valid base64url"),
+            (1, "segment 2 (encrypted key) is not valid base64url"),
+            (2, "segment 3 (iv) is not valid base64url"),
+            (3, "segment 4 (ciphertext) is not valid base64url"),
+            (4, "segment 5 (tag) is not valid base64url"),
+        ] {
+            let mut malformed = parts.clone();
+            malformed[index] = "!!";
+            match deserialize_compact(&malformed.join("."), &decrypter) {
+                Err(JoseError::InvalidJweFormat(err)) => {
+                    assert!(err.to_string().contains(expected), "{}", err)
+                }
+                other => panic!("A malformed segment was accepted: {:?}", other),
+            }
+        }
+
+        let mut malformed = parts.clone();
+        let header = util::encode_base64_urlsafe_nopad("[]");
+        malformed[0] = &header;
+        match deserialize_compact(&malformed.join("."), &decrypter) {
+            Err(JoseError::InvalidJweFormat(err)) => {
+                assert!(
+                    err.to_string()
+                        .contains("segment 1 (header) is not valid JSON object"),
+                    "{}",
+                    err
+                )
+            }
+            other => panic!("A malformed header was accepted: {:?}", other),
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn compact_with_none_algorithm() -> Result<()> {
+        let payload = b"hello world";
+        let key = util::random_bytes(16);
+        let decrypter = DirectJweAlgorithm::Dir.decrypter_from_bytes(&key)?;
+
+        for (header, name) in [
+            (r#"{"alg":"none","enc":"A128GCM"}"#, "alg"),
+            (r#"{"alg":"dir","enc":"none"}"#, "enc"),
+        ] {
+            let jwe = encrypt_compact(header, &A128GCM, &key, payload)?;
+            match deserialize_compact(&jwe, &decrypter) {
+                Err(err @ JoseError::InvalidJweFormat(_)) => assert_eq!(
+                    err.to_string(),
+                    format!(
+                        "Invalid JWE format: The JWE {} header claim must not be none.",
+                        name
+                    )
+                ),
+                other => panic!("The none algorithm was accepted: {:?}", other),
+            }
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn compact_with_key_id() -> Result<()> {
+        let payload = b"hello world";
+        let key = util::random_bytes(16);
+
+        let with_kid = encrypt_compact(
+            r#"{"alg":"dir","enc":"A128GCM","kid":"key-1"}"#,
+            &A128GCM,
+            &key,
+            payload,
+        )?;
+        let without_kid =
+            encrypt_compact(r#"{"alg":"dir","enc":"A128GCM"}"#, &A128GCM, &key, payload)?;
+
+        let decrypter = DirectJweAlgorithm::Dir.decrypter_from_bytes(&key)?;
+        let mut decrypter_1 = decrypter.clone();
+        decrypter_1.set_key_id("key-1");
+        let mut decrypter_2 = decrypter.clone();
+        decrypter_2.set_key_id("key-2");
+
+        let (dst_payload, _) = deserialize_compact(&with_kid, &decrypter_1)?;
+        assert_eq!(dst_payload, payload);
+        let (dst_payload, _) = deserialize_compact(&with_kid, &decrypter)?;
+        assert_eq!(dst_payload, payload);
+        let (dst_payload, _) = deserialize_compact(&without_kid, &decrypter_1)?;
+        assert_eq!(dst_payload, payload);
+
+        match deserialize_compact(&with_kid, &decrypter_2) {
+            Err(JoseError::InvalidJweFormat(_)) => {}
+            other => panic!("Mismatched kid was accepted: {:?}", other),
+        }
+
+        Ok(())
+    }
 }
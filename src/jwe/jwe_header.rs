@@ -5,6 +5,7 @@ use std::fmt::{Debug, Display};
 use std::ops::Deref;
 
 use anyhow::bail;
+use serde::de::DeserializeOwned;
 
 use crate::jwk::Jwk;
 use crate::util;
@@ -52,6 +53,24 @@ impl JweHeader {
         Ok(Self { claims: map })
     }
 
+    /// Return a new header instance from json value.
+    ///
+    /// # Arguments
+    ///
+    /// * `value` - The header claims as a json object
+    pub fn from_value(value: Value) -> Result<Self, JoseError> {
+        let map = match value {
+            Value::Object(map) => map,
+            _ => {
+                return Err(JoseError::InvalidJson(anyhow::anyhow!(
+                    "The JWE header must be a JSON object."
+                )))
+            }
+        };
+
+        Self::from_map(map)
+    }
+
     /// Set a value for algorithm header claim (alg).
     ///
     /// # Arguments
@@ -298,16 +317,21 @@ impl JweHeader {
 
     /// Set values for critical header claim (crit).
     ///
+    /// Fails if the values are empty, or contain an empty or duplicate name.
+    ///
     /// # Arguments
     ///
     /// * `values` - critical claim names
-    pub fn set_critical(&mut self, values: &Vec<impl AsRef<str>>) {
+    pub fn set_critical(&mut self, values: &Vec<impl AsRef<str>>) -> Result<(), JoseError> {
         let key = "crit";
         let vec = values
             .iter()
             .map(|v| Value::String(v.as_ref().to_string()))
             .collect();
-        self.claims.insert(key.to_string(), Value::Array(vec));
+        let value = Value::Array(vec);
+        Self::check_claim(key, &value)?;
+        self.claims.insert(key.to_string(), value);
+        Ok(())
     }
 
     /// Return values for critical header claim (crit).
@@ -517,11 +541,69 @@ impl JweHeader {
         &self.claims
     }
 
+    /// Return a value for header claim of a specified key deserialized into a type.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - a key name of header claim
+    pub fn parameter_as<T: DeserializeOwned>(&self, name: &str) -> Result<Option<T>, JoseError> {
+        match self.claims.get(name) {
+            Some(val) => {
+                let val = T::deserialize(val).map_err(|err| {
+                    JoseError::InvalidJweFormat(anyhow::anyhow!(
+                        "The JWE {} header claim is invalid: {}",
+                        name,
+                        err
+                    ))
+                })?;
+                Ok(Some(val))
+            }
+            None => Ok(None),
+        }
+    }
+
+    /// Return an iterator over the names and values of all header claims.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &Value)> {
+        self.claims.iter().map(|(key, value)| (key.as_str(), value))
+    }
+
+    /// Test whether the header claims are the same as the other ones regardless of
+    /// the order of members, including the members of nested objects.
+    ///
+    /// # Arguments
+    ///
+    /// * `other` - The other header
+    pub fn semantically_equals(&self, other: &JweHeader) -> bool {
+        Self::map_equals(&self.claims, &other.claims)
+    }
+
     /// Convert into map
     pub fn into_map(self) -> Map<String, Value> {
         self.claims
     }
 
+    fn map_equals(map1: &Map<String, Value>, map2: &Map<String, Value>) -> bool {
+        map1.len() == map2.len()
+            && map1.iter().all(|(key, val1)| match map2.get(key) {
+                Some(val2) => Self::value_equals(val1, val2),
+                None => false,
+            })
+    }
+
+    fn value_equals(val1: &Value, val2: &Value) -> bool {
+        match (val1, val2) {
+            (Value::Object(map1), Value::Object(map2)) => Self::map_equals(map1, map2),
+            (Value::Array(vals1), Value::Array(vals2)) => {
+                vals1.len() == vals2.len()
+                    && vals1
+                        .iter()
+                        .zip(vals2)
+                        .all(|(val1, val2)| Self::value_equals(val1, val2))
+            }
+            _ => val1 == val2,
+        }
+    }
+
     pub(crate) fn check_claim(key: &str, value: &Value) -> Result<(), JoseError> {
         (|| -> anyhow::Result<()> {
             match key {
@@ -535,6 +617,10 @@ impl JweHeader {
                     Value::Array(vals) => {
                         for val in vals {
                             match val {
+                                Value::String(val) if val.is_empty() => bail!(
+                                    "An element of the JWE {} header claim must not be empty.",
+                                    key
+                                ),
                                 Value::String(_) => {}
                                 _ => bail!(
                                     "An element of the JWE {} header claim must be a string.",
@@ -547,14 +633,26 @@ impl JweHeader {
                 },
                 "crit" => match &value {
                     Value::Array(vals) => {
-                        for val in vals {
+                        if vals.is_empty() {
+                            bail!("The JWE {} header claim must not be empty.", key);
+                        }
+
+                        for (i, val) in vals.iter().enumerate() {
                             match val {
+                                Value::String(val) if val.is_empty() => bail!(
+                                    "An element of the JWE {} header claim must not be empty.",
+                                    key
+                                ),
                                 Value::String(_) => {}
                                 _ => bail!(
                                     "An element of the JWE {} header claim must be a string.",
                                     key
                                 ),
                             }
+
+                            if vals[..i].contains(val) {
+                                bail!("The JWE {} header claim has a duplicate name: {}", key, val);
+                            }
                         }
                     }
                     _ => bail!("The JWE {} header claim must be a array.", key),
@@ -683,7 +781,7 @@ mod tests {
         header.set_key_id("kid");
         header.set_token_type("typ");
         header.set_content_type("cty");
-        header.set_critical(&vec!["crit0", "crit1"]);
+        header.set_critical(&vec!["crit0", "crit1"])?;
         header.set_url("url");
         header.set_nonce(b"nonce");
         header.set_agreement_partyuinfo(b"apu");
@@ -747,4 +845,133 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn test_iter_jwe_header() -> Result<()> {
+        let mut header = JweHeader::new();
+        header.set_algorithm("dir");
+        header.set_content_encryption("A128GCM");
+        header.set_claim("custom", Some(json!({ "level": 1 })))?;
+
+        let map: Map<String, Value> = header
+            .iter()
+            .map(|(key, value)| (key.to_string(), value.clone()))
+            .collect();
+        assert_eq!(map.len(), 3);
+        assert_eq!(map.get("alg"), Some(&json!("dir")));
+        assert_eq!(map.get("enc"), Some(&json!("A128GCM")));
+        assert_eq!(map.get("custom"), Some(&json!({ "level": 1 })));
+        assert_eq!(&map, header.claims_set());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_jwe_header_from_value() -> Result<()> {
+        let header = JweHeader::from_value(json!({
+            "alg": "ECDH-ES",
+            "enc": "A256GCM",
+            "kid": "key-1",
+            "apu": "YXB1",
+            "custom": { "level": 1 }
+        }))?;
+        assert_eq!(header.algorithm(), Some("ECDH-ES"));
+        assert_eq!(header.content_encryption(), Some("A256GCM"));
+        assert_eq!(header.key_id(), Some("key-1"));
+        assert_eq!(header.agreement_partyuinfo(), Some(b"apu".to_vec()));
+        assert_eq!(header.claim("custom"), Some(&json!({ "level": 1 })));
+        assert_eq!(header.claims_set().len(), 5);
+
+        assert!(JweHeader::from_value(json!(["alg", "dir"])).is_err());
+        assert!(JweHeader::from_value(json!({ "enc": 1 })).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_jwe_header_semantically_equals() -> Result<()> {
+        let header1 = JweHeader::from_value(json!({
+            "alg": "ECDH-ES",
+            "enc": "A128GCM",
+            "epk": {"kty": "OKP", "crv": "X25519", "x": "AAAA"},
+            "crit": ["b64", "x-vendor"]
+        }))?;
+        let header2 = JweHeader::from_value(json!({
+            "crit": ["b64", "x-vendor"],
+            "epk": {"x": "AAAA", "crv": "X25519", "kty": "OKP"},
+            "enc": "A128GCM",
+            "alg": "ECDH-ES"
+        }))?;
+        assert!(header1.semantically_equals(&header2));
+        assert!(header2.semantically_equals(&header1));
+        assert_ne!(header1.to_string(), header2.to_string());
+
+        let header3 = JweHeader::from_value(json!({
+            "crit": ["x-vendor", "b64"],
+            "epk": {"x": "AAAA", "crv": "X25519", "kty": "OKP"},
+            "enc": "A128GCM",
+            "alg": "ECDH-ES"
+        }))?;
+        assert!(!header1.semantically_equals(&header3));
+
+        let mut header4 = header1.clone();
+        header4.set_key_id("key-1");
+        assert!(!header1.semantically_equals(&header4));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_jwe_header_parameter_as() -> Result<()> {
+        #[derive(Debug, Eq, PartialEq, serde::Deserialize)]
+        struct Context {
+            tenant: String,
+        }
+
+        let header = JweHeader::from_value(json!({
+            "alg": "dir",
+            "ctx": { "tenant": "acme" }
+        }))?;
+        assert_eq!(
+            header.parameter_as::<Context>("ctx")?,
+            Some(Context {
+                tenant: "acme".to_string()
+            })
+        );
+        assert_eq!(header.parameter_as::<Context>("svt")?, None);
+        assert!(header.parameter_as::<Context>("alg").is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_critical_jwe_header() -> Result<()> {
+        let mut header = JweHeader::new();
+        header.set_critical(&vec!["b64", "exp"])?;
+        assert_eq!(header.critical(), Some(vec!["b64", "exp"]));
+
+        let header = JweHeader::from_map(header.claims_set().clone())?;
+        assert_eq!(header.critical(), Some(vec!["b64", "exp"]));
+        assert_eq!(header.claim("crit"), Some(&json!(["b64", "exp"])));
+
+        let mut map = Map::new();
+        map.insert("crit".to_string(), json!([]));
+        assert!(JweHeader::from_map(map).is_err());
+
+        let mut map = Map::new();
+        map.insert("crit".to_string(), json!(["exp", "exp"]));
+        assert!(JweHeader::from_map(map).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_critical_jwe_header_with_invalid_names() {
+        let mut header = JweHeader::new();
+        let empty: Vec<&str> = vec![];
+        assert!(header.set_critical(&empty).is_err());
+        assert!(header.set_critical(&vec!["exp", ""]).is_err());
+        assert!(header.set_critical(&vec!["exp", "exp"]).is_err());
+        assert_eq!(header.critical(), None);
+    }
 }
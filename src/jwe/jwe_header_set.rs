@@ -2,6 +2,8 @@ use std::any::Any;
 use std::fmt::{Debug, Display};
 use std::ops::Deref;
 
+use anyhow::anyhow;
+
 use crate::jwe::JweHeader;
 use crate::jwk::Jwk;
 use crate::{util, JoseError, JoseHeader, Map, Value};
@@ -362,17 +364,22 @@ impl JweHeaderSet {
 
     /// Set values for critical header claim (crit).
     ///
+    /// Fails if the values are empty, or contain an empty or duplicate name.
+    ///
     /// # Arguments
     ///
     /// * `values` - critical claim names
-    pub fn set_critical(&mut self, values: &Vec<impl AsRef<str>>) {
+    pub fn set_critical(&mut self, values: &Vec<impl AsRef<str>>) -> Result<(), JoseError> {
         let key = "crit";
         let vec = values
             .iter()
             .map(|v| Value::String(v.as_ref().to_string()))
             .collect();
+        let value = Value::Array(vec);
+        JweHeader::check_claim(key, &value)?;
         self.unprotected.remove(key);
-        self.protected.insert(key.to_string(), Value::Array(vec));
+        self.protected.insert(key.to_string(), value);
+        Ok(())
     }
 
     /// Return values for critical header claim (crit).
@@ -622,6 +629,15 @@ impl JweHeaderSet {
         match value {
             Some(val) => {
                 JweHeader::check_claim(key, &val)?;
+                match key {
+                    "alg" | "enc" | "crit" | "zip" if !protection => {
+                        return Err(JoseError::InvalidJweFormat(anyhow!(
+                            "The JWE {} header claim must be protected.",
+                            key
+                        )));
+                    }
+                    _ => {}
+                }
                 if protection {
                     self.unprotected.remove(key);
                     self.protected.insert(key.to_string(), val);
@@ -741,7 +757,7 @@ mod tests {
         header.set_key_id("kid", true);
         header.set_token_type("typ", true);
         header.set_content_type("cty", true);
-        header.set_critical(&vec!["crit0", "crit1"]);
+        header.set_critical(&vec!["crit0", "crit1"])?;
         header.set_url("url", true);
         header.set_nonce(b"nonce", true);
         header.set_agreement_partyuinfo(b"apu", true);
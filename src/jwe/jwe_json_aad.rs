@@ -0,0 +1,87 @@
+/// A builder of the additional authenticated data for JWE JSON serialization.
+///
+/// It follows the step 14 of RFC 7516 section 5.1: the encoded protected header,
+/// followed by a period and the encoded JWE AAD only when the aad member is present,
+/// even if it is empty.
+#[derive(Debug, Eq, PartialEq, Copy, Clone, Default)]
+pub(crate) struct JsonAad<'a> {
+    protected_b64: Option<&'a str>,
+    aad_b64: Option<&'a str>,
+}
+
+impl<'a> JsonAad<'a> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the base64url encoded protected header.
+    ///
+    /// # Arguments
+    ///
+    /// * `value` - a base64url encoded protected header
+    pub fn set_protected(&mut self, value: Option<&'a str>) -> &mut Self {
+        self.protected_b64 = value;
+        self
+    }
+
+    /// Set the base64url encoded JWE AAD.
+    ///
+    /// # Arguments
+    ///
+    /// * `value` - a base64url encoded JWE AAD
+    pub fn set_aad(&mut self, value: Option<&'a str>) -> &mut Self {
+        self.aad_b64 = value;
+        self
+    }
+
+    /// Return the additional authenticated data for the content encryption.
+    pub fn build(&self) -> String {
+        let protected_b64 = self.protected_b64.unwrap_or_default();
+        match self.aad_b64 {
+            Some(val) => {
+                let mut full_aad = String::with_capacity(protected_b64.len() + 1 + val.len());
+                full_aad.push_str(protected_b64);
+                full_aad.push('.');
+                full_aad.push_str(val);
+                full_aad
+            }
+            None => protected_b64.to_string(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::JsonAad;
+
+    #[test]
+    fn build_without_aad() {
+        let full_aad = JsonAad::new()
+            .set_protected(Some("eyJlbmMiOiJBMTI4R0NNIn0"))
+            .build();
+        assert_eq!(full_aad, "eyJlbmMiOiJBMTI4R0NNIn0");
+
+        assert_eq!(JsonAad::new().build(), "");
+    }
+
+    #[test]
+    fn build_with_empty_aad() {
+        let full_aad = JsonAad::new()
+            .set_protected(Some("eyJlbmMiOiJBMTI4R0NNIn0"))
+            .set_aad(Some(""))
+            .build();
+        assert_eq!(full_aad, "eyJlbmMiOiJBMTI4R0NNIn0.");
+    }
+
+    #[test]
+    fn build_with_aad() {
+        let full_aad = JsonAad::new()
+            .set_protected(Some("eyJlbmMiOiJBMTI4R0NNIn0"))
+            .set_aad(Some("YWFk"))
+            .build();
+        assert_eq!(full_aad, "eyJlbmMiOiJBMTI4R0NNIn0.YWFk");
+
+        let full_aad = JsonAad::new().set_aad(Some("YWFk")).build();
+        assert_eq!(full_aad, ".YWFk");
+    }
+}
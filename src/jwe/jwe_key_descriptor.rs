@@ -0,0 +1,25 @@
+/// Represent the metadata of a JWE key without any key material.
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub struct JweKeyDescriptor {
+    algorithm: String,
+    key_id: Option<String>,
+}
+
+impl JweKeyDescriptor {
+    pub(crate) fn new(algorithm: &str, key_id: Option<&str>) -> Self {
+        Self {
+            algorithm: algorithm.to_string(),
+            key_id: key_id.map(|val| val.to_string()),
+        }
+    }
+
+    /// Return the "alg" (algorithm) header parameter value of JWE.
+    pub fn algorithm(&self) -> &str {
+        &self.algorithm
+    }
+
+    /// Return the key ID.
+    pub fn key_id(&self) -> Option<&str> {
+        self.key_id.as_deref()
+    }
+}
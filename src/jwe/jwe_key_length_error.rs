@@ -0,0 +1,29 @@
+use thiserror::Error;
+
+/// An error that the unwrapped content encryption key does not have the length
+/// the content encryption requires.
+///
+/// It is wrapped in `JoseError::InvalidJweFormat` and mostly means
+/// a wrong key or a corrupted token.
+#[derive(Error, Debug, Eq, PartialEq, Copy, Clone)]
+#[error("The key size is expected to be {expected}: {actual}")]
+pub struct KeyLengthError {
+    expected: usize,
+    actual: usize,
+}
+
+impl KeyLengthError {
+    pub(crate) fn new(expected: usize, actual: usize) -> Self {
+        Self { expected, actual }
+    }
+
+    /// Return the key length the content encryption requires.
+    pub fn expected(&self) -> usize {
+        self.expected
+    }
+
+    /// Return the length of the unwrapped key.
+    pub fn actual(&self) -> usize {
+        self.actual
+    }
+}
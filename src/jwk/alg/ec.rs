@@ -5,7 +5,7 @@ use anyhow::bail;
 use openssl::bn::{BigNum, BigNumContext, BigNumRef};
 use openssl::ec::{EcGroup, EcKey};
 use openssl::nid::Nid;
-use openssl::pkey::{PKey, Private};
+use openssl::pkey::{PKey, Private, Public};
 
 use crate::jwk::{Jwk, KeyPair};
 use crate::util;
@@ -129,13 +129,16 @@ impl EcKeyPair {
                     Some(val2) => bail!("The curve is mismatched: {}", val2),
                     None => (input, val),
                 },
-                None => match curve {
-                    Some(val) => {
-                        pkcs8_der_vec = Self::to_pkcs8(input.as_ref(), false, val);
-                        (pkcs8_der_vec.as_slice(), val)
-                    }
-                    None => bail!("A curve is required for raw format."),
-                },
+                None => {
+                    let curve = match (Self::detect_ec_curve(input), curve) {
+                        (Some(val), Some(val2)) if val2 == val => val,
+                        (Some(_), Some(val2)) => bail!("The curve is mismatched: {}", val2),
+                        (Some(val), None) | (None, Some(val)) => val,
+                        (None, None) => bail!("A curve is required for raw format."),
+                    };
+                    pkcs8_der_vec = Self::to_pkcs8(input, false, curve);
+                    (pkcs8_der_vec.as_slice(), curve)
+                }
             };
 
             let private_key = PKey::private_key_from_der(pkcs8_der)?;
@@ -230,6 +233,9 @@ impl EcKeyPair {
 
             let pkcs8 = EcKeyPair::to_pkcs8(&builder.build(), false, curve);
             let private_key = PKey::private_key_from_der(&pkcs8)?;
+            if private_key.ec_key()?.check_key().is_err() {
+                bail!("The key pair is not valid for {}.", curve);
+            }
             let algorithm = jwk.algorithm().map(|val| val.to_string());
             let key_id = jwk.key_id().map(|val| val.to_string());
 
@@ -459,6 +465,41 @@ impl EcKeyPair {
         Some(curve)
     }
 
+    /// Create a public key from affine coordinates and check that the point is on the curve.
+    ///
+    /// # Arguments
+    ///
+    /// * `x` - x coordinate of the public key
+    /// * `y` - y coordinate of the public key
+    /// * `curve` - EC curve
+    pub(crate) fn public_key_from_coordinates(
+        x: &[u8],
+        y: &[u8],
+        curve: EcCurve,
+    ) -> anyhow::Result<PKey<Public>> {
+        let expected_len = curve.coordinate_size();
+        if x.len() != expected_len || y.len() != expected_len {
+            bail!(
+                "The length of coordinates must be {} for {}.",
+                expected_len,
+                curve
+            );
+        }
+
+        let mut vec = Vec::with_capacity(1 + x.len() + y.len());
+        vec.push(0x04);
+        vec.extend_from_slice(x);
+        vec.extend_from_slice(y);
+
+        let pkcs8 = Self::to_pkcs8(&vec, true, curve);
+        let public_key = PKey::public_key_from_der(&pkcs8)?;
+        if public_key.ec_key()?.check_key().is_err() {
+            bail!("The public key is not a valid point on {}.", curve);
+        }
+
+        Ok(public_key)
+    }
+
     pub(crate) fn to_pkcs8(input: &[u8], is_public: bool, curve: EcCurve) -> Vec<u8> {
         let mut builder = DerBuilder::new();
         builder.begin(DerType::Sequence);
@@ -559,8 +600,12 @@ impl Deref for EcKeyPair {
 #[cfg(test)]
 mod tests {
     use anyhow::Result;
+    use openssl::bn::BigNumContext;
+    use openssl::ec::PointConversionForm;
+    use openssl::pkey::PKey;
 
     use super::{EcCurve, EcKeyPair};
+    use crate::jwk::KeyPair;
 
     #[test]
     fn test_ec_jwt() -> Result<()> {
@@ -586,4 +631,31 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn test_ec_public_key_spki() -> Result<()> {
+        for curve in [
+            EcCurve::P256,
+            EcCurve::P384,
+            EcCurve::P521,
+            EcCurve::Secp256k1,
+        ] {
+            let key_pair = EcKeyPair::generate(curve)?;
+            let ec_key = key_pair.private_key.ec_key()?;
+            let mut ctx = BigNumContext::new()?;
+            let point = ec_key.public_key().to_bytes(
+                ec_key.group(),
+                PointConversionForm::UNCOMPRESSED,
+                &mut ctx,
+            )?;
+
+            let spki = EcKeyPair::to_pkcs8(&point, true, curve);
+            let public_key = PKey::public_key_from_der(&spki)?;
+            assert!(public_key.public_eq(&key_pair.private_key));
+            assert_eq!(spki, key_pair.to_der_public_key());
+            assert_eq!(EcKeyPair::detect_pkcs8(&spki, true), Some(curve));
+        }
+
+        Ok(())
+    }
 }
@@ -2,7 +2,7 @@ use std::fmt::Display;
 use std::ops::Deref;
 
 use anyhow::bail;
-use openssl::pkey::{PKey, Private};
+use openssl::pkey::{PKey, Private, Public};
 
 use crate::jwk::{Jwk, KeyPair};
 use crate::util;
@@ -30,6 +30,13 @@ impl EcxCurve {
             Self::X448 => &*OID_X448,
         }
     }
+
+    fn key_size(&self) -> usize {
+        match self {
+            Self::X25519 => 32,
+            Self::X448 => 56,
+        }
+    }
 }
 
 impl Display for EcxCurve {
@@ -384,6 +391,28 @@ impl EcxKeyPair {
         Some(curve)
     }
 
+    /// Create a public key from a raw u-coordinate and check that the length fits the curve.
+    ///
+    /// # Arguments
+    ///
+    /// * `x` - raw public key
+    /// * `curve` - Montgomery curve
+    pub(crate) fn public_key_from_bytes(x: &[u8], curve: EcxCurve) -> anyhow::Result<PKey<Public>> {
+        let expected_len = curve.key_size();
+        if x.len() != expected_len {
+            bail!(
+                "The length of public key must be {} for {}: {}",
+                expected_len,
+                curve,
+                x.len()
+            );
+        }
+
+        let pkcs8 = Self::to_pkcs8(x, true, curve);
+        let public_key = PKey::public_key_from_der(&pkcs8)?;
+        Ok(public_key)
+    }
+
     pub(crate) fn to_pkcs8(input: &[u8], is_public: bool, curve: EcxCurve) -> Vec<u8> {
         let mut builder = DerBuilder::new();
         builder.begin(DerType::Sequence);
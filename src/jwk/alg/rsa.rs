@@ -54,6 +54,10 @@ impl RsaKeyPair {
         }
     }
 
+    pub(crate) fn private_key(&self) -> &PKey<Private> {
+        &self.private_key
+    }
+
     pub(crate) fn into_private_key(self) -> PKey<Private> {
         self.private_key
     }
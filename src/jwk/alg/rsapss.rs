@@ -60,6 +60,10 @@ impl RsaPssKeyPair {
         }
     }
 
+    pub(crate) fn private_key(&self) -> &PKey<Private> {
+        &self.private_key
+    }
+
     pub(crate) fn into_private_key(self) -> PKey<Private> {
         self.private_key
     }
@@ -607,12 +611,7 @@ impl RsaPssKeyPair {
                     {
                         builder.begin(DerType::Sequence);
                         {
-                            builder.append_object_identifier(match hash {
-                                HashAlgorithm::Sha1 => &OID_SHA1,
-                                HashAlgorithm::Sha256 => &OID_SHA256,
-                                HashAlgorithm::Sha384 => &OID_SHA384,
-                                HashAlgorithm::Sha512 => &OID_SHA512,
-                            });
+                            builder.append_object_identifier(hash.oid());
                         }
                         builder.end();
                     }
@@ -625,12 +624,7 @@ impl RsaPssKeyPair {
                             builder.append_object_identifier(&OID_MGF1);
                             builder.begin(DerType::Sequence);
                             {
-                                builder.append_object_identifier(match mgf1_hash {
-                                    HashAlgorithm::Sha1 => &OID_SHA1,
-                                    HashAlgorithm::Sha256 => &OID_SHA256,
-                                    HashAlgorithm::Sha384 => &OID_SHA384,
-                                    HashAlgorithm::Sha512 => &OID_SHA512,
-                                });
+                                builder.append_object_identifier(mgf1_hash.oid());
                             }
                             builder.end();
                         }
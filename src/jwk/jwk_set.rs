@@ -135,6 +135,34 @@ impl JwkSet {
             self.keys.remove(index);
         }
     }
+
+    /// Return a JWK set that has the public keys of this set for publishing,
+    /// and the key IDs of the skipped keys.
+    ///
+    /// The kid and alg parameters are kept. The oct keys are skipped, because they are
+    /// symmetric and don't have a public form. The skipped keys are reported by the kid
+    /// parameter (None for a key without it) so that the caller can log them.
+    /// The other keys that cannot be converted to a public key are reported as an error.
+    pub fn to_public_set(&self) -> Result<(JwkSet, Vec<Option<String>>), JoseError> {
+        let mut jwks = JwkSet::new();
+        let mut skipped = Vec::new();
+        for jwk in &self.keys {
+            if jwk.key_type() == "oct" {
+                skipped.push(jwk.key_id().map(|val| val.to_string()));
+                continue;
+            }
+
+            let mut public_key = jwk.to_public_key()?;
+            if let Some(val) = jwk.key_id() {
+                public_key.set_key_id(val);
+            }
+            if let Some(val) = jwk.algorithm() {
+                public_key.set_algorithm(val);
+            }
+            jwks.push_key(public_key);
+        }
+        Ok((jwks, skipped))
+    }
 }
 
 impl AsRef<Map<String, Value>> for JwkSet {
@@ -205,6 +233,53 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_to_public_set() -> Result<()> {
+        let mut jwks = JwkSet::new();
+        for path in [
+            "jwk/RSA_private.jwk",
+            "jwk/EC_P-256_private.jwk",
+            "jwk/oct_256bit_private.jwk",
+        ] {
+            let mut input = Vec::new();
+            load_file(path)?.read_to_end(&mut input)?;
+            let mut jwk = Jwk::from_bytes(&input)?;
+            jwk.set_key_id(path);
+            jwks.push_key(jwk);
+        }
+
+        let (public_set, skipped) = jwks.to_public_set()?;
+        assert_eq!(public_set.keys().len(), 2);
+        assert_eq!(
+            skipped,
+            vec![Some("jwk/oct_256bit_private.jwk".to_string())]
+        );
+        assert_eq!(public_set.get("jwk/RSA_private.jwk")[0].key_type(), "RSA");
+        assert_eq!(
+            public_set.get("jwk/EC_P-256_private.jwk")[0].key_type(),
+            "EC"
+        );
+        for jwk in public_set.keys() {
+            assert!(jwk.parameter("d").is_none());
+        }
+
+        let public_set = JwkSet::from_bytes(public_set.to_string())?;
+        assert_eq!(public_set.keys().len(), 2);
+
+        let mut jwk = Jwk::new("RSA");
+        jwk.set_parameter("e", Some(Value::String("AQAB".to_string())))?;
+        jwks.push_key(jwk);
+        match jwks.to_public_set() {
+            Err(JoseError::InvalidJwkFormat(err)) => assert_eq!(
+                err.to_string(),
+                "The key type 'RSA' must have parameter 'n'."
+            ),
+            other => panic!("A broken key was dropped silently: {:?}", other),
+        }
+
+        Ok(())
+    }
+
     fn load_file(path: &str) -> Result<File> {
         let mut pb = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
         pb.push("data");
@@ -0,0 +1,37 @@
+use std::fmt::Display;
+
+use openssl::hash::MessageDigest;
+
+/// Represent a hash algorithm for JWK thumbprints (RFC 7638, RFC 9278).
+#[derive(Debug, Eq, PartialEq, Copy, Clone, Default)]
+pub enum ThumbprintHash {
+    #[default]
+    Sha256,
+    Sha384,
+    Sha512,
+}
+
+impl ThumbprintHash {
+    /// Return the hash name registered in the IANA "Named Information Hash Algorithm" registry.
+    pub fn name(&self) -> &str {
+        match self {
+            Self::Sha256 => "sha-256",
+            Self::Sha384 => "sha-384",
+            Self::Sha512 => "sha-512",
+        }
+    }
+
+    pub(crate) fn message_digest(&self) -> MessageDigest {
+        match self {
+            Self::Sha256 => MessageDigest::sha256(),
+            Self::Sha384 => MessageDigest::sha384(),
+            Self::Sha512 => MessageDigest::sha512(),
+        }
+    }
+}
+
+impl Display for ThumbprintHash {
+    fn fmt(&self, fmt: &mut std::fmt::Formatter<'_>) -> Result<(), std::fmt::Error> {
+        fmt.write_str(self.name())
+    }
+}
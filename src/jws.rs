@@ -5,10 +5,14 @@ mod jws_algorithm;
 mod jws_context;
 mod jws_header;
 mod jws_header_set;
+mod jws_verify_outcome;
 
 use std::sync::LazyLock;
 
-use crate::JoseError;
+use anyhow::anyhow;
+
+use crate::jwk::{Jwk, JwkSet};
+use crate::{alg_names, JoseError};
 
 pub use crate::jws::jws_algorithm::JwsAlgorithm;
 pub use crate::jws::jws_algorithm::JwsSigner;
@@ -16,6 +20,7 @@ pub use crate::jws::jws_algorithm::JwsVerifier;
 pub use crate::jws::jws_context::JwsContext;
 pub use crate::jws::jws_header::JwsHeader;
 pub use crate::jws::jws_header_set::JwsHeaderSet;
+pub use crate::jws::jws_verify_outcome::VerifyOutcome;
 
 use crate::jws::alg::hmac::HmacJwsAlgorithm;
 pub use HmacJwsAlgorithm::Hs256 as HS256;
@@ -34,6 +39,7 @@ pub use RsassaPssJwsAlgorithm::Ps512 as PS512;
 
 use crate::jws::alg::ecdsa::EcdsaJwsAlgorithm;
 pub use EcdsaJwsAlgorithm::Es256 as ES256;
+#[cfg(feature = "secp256k1")]
 pub use EcdsaJwsAlgorithm::Es256k as ES256K;
 pub use EcdsaJwsAlgorithm::Es384 as ES384;
 pub use EcdsaJwsAlgorithm::Es512 as ES512;
@@ -173,6 +179,23 @@ where
     DEFAULT_CONTEXT.deserialize_compact_with_selector(input, selector)
 }
 
+/// Verify the input that is formatted by compact serialization with a detached payload.
+///
+/// # Arguments
+///
+/// * `input` - The input data.
+/// * `payload` - The detached payload.
+/// * `max_payload_len` - The maximum length of the detached payload in bytes.
+/// * `verifier` - The JWS verifier.
+pub fn deserialize_compact_detached(
+    input: impl AsRef<[u8]>,
+    payload: &[u8],
+    max_payload_len: usize,
+    verifier: &dyn JwsVerifier,
+) -> Result<JwsHeader, JoseError> {
+    DEFAULT_CONTEXT.deserialize_compact_detached(input, payload, max_payload_len, verifier)
+}
+
 /// Deserialize the input that is formatted by json serialization.
 ///
 /// # Arguments
@@ -204,6 +227,86 @@ where
     DEFAULT_CONTEXT.deserialize_json_with_selector(input, selector)
 }
 
+/// Verify the input that is formatted by compact serialization by using a JWK set,
+/// and return the outcome including the key ID and the algorithm that verified it.
+///
+/// # Arguments
+///
+/// * `input` - The input data.
+/// * `jwk_set` - a JWK set.
+pub fn verify_with_key_set(
+    input: impl AsRef<[u8]>,
+    jwk_set: &JwkSet,
+) -> Result<VerifyOutcome, JoseError> {
+    DEFAULT_CONTEXT.verify_with_key_set(input, jwk_set)
+}
+
+/// Verify the input that is formatted by compact serialization by using the public key
+/// in the jwk header claim.
+///
+/// The caller must establish trust in the embedded key independently,
+/// for example by thumbprint binding.
+///
+/// # Arguments
+///
+/// * `input` - The input data.
+/// * `allowed_algs` - acceptable values of the alg header claim.
+pub fn verify_with_embedded_jwk(
+    input: impl AsRef<[u8]>,
+    allowed_algs: &[&str],
+) -> Result<(Vec<u8>, JwsHeader), JoseError> {
+    DEFAULT_CONTEXT.verify_with_embedded_jwk(input, allowed_algs)
+}
+
+/// Return a verifier of the algorithm for a key in a JWK set.
+///
+/// # Arguments
+///
+/// * `alg` - A value of the alg header claim.
+/// * `jwk` - A key in a JWK set.
+pub(crate) fn verifier_from_jwk(alg: &str, jwk: &Jwk) -> Result<Box<dyn JwsVerifier>, JoseError> {
+    let verifier: Box<dyn JwsVerifier> = match alg {
+        alg_names::HS256 => Box::new(HS256.verifier_from_jwk(jwk)?),
+        alg_names::HS384 => Box::new(HS384.verifier_from_jwk(jwk)?),
+        alg_names::HS512 => Box::new(HS512.verifier_from_jwk(jwk)?),
+        _ => verifier_from_embedded_jwk(alg, jwk)?,
+    };
+    Ok(verifier)
+}
+
+/// Return a verifier of the asymmetric algorithm for a public key in the jwk header claim.
+///
+/// # Arguments
+///
+/// * `alg` - A value of the alg header claim.
+/// * `jwk` - A public key of the jwk header claim.
+pub(crate) fn verifier_from_embedded_jwk(
+    alg: &str,
+    jwk: &Jwk,
+) -> Result<Box<dyn JwsVerifier>, JoseError> {
+    let verifier: Box<dyn JwsVerifier> = match alg {
+        alg_names::RS256 => Box::new(RS256.verifier_from_jwk(jwk)?),
+        alg_names::RS384 => Box::new(RS384.verifier_from_jwk(jwk)?),
+        alg_names::RS512 => Box::new(RS512.verifier_from_jwk(jwk)?),
+        alg_names::PS256 => Box::new(PS256.verifier_from_jwk(jwk)?),
+        alg_names::PS384 => Box::new(PS384.verifier_from_jwk(jwk)?),
+        alg_names::PS512 => Box::new(PS512.verifier_from_jwk(jwk)?),
+        alg_names::ES256 => Box::new(ES256.verifier_from_jwk(jwk)?),
+        #[cfg(feature = "secp256k1")]
+        alg_names::ES256K => Box::new(ES256K.verifier_from_jwk(jwk)?),
+        alg_names::ES384 => Box::new(ES384.verifier_from_jwk(jwk)?),
+        alg_names::ES512 => Box::new(ES512.verifier_from_jwk(jwk)?),
+        alg_names::EDDSA => Box::new(EdDSA.verifier_from_jwk(jwk)?),
+        _ => {
+            return Err(JoseError::UnsupportedSignatureAlgorithm(anyhow!(
+                "The algorithm is not supported for an embedded key: {}",
+                alg
+            )))
+        }
+    };
+    Ok(verifier)
+}
+
 #[cfg(test)]
 mod tests {
     use std::cell::OnceCell;
@@ -212,8 +315,11 @@ mod tests {
 
     use anyhow::Result;
 
-    use crate::jws::{self, EdDSA, JwsHeader, JwsHeaderSet, JwsVerifier, ES256, RS256};
-    use crate::Value;
+    use crate::jwk::{Jwk, JwkSet, ThumbprintHash};
+    use crate::jws::{
+        self, EdDSA, JwsContext, JwsHeader, JwsHeaderSet, JwsVerifier, ES256, HS256, RS256,
+    };
+    use crate::{util, JoseError, Value};
 
     #[test]
     fn test_jws_compact_serialization() -> Result<()> {
@@ -238,6 +344,99 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_jws_verify_with_key_set() -> Result<()> {
+        let mut jwk_set = JwkSet::new();
+        let mut key_pairs = Vec::new();
+        for key_id in ["key-1", "key-2"] {
+            let mut key_pair = ES256.generate_key_pair()?;
+            key_pair.set_key_id(Some(key_id));
+            jwk_set.push_key(key_pair.to_jwk_public_key());
+            key_pairs.push(key_pair);
+        }
+        let mut hmac_key = Jwk::from_bytes(load_file("jwk/oct_256bit_private.jwk")?)?;
+        hmac_key.set_key_id("key-3");
+        jwk_set.push_key(hmac_key.clone());
+
+        let src_payload = b"test payload!";
+
+        let mut header = JwsHeader::new();
+        header.set_key_id("key-2");
+        let signer = ES256.signer_from_jwk(&key_pairs[1].to_jwk_private_key())?;
+        let jws = jws::serialize_compact(src_payload, &header, &signer)?;
+        let outcome = jws::verify_with_key_set(&jws, &jwk_set)?;
+        assert_eq!(outcome.key_id(), Some("key-2"));
+        assert_eq!(outcome.algorithm(), "ES256");
+        assert_eq!(outcome.payload(), src_payload);
+        assert_eq!(outcome.header().key_id(), Some("key-2"));
+
+        let signer = ES256.signer_from_der(key_pairs[1].to_der_private_key())?;
+        let jws = jws::serialize_compact(src_payload, &JwsHeader::new(), &signer)?;
+        let outcome = jws::verify_with_key_set(&jws, &jwk_set)?;
+        assert_eq!(outcome.key_id(), Some("key-2"));
+
+        let signer = HS256.signer_from_jwk(&hmac_key)?;
+        let jws = jws::serialize_compact(src_payload, &JwsHeader::new(), &signer)?;
+        let outcome = jws::verify_with_key_set(&jws, &jwk_set)?;
+        assert_eq!(outcome.key_id(), Some("key-3"));
+        assert_eq!(outcome.algorithm(), "HS256");
+
+        let mut header = JwsHeader::new();
+        header.set_key_id("key-1");
+        let signer = ES256.signer_from_der(key_pairs[1].to_der_private_key())?;
+        let jws = jws::serialize_compact(src_payload, &header, &signer)?;
+        assert!(jws::verify_with_key_set(&jws, &jwk_set).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_jws_verify_with_embedded_jwk() -> Result<()> {
+        let key_pair = ES256.generate_key_pair()?;
+        let public_key = key_pair.to_jwk_public_key();
+
+        let mut header = JwsHeader::new();
+        header.set_token_type("dpop+jwt");
+        header.set_jwk(public_key.clone());
+        let src_payload = br#"{"htm":"POST","htu":"https://server.example.com/token"}"#;
+        let signer = ES256.signer_from_jwk(&key_pair.to_jwk_private_key())?;
+        let jws = jws::serialize_compact(src_payload, &header, &signer)?;
+
+        let (payload, header) = jws::verify_with_embedded_jwk(&jws, &["ES256"])?;
+        assert_eq!(payload, src_payload);
+        let jwk = header.jwk().unwrap();
+        assert_eq!(
+            jwk.thumbprint(ThumbprintHash::Sha256)?,
+            public_key.thumbprint(ThumbprintHash::Sha256)?
+        );
+
+        assert!(jws::verify_with_embedded_jwk(&jws, &["RS256"]).is_err());
+
+        let other = ES256.generate_key_pair()?;
+        let signer = ES256.signer_from_jwk(&other.to_jwk_private_key())?;
+        let forged = jws::serialize_compact(src_payload, &header, &signer)?;
+        assert!(jws::verify_with_embedded_jwk(&forged, &["ES256"]).is_err());
+
+        let mut public_key_with_kid = public_key.clone();
+        public_key_with_kid.set_key_id("key-1");
+        let mut header = JwsHeader::new();
+        header.set_token_type("dpop+jwt");
+        header.set_jwk(public_key_with_kid);
+        let signer = ES256.signer_from_jwk(&key_pair.to_jwk_private_key())?;
+        let jws = jws::serialize_compact(src_payload, &header, &signer)?;
+        let (payload, header) = jws::verify_with_embedded_jwk(&jws, &["ES256"])?;
+        assert_eq!(payload, src_payload);
+        assert_eq!(header.key_id(), None);
+
+        let mut header = JwsHeader::new();
+        header.set_jwk(key_pair.to_jwk_private_key());
+        let signer = ES256.signer_from_jwk(&key_pair.to_jwk_private_key())?;
+        let jws = jws::serialize_compact(src_payload, &header, &signer)?;
+        assert!(jws::verify_with_embedded_jwk(&jws, &["ES256"]).is_err());
+
+        Ok(())
+    }
+
     #[test]
     fn test_jws_compact_serialization_with_selector() -> Result<()> {
         let alg = RS256;
@@ -266,6 +465,151 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_jws_compact_serialization_with_malformed_segment() -> Result<()> {
+        let alg = RS256;
+
+        let private_key = load_file("pem/RSA_2048bit_private.pem")?;
+        let public_key = load_file("pem/RSA_2048bit_public.pem")?;
+
+        let signer = alg.signer_from_pem(&private_key)?;
+        let jwt = jws::serialize_compact(b"test payload!", &JwsHeader::new(), &signer)?;
+        let verifier = alg.verifier_from_pem(&public_key)?;
+        let parts: Vec<&str> = jwt.split('.').collect();
+
+        for (index, expected) in [
+            (0, "segment 1 (header) is not valid base64url"),
+            (2, "segment 3 (signature) is not valid base64url"),
+        ] {
+            let mut malformed = parts.clone();
+            malformed[index] = "!!";
+            match jws::deserialize_compact(malformed.join("."), &verifier) {
+                Err(JoseError::InvalidJwsFormat(err)) => {
+                    assert!(err.to_string().contains(expected), "{}", err)
+                }
+                other => panic!("A malformed segment was accepted: {:?}", other),
+            }
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_jws_detached_unencoded_payload_rfc7797() -> Result<()> {
+        let jwk = Jwk::from_bytes(
+            br#"{"kty":"oct","k":"AyM1SysPpbyDfgZld3umj1qzKObwVMkoqQ-EstJQLr_T-1qS0gZH75aKtMN3Yj0iPS4hcgUuTwjAzZr1Z9CAow"}"#,
+        )?;
+        let verifier = HS256.verifier_from_jwk(&jwk)?;
+        let payload = b"$.02";
+
+        let mut context = JwsContext::new();
+        context.add_acceptable_critical("b64");
+
+        // RFC 7797 Appendix A.4
+        let jws = "eyJhbGciOiJIUzI1NiIsImI2NCI6ZmFsc2UsImNyaXQiOlsiYjY0Il19..A5dxf2s96_n5FLueVuW1Z_vh161FwXZC4YLPff6dmDY";
+        let header = context.deserialize_compact_detached(jws, payload, 4, &verifier)?;
+        assert_eq!(header.base64url_encode_payload(), Some(false));
+
+        match context.deserialize_compact_detached(jws, payload, 3, &verifier) {
+            Err(JoseError::InvalidJwsFormat(err)) => assert_eq!(
+                err.to_string(),
+                "The detached payload size must be 3 or less: 4"
+            ),
+            other => panic!("An over-limit payload was accepted: {:?}", other),
+        }
+        assert!(context
+            .deserialize_compact_detached(jws, b"$.03", 4, &verifier)
+            .is_err());
+
+        // RFC 7797 Appendix A.1 with the payload detached
+        let jws = "eyJhbGciOiJIUzI1NiJ9..5mvfOroL-g7HyqJoozehmsaqmvTYGEq5jTI1gVvoEoQ";
+        jws::deserialize_compact_detached(jws, payload, 1024, &verifier)?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_jws_unencoded_payload_rfc7797() -> Result<()> {
+        let jwk = Jwk::from_bytes(
+            br#"{"kty":"oct","k":"AyM1SysPpbyDfgZld3umj1qzKObwVMkoqQ-EstJQLr_T-1qS0gZH75aKtMN3Yj0iPS4hcgUuTwjAzZr1Z9CAow"}"#,
+        )?;
+        let signer = HS256.signer_from_jwk(&jwk)?;
+        let verifier = HS256.verifier_from_jwk(&jwk)?;
+        let payload = b"$.02";
+
+        let mut context = JwsContext::new();
+        context.add_acceptable_critical("b64");
+
+        // RFC 7797 Appendix A.1
+        let mut header = JwsHeader::new();
+        header.set_algorithm("HS256");
+        let jws = context.serialize_compact(payload, &header, &signer)?;
+        assert_eq!(
+            jws,
+            "eyJhbGciOiJIUzI1NiJ9.JC4wMg.5mvfOroL-g7HyqJoozehmsaqmvTYGEq5jTI1gVvoEoQ"
+        );
+
+        // RFC 7797 Appendix A.5
+        let mut header = JwsHeaderSet::new();
+        header.set_algorithm("HS256", true);
+        header.set_base64url_encode_payload(false);
+        header.set_critical(&vec!["b64"])?;
+        let json = context.serialize_flattened_json(payload, &header, &signer)?;
+        assert_eq!(
+            json,
+            concat!(
+                r#"{"protected":"eyJhbGciOiJIUzI1NiIsImI2NCI6ZmFsc2UsImNyaXQiOlsiYjY0Il19","#,
+                r#""payload":"$.02","#,
+                r#""signature":"A5dxf2s96_n5FLueVuW1Z_vh161FwXZC4YLPff6dmDY"}"#
+            )
+        );
+        let (dst_payload, dst_header) = context.deserialize_json(&json, &verifier)?;
+        assert_eq!(dst_payload, payload);
+        assert_eq!(dst_header.base64url_encode_payload(), Some(false));
+
+        let mut header = JwsHeader::new();
+        header.set_base64url_encode_payload(false);
+        header.set_critical(&vec!["b64"])?;
+        let jws = context.serialize_compact(b"unencoded payload", &header, &signer)?;
+        assert!(jws.contains(".unencoded payload."));
+        let (dst_payload, _) = context.deserialize_compact(&jws, &verifier)?;
+        assert_eq!(dst_payload, b"unencoded payload");
+
+        let mut header = JwsHeader::new();
+        header.set_base64url_encode_payload(false);
+        assert!(context
+            .serialize_compact(b"unencoded payload", &header, &signer)
+            .is_err());
+
+        let mut header = JwsHeaderSet::new();
+        header.set_base64url_encode_payload(false);
+        assert!(context
+            .serialize_flattened_json(payload, &header, &signer)
+            .is_err());
+
+        // b64 is false but not listed in crit, with a valid signature.
+        let signing_input = format!(
+            "{}.unencoded",
+            util::encode_base64_urlsafe_nopad(r#"{"alg":"HS256","b64":false}"#)
+        );
+        let signature = signer.sign(signing_input.as_bytes())?;
+        verifier.verify(signing_input.as_bytes(), &signature)?;
+        let jws = format!(
+            "{}.{}",
+            signing_input,
+            util::encode_base64_urlsafe_nopad(signature)
+        );
+        match context.deserialize_compact(&jws, &verifier) {
+            Err(JoseError::InvalidJwsFormat(err)) => assert_eq!(
+                err.to_string(),
+                "The b64 header claim name must be in critical."
+            ),
+            other => panic!("b64 without crit was accepted: {:?}", other),
+        }
+
+        Ok(())
+    }
+
     #[test]
     fn test_jws_json_serialization() -> Result<()> {
         let alg = RS256;
@@ -288,6 +632,19 @@ mod tests {
         assert_eq!(src_header.token_type(), dst_header.token_type());
         assert_eq!(src_payload.to_vec(), dst_payload);
 
+        src_header.set_algorithm(alg.name(), false);
+        match jws::serialize_flattened_json(src_payload, &src_header, &signer) {
+            Err(JoseError::InvalidJwsFormat(err)) => assert_eq!(
+                err.to_string(),
+                "The JWS alg header claim must be protected."
+            ),
+            other => panic!("An unprotected alg header claim was accepted: {:?}", other),
+        }
+        assert!(jws::serialize_general_json(src_payload, &[(&src_header, &*signer)]).is_err());
+        assert!(src_header
+            .set_claim("alg", Some(Value::from(alg.name())), false)
+            .is_err());
+
         Ok(())
     }
 
@@ -1,9 +1,13 @@
 use std::fmt::Display;
 use std::ops::Deref;
 
-use anyhow::bail;
-use openssl::hash::MessageDigest;
+use anyhow::{anyhow, bail};
+use openssl::hash::{self, MessageDigest};
+use openssl::md::{Md, MdRef};
 use openssl::pkey::{PKey, Private, Public};
+#[cfg(ossl320)]
+use openssl::pkey_ctx::NonceType;
+use openssl::pkey_ctx::PkeyCtx;
 use openssl::sign::{Signer, Verifier};
 
 use crate::jwk::{
@@ -23,7 +27,8 @@ pub enum EcdsaJwsAlgorithm {
     Es384,
     /// ECDSA using P-521 and SHA-512
     Es512,
-    /// ECDSA using secp256k1 curve and SHA-256
+    /// ECDSA using secp256k1 curve and SHA-256 (the secp256k1 feature)
+    #[cfg(feature = "secp256k1")]
     Es256k,
 }
 
@@ -71,6 +76,7 @@ impl EcdsaJwsAlgorithm {
             algorithm: self.clone(),
             private_key: key_pair.into_private_key(),
             key_id: None,
+            deterministic_nonces: false,
         })
     }
 
@@ -90,6 +96,7 @@ impl EcdsaJwsAlgorithm {
             algorithm: self.clone(),
             private_key: key_pair.into_private_key(),
             key_id: None,
+            deterministic_nonces: false,
         })
     }
 
@@ -114,7 +121,11 @@ impl EcdsaJwsAlgorithm {
             }
             match jwk.curve() {
                 Some(val) if val == self.curve().name() => {}
-                Some(val) => bail!("A parameter crv must be {} but {}", self.name(), val),
+                Some(val) => bail!(
+                    "A parameter crv must be {} but {}",
+                    self.curve().name(),
+                    val
+                ),
                 None => bail!("A parameter crv is required."),
             }
 
@@ -126,6 +137,7 @@ impl EcdsaJwsAlgorithm {
                 algorithm: self.clone(),
                 private_key,
                 key_id,
+                deterministic_nonces: false,
             })
         })()
         .map_err(|err| JoseError::InvalidKeyFormat(err))
@@ -174,12 +186,11 @@ impl EcdsaJwsAlgorithm {
             let (alg, data) = util::parse_pem(input.as_ref())?;
 
             let spki = match alg.as_str() {
-                "PUBLIC KEY" => {
-                    if let None = EcKeyPair::detect_pkcs8(&data, true) {
-                        bail!("PEM contents is expected SubjectPublicKeyInfo wrapped key.");
-                    }
-                    &data
-                }
+                "PUBLIC KEY" => match EcKeyPair::detect_pkcs8(&data, true) {
+                    Some(curve) if curve == self.curve() => &data,
+                    Some(curve) => bail!("The curve is mismatched: {}", curve),
+                    None => bail!("PEM contents is expected SubjectPublicKeyInfo wrapped key."),
+                },
                 alg => bail!("Inappropriate algorithm: {}", alg),
             };
 
@@ -238,13 +249,7 @@ impl EcdsaJwsAlgorithm {
                 None => bail!("A parameter y is required."),
             };
 
-            let mut vec = Vec::with_capacity(1 + x.len() + y.len());
-            vec.push(0x04);
-            vec.extend_from_slice(&x);
-            vec.extend_from_slice(&y);
-
-            let pkcs8 = EcKeyPair::to_pkcs8(&vec, true, self.curve());
-            let public_key = PKey::public_key_from_der(&pkcs8)?;
+            let public_key = EcKeyPair::public_key_from_coordinates(&x, &y, curve)?;
             let key_id = jwk.key_id().map(|val| val.to_string());
 
             Ok(EcdsaJwsVerifier {
@@ -261,13 +266,16 @@ impl EcdsaJwsAlgorithm {
             Self::Es256 => EcCurve::P256,
             Self::Es384 => EcCurve::P384,
             Self::Es512 => EcCurve::P521,
+            #[cfg(feature = "secp256k1")]
             Self::Es256k => EcCurve::Secp256k1,
         }
     }
 
     fn signature_len(&self) -> usize {
         match self {
-            Self::Es256 | Self::Es256k => 64,
+            Self::Es256 => 64,
+            #[cfg(feature = "secp256k1")]
+            Self::Es256k => 64,
             Self::Es384 => 96,
             Self::Es512 => 132,
         }
@@ -278,6 +286,7 @@ impl EcdsaJwsAlgorithm {
             Self::Es256 => HashAlgorithm::Sha256,
             Self::Es384 => HashAlgorithm::Sha384,
             Self::Es512 => HashAlgorithm::Sha512,
+            #[cfg(feature = "secp256k1")]
             Self::Es256k => HashAlgorithm::Sha256,
         }
     }
@@ -289,6 +298,7 @@ impl JwsAlgorithm for EcdsaJwsAlgorithm {
             Self::Es256 => "ES256",
             Self::Es384 => "ES384",
             Self::Es512 => "ES512",
+            #[cfg(feature = "secp256k1")]
             Self::Es256k => "ES256K",
         }
     }
@@ -317,6 +327,7 @@ pub struct EcdsaJwsSigner {
     algorithm: EcdsaJwsAlgorithm,
     private_key: PKey<Private>,
     key_id: Option<String>,
+    deterministic_nonces: bool,
 }
 
 impl EcdsaJwsSigner {
@@ -327,6 +338,42 @@ impl EcdsaJwsSigner {
     pub fn remove_key_id(&mut self) {
         self.key_id = None;
     }
+
+    /// Return a signer that derives nonces deterministically as described in RFC 6979
+    /// instead of generating them randomly.
+    ///
+    /// The nonces are derived by OpenSSL, so this requires OpenSSL 3.2.0 or newer.
+    #[cfg(ossl320)]
+    pub fn with_deterministic_nonces(mut self) -> Self {
+        self.deterministic_nonces = true;
+        self
+    }
+
+    /// Test this signer derives nonces deterministically.
+    pub fn is_deterministic_nonces(&self) -> bool {
+        self.deterministic_nonces
+    }
+
+    fn sign_random(&self, message: &[u8]) -> anyhow::Result<Vec<u8>> {
+        let md = self.algorithm.hash_algorithm().message_digest()?;
+        let mut signer = Signer::new(md, &self.private_key)?;
+        signer.update(message)?;
+        Ok(signer.sign_to_vec()?)
+    }
+
+    #[cfg(ossl320)]
+    fn sign_deterministic(&self, message: &[u8]) -> anyhow::Result<Vec<u8>> {
+        let md = self.algorithm.hash_algorithm().message_digest()?;
+        let digest = hash::hash(md, message)?;
+
+        let mut ctx = PkeyCtx::new(&self.private_key)?;
+        ctx.sign_init()?;
+        ctx.set_signature_md(signature_md(self.algorithm))?;
+        ctx.set_nonce_type(NonceType::DETERMINISTIC_K)?;
+        let mut der_signature = Vec::new();
+        ctx.sign_to_vec(&digest, &mut der_signature)?;
+        Ok(der_signature)
+    }
 }
 
 impl JwsSigner for EcdsaJwsSigner {
@@ -347,15 +394,14 @@ impl JwsSigner for EcdsaJwsSigner {
 
     fn sign(&self, message: &[u8]) -> Result<Vec<u8>, JoseError> {
         (|| -> anyhow::Result<Vec<u8>> {
-            let md = match &self.algorithm.hash_algorithm() {
-                HashAlgorithm::Sha1 => MessageDigest::sha1(),
-                HashAlgorithm::Sha256 => MessageDigest::sha256(),
-                HashAlgorithm::Sha384 => MessageDigest::sha384(),
-                HashAlgorithm::Sha512 => MessageDigest::sha512(),
+            #[cfg(ossl320)]
+            let der_signature = if self.deterministic_nonces {
+                self.sign_deterministic(message)?
+            } else {
+                self.sign_random(message)?
             };
-            let mut signer = Signer::new(md, &self.private_key)?;
-            signer.update(message)?;
-            let der_signature = signer.sign_to_vec()?;
+            #[cfg(not(ossl320))]
+            let der_signature = self.sign_random(message)?;
 
             let signature_len = self.signature_len();
             let sep = signature_len / 2;
@@ -412,6 +458,30 @@ impl EcdsaJwsVerifier {
     pub fn remove_key_id(&mut self) {
         self.key_id = None;
     }
+
+    fn to_der_signature(&self, signature: &[u8]) -> anyhow::Result<Vec<u8>> {
+        let signature_len = self.algorithm.signature_len();
+        if signature.len() != signature_len {
+            bail!(
+                "A signature size must be {}: {}",
+                signature_len,
+                signature.len()
+            );
+        }
+
+        let mut der_builder = DerBuilder::new();
+        der_builder.begin(DerType::Sequence);
+        {
+            let sep = signature_len / 2;
+
+            let zeros = signature[..sep].iter().take_while(|b| **b == 0).count();
+            der_builder.append_integer_from_be_slice(&signature[zeros..sep], false);
+            let zeros = signature[sep..].iter().take_while(|b| **b == 0).count();
+            der_builder.append_integer_from_be_slice(&signature[(sep + zeros)..], false);
+        }
+        der_builder.end();
+        Ok(der_builder.build())
+    }
 }
 
 impl JwsVerifier for EcdsaJwsVerifier {
@@ -428,34 +498,9 @@ impl JwsVerifier for EcdsaJwsVerifier {
 
     fn verify(&self, message: &[u8], signature: &[u8]) -> Result<(), JoseError> {
         (|| -> anyhow::Result<()> {
-            let signature_len = self.algorithm.signature_len();
-            if signature.len() != signature_len {
-                bail!(
-                    "A signature size must be {}: {}",
-                    signature_len,
-                    signature.len()
-                );
-            }
-
-            let mut der_builder = DerBuilder::new();
-            der_builder.begin(DerType::Sequence);
-            {
-                let sep = signature_len / 2;
+            let der_signature = self.to_der_signature(signature)?;
 
-                let zeros = signature[..sep].iter().take_while(|b| **b == 0).count();
-                der_builder.append_integer_from_be_slice(&signature[zeros..sep], true);
-                let zeros = signature[sep..].iter().take_while(|b| **b == 0).count();
-                der_builder.append_integer_from_be_slice(&signature[(sep + zeros)..], true);
-            }
-            der_builder.end();
-            let der_signature = der_builder.build();
-
-            let md = match &self.algorithm.hash_algorithm() {
-                HashAlgorithm::Sha1 => MessageDigest::sha1(),
-                HashAlgorithm::Sha256 => MessageDigest::sha256(),
-                HashAlgorithm::Sha384 => MessageDigest::sha384(),
-                HashAlgorithm::Sha512 => MessageDigest::sha512(),
-            };
+            let md = self.algorithm.hash_algorithm().message_digest()?;
             let mut verifier = Verifier::new(md, &self.public_key)?;
             verifier.update(message)?;
             if !verifier.verify(&der_signature)? {
@@ -466,6 +511,42 @@ impl JwsVerifier for EcdsaJwsVerifier {
         .map_err(|err| JoseError::InvalidSignature(err))
     }
 
+    fn verify_batch(&self, items: &[(&[u8], &[u8])]) -> Vec<Result<(), JoseError>> {
+        // The key context is initialized once and each item only hashes its message.
+        let context = (|| -> anyhow::Result<(MessageDigest, PkeyCtx<Public>)> {
+            let md = self.algorithm.hash_algorithm().message_digest()?;
+            let mut ctx = PkeyCtx::new(&self.public_key)?;
+            ctx.verify_init()?;
+            ctx.set_signature_md(signature_md(self.algorithm))?;
+            Ok((md, ctx))
+        })();
+
+        let (md, mut ctx) = match context {
+            Ok(val) => val,
+            Err(err) => {
+                return items
+                    .iter()
+                    .map(|_| Err(JoseError::InvalidSignature(anyhow!("{}", err))))
+                    .collect()
+            }
+        };
+
+        items
+            .iter()
+            .map(|(message, signature)| {
+                (|| -> anyhow::Result<()> {
+                    let der_signature = self.to_der_signature(signature)?;
+                    let digest = hash::hash(md, message)?;
+                    if !ctx.verify(&digest, &der_signature)? {
+                        bail!("The signature does not match.");
+                    }
+                    Ok(())
+                })()
+                .map_err(JoseError::InvalidSignature)
+            })
+            .collect()
+    }
+
     fn box_clone(&self) -> Box<dyn JwsVerifier> {
         Box::new(self.clone())
     }
@@ -479,6 +560,16 @@ impl Deref for EcdsaJwsVerifier {
     }
 }
 
+fn signature_md(algorithm: EcdsaJwsAlgorithm) -> &'static MdRef {
+    match algorithm {
+        EcdsaJwsAlgorithm::Es256 => Md::sha256(),
+        #[cfg(feature = "secp256k1")]
+        EcdsaJwsAlgorithm::Es256k => Md::sha256(),
+        EcdsaJwsAlgorithm::Es384 => Md::sha384(),
+        EcdsaJwsAlgorithm::Es512 => Md::sha512(),
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -487,6 +578,62 @@ mod tests {
     use std::fs;
     use std::path::PathBuf;
 
+    #[test]
+    fn ecdsa_curve_compatibility() -> Result<()> {
+        let pairs = [
+            (EcdsaJwsAlgorithm::Es256, "P-256"),
+            (EcdsaJwsAlgorithm::Es384, "P-384"),
+            (EcdsaJwsAlgorithm::Es512, "P-521"),
+            #[cfg(feature = "secp256k1")]
+            (EcdsaJwsAlgorithm::Es256k, "secp256k1"),
+        ];
+
+        for (_, curve) in &pairs {
+            let private_key = load_file(&format!("pem/EC_{}_private.pem", curve))?;
+            let public_key = load_file(&format!("pem/EC_{}_public.pem", curve))?;
+
+            for (other_alg, other_curve) in &pairs {
+                let result = (
+                    other_alg.signer_from_pem(&private_key),
+                    other_alg.verifier_from_pem(&public_key),
+                );
+                if curve == other_curve {
+                    let signature = result.0?.sign(b"abcde12345")?;
+                    result.1?.verify(b"abcde12345", &signature)?;
+                } else {
+                    assert!(matches!(result.0, Err(JoseError::InvalidKeyFormat(_))));
+                    assert!(matches!(result.1, Err(JoseError::InvalidKeyFormat(_))));
+                }
+            }
+        }
+
+        let key_pair = EcdsaJwsAlgorithm::Es256.generate_key_pair()?;
+        let alg = EcdsaJwsAlgorithm::Es512;
+        assert!(matches!(
+            alg.signer_from_der(key_pair.to_der_private_key()),
+            Err(JoseError::InvalidKeyFormat(_))
+        ));
+        assert!(matches!(
+            alg.verifier_from_der(key_pair.to_der_public_key()),
+            Err(JoseError::InvalidKeyFormat(_))
+        ));
+
+        let mut jwk = key_pair.to_jwk_key_pair();
+        jwk.set_algorithm("ES512");
+        assert!(matches!(
+            alg.signer_from_jwk(&jwk),
+            Err(JoseError::InvalidKeyFormat(_))
+        ));
+        let mut jwk = key_pair.to_jwk_public_key();
+        jwk.set_algorithm("ES512");
+        assert!(matches!(
+            alg.verifier_from_jwk(&jwk),
+            Err(JoseError::InvalidKeyFormat(_))
+        ));
+
+        Ok(())
+    }
+
     #[test]
     fn sign_and_verify_ecdsa_generated_der() -> Result<()> {
         let input = b"abcde12345";
@@ -495,6 +642,7 @@ mod tests {
             EcdsaJwsAlgorithm::Es256,
             EcdsaJwsAlgorithm::Es384,
             EcdsaJwsAlgorithm::Es512,
+            #[cfg(feature = "secp256k1")]
             EcdsaJwsAlgorithm::Es256k,
         ] {
             let key_pair = alg.generate_key_pair()?;
@@ -517,6 +665,7 @@ mod tests {
             EcdsaJwsAlgorithm::Es256,
             EcdsaJwsAlgorithm::Es384,
             EcdsaJwsAlgorithm::Es512,
+            #[cfg(feature = "secp256k1")]
             EcdsaJwsAlgorithm::Es256k,
         ] {
             let key_pair = alg.generate_key_pair()?;
@@ -539,6 +688,7 @@ mod tests {
             EcdsaJwsAlgorithm::Es256,
             EcdsaJwsAlgorithm::Es384,
             EcdsaJwsAlgorithm::Es512,
+            #[cfg(feature = "secp256k1")]
             EcdsaJwsAlgorithm::Es256k,
         ] {
             let key_pair = alg.generate_key_pair()?;
@@ -561,6 +711,7 @@ mod tests {
             EcdsaJwsAlgorithm::Es256,
             EcdsaJwsAlgorithm::Es384,
             EcdsaJwsAlgorithm::Es512,
+            #[cfg(feature = "secp256k1")]
             EcdsaJwsAlgorithm::Es256k,
         ] {
             let key_pair = alg.generate_key_pair()?;
@@ -583,6 +734,7 @@ mod tests {
             EcdsaJwsAlgorithm::Es256,
             EcdsaJwsAlgorithm::Es384,
             EcdsaJwsAlgorithm::Es512,
+            #[cfg(feature = "secp256k1")]
             EcdsaJwsAlgorithm::Es256k,
         ] {
             let key_pair = alg.generate_key_pair()?;
@@ -597,6 +749,39 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    #[cfg(feature = "secp256k1")]
+    fn sign_and_verify_es256k_generated() -> Result<()> {
+        use openssl::bn::BigNum;
+
+        // RFC 8812 Section 3.2: ES256K uses secp256k1 with SHA-256 and R || S.
+        let input = b"abcde12345";
+        let alg = EcdsaJwsAlgorithm::Es256k;
+        assert_eq!(alg.name(), "ES256K");
+
+        let key_pair = alg.generate_key_pair()?;
+        assert_eq!(key_pair.curve(), EcCurve::Secp256k1);
+
+        let public_key = key_pair.to_jwk_public_key();
+        assert_eq!(public_key.parameter("crv"), Some(&Value::from("secp256k1")));
+
+        let signer = alg.signer_from_jwk(&key_pair.to_jwk_private_key())?;
+        let signature = signer.sign(input)?;
+        assert_eq!(signature.len(), 64);
+
+        let r = BigNum::from_slice(&signature[..32])?;
+        let s = BigNum::from_slice(&signature[32..])?;
+        let ecdsa_sig = openssl::ecdsa::EcdsaSig::from_private_components(r, s)?;
+        let digest = openssl::sha::sha256(input);
+        let ec_key = PKey::public_key_from_der(&key_pair.to_der_public_key())?.ec_key()?;
+        assert!(ecdsa_sig.verify(&digest, &ec_key)?);
+
+        let verifier = alg.verifier_from_jwk(&public_key)?;
+        verifier.verify(input, &signature)?;
+
+        Ok(())
+    }
+
     #[test]
     fn sign_and_verify_ecdsa_jwt() -> Result<()> {
         let input = b"abcde12345";
@@ -605,18 +790,21 @@ mod tests {
             EcdsaJwsAlgorithm::Es256,
             EcdsaJwsAlgorithm::Es384,
             EcdsaJwsAlgorithm::Es512,
+            #[cfg(feature = "secp256k1")]
             EcdsaJwsAlgorithm::Es256k,
         ] {
             let private_key = load_file(match alg {
                 EcdsaJwsAlgorithm::Es256 => "jwk/EC_P-256_private.jwk",
                 EcdsaJwsAlgorithm::Es384 => "jwk/EC_P-384_private.jwk",
                 EcdsaJwsAlgorithm::Es512 => "jwk/EC_P-521_private.jwk",
+                #[cfg(feature = "secp256k1")]
                 EcdsaJwsAlgorithm::Es256k => "jwk/EC_secp256k1_private.jwk",
             })?;
             let public_key = load_file(match alg {
                 EcdsaJwsAlgorithm::Es256 => "jwk/EC_P-256_public.jwk",
                 EcdsaJwsAlgorithm::Es384 => "jwk/EC_P-384_public.jwk",
                 EcdsaJwsAlgorithm::Es512 => "jwk/EC_P-521_public.jwk",
+                #[cfg(feature = "secp256k1")]
                 EcdsaJwsAlgorithm::Es256k => "jwk/EC_secp256k1_public.jwk",
             })?;
 
@@ -638,6 +826,7 @@ mod tests {
             EcdsaJwsAlgorithm::Es256,
             EcdsaJwsAlgorithm::Es384,
             EcdsaJwsAlgorithm::Es512,
+            #[cfg(feature = "secp256k1")]
             EcdsaJwsAlgorithm::Es256k,
         ] {
             println!("{}", alg);
@@ -646,12 +835,14 @@ mod tests {
                 EcdsaJwsAlgorithm::Es256 => "pem/EC_P-256_private.pem",
                 EcdsaJwsAlgorithm::Es384 => "pem/EC_P-384_private.pem",
                 EcdsaJwsAlgorithm::Es512 => "pem/EC_P-521_private.pem",
+                #[cfg(feature = "secp256k1")]
                 EcdsaJwsAlgorithm::Es256k => "pem/EC_secp256k1_private.pem",
             })?;
             let public_key = load_file(match alg {
                 EcdsaJwsAlgorithm::Es256 => "pem/EC_P-256_public.pem",
                 EcdsaJwsAlgorithm::Es384 => "pem/EC_P-384_public.pem",
                 EcdsaJwsAlgorithm::Es512 => "pem/EC_P-521_public.pem",
+                #[cfg(feature = "secp256k1")]
                 EcdsaJwsAlgorithm::Es256k => "pem/EC_secp256k1_public.pem",
             })?;
 
@@ -673,18 +864,21 @@ mod tests {
             EcdsaJwsAlgorithm::Es256,
             EcdsaJwsAlgorithm::Es384,
             EcdsaJwsAlgorithm::Es512,
+            #[cfg(feature = "secp256k1")]
             EcdsaJwsAlgorithm::Es256k,
         ] {
             let private_key = load_file(match alg {
                 EcdsaJwsAlgorithm::Es256 => "der/EC_P-256_pkcs8_private.der",
                 EcdsaJwsAlgorithm::Es384 => "der/EC_P-384_pkcs8_private.der",
                 EcdsaJwsAlgorithm::Es512 => "der/EC_P-521_pkcs8_private.der",
+                #[cfg(feature = "secp256k1")]
                 EcdsaJwsAlgorithm::Es256k => "der/EC_secp256k1_pkcs8_private.der",
             })?;
             let public_key = load_file(match alg {
                 EcdsaJwsAlgorithm::Es256 => "der/EC_P-256_spki_public.der",
                 EcdsaJwsAlgorithm::Es384 => "der/EC_P-384_spki_public.der",
                 EcdsaJwsAlgorithm::Es512 => "der/EC_P-521_spki_public.der",
+                #[cfg(feature = "secp256k1")]
                 EcdsaJwsAlgorithm::Es256k => "der/EC_secp256k1_spki_public.der",
             })?;
 
@@ -706,6 +900,7 @@ mod tests {
             EcdsaJwsAlgorithm::Es256,
             EcdsaJwsAlgorithm::Es384,
             EcdsaJwsAlgorithm::Es512,
+            #[cfg(feature = "secp256k1")]
             EcdsaJwsAlgorithm::Es256k,
         ] {
             let signer_key_pair = alg.generate_key_pair()?;
@@ -723,6 +918,105 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn verify_batch_ecdsa() -> Result<()> {
+        for alg in &[
+            EcdsaJwsAlgorithm::Es256,
+            EcdsaJwsAlgorithm::Es384,
+            EcdsaJwsAlgorithm::Es512,
+            #[cfg(feature = "secp256k1")]
+            EcdsaJwsAlgorithm::Es256k,
+        ] {
+            let key_pair = alg.generate_key_pair()?;
+
+            let signer = alg.signer_from_der(key_pair.to_der_private_key())?;
+            let signature_1 = signer.sign(b"message-1")?;
+            let signature_2 = signer.sign(b"message-2")?;
+
+            let verifier = alg.verifier_from_der(key_pair.to_der_public_key())?;
+            let results = verifier.verify_batch(&[
+                (b"message-1", &signature_1),
+                (b"message-2", &signature_1),
+                (b"message-2", &signature_2),
+                (b"message-2", &signature_2[1..]),
+            ]);
+
+            assert_eq!(results.len(), 4);
+            assert!(results[0].is_ok());
+            assert!(results[1].is_err());
+            assert!(results[2].is_ok());
+            assert!(results[3].is_err());
+        }
+
+        Ok(())
+    }
+
+    #[cfg(ossl320)]
+    #[test]
+    fn sign_and_verify_ecdsa_deterministic() -> Result<()> {
+        let input = b"abcde12345";
+
+        for alg in &[
+            EcdsaJwsAlgorithm::Es256,
+            EcdsaJwsAlgorithm::Es384,
+            EcdsaJwsAlgorithm::Es512,
+            #[cfg(feature = "secp256k1")]
+            EcdsaJwsAlgorithm::Es256k,
+        ] {
+            let key_pair = alg.generate_key_pair()?;
+
+            let signer = alg.signer_from_der(key_pair.to_der_private_key())?;
+            assert!(!signer.is_deterministic_nonces());
+            let signer = signer.with_deterministic_nonces();
+            assert!(signer.is_deterministic_nonces());
+
+            let signature1 = signer.sign(input)?;
+            let signature2 = signer.sign(input)?;
+            assert_eq!(signature1, signature2);
+
+            let verifier = alg.verifier_from_der(key_pair.to_der_public_key())?;
+            verifier.verify(input, &signature1)?;
+        }
+
+        Ok(())
+    }
+
+    #[cfg(ossl320)]
+    #[test]
+    fn sign_ecdsa_deterministic_rfc6979_vector() -> Result<()> {
+        use openssl::bn::{BigNum, BigNumContext};
+        use openssl::ec::EcPoint;
+
+        // RFC 6979 Appendix A.2.5: P-256, SHA-256, message "sample"
+        let group = openssl::ec::EcGroup::from_curve_name(openssl::nid::Nid::X9_62_PRIME256V1)?;
+        let d = BigNum::from_hex_str(
+            "C9AFA9D845BA75166B5C215767B1D6934E50C3DB36E89B127B8A622B120F6721",
+        )?;
+        let mut ctx = BigNumContext::new()?;
+        let mut public_key = EcPoint::new(&group)?;
+        public_key.mul_generator2(&group, &d, &mut ctx)?;
+        let ec_key = openssl::ec::EcKey::from_private_components(&group, &d, &public_key)?;
+
+        let signer = EcdsaJwsAlgorithm::Es256
+            .signer_from_der(ec_key.private_key_to_der()?)?
+            .with_deterministic_nonces();
+        let signature = signer.sign(b"sample")?;
+
+        let mut expected = BigNum::from_hex_str(
+            "EFD48B2AACB6A8FD1140DD9CD45E81D69D2C877B56AAF991C34D0EA84EAF3716",
+        )?
+        .to_vec();
+        expected.extend_from_slice(
+            &BigNum::from_hex_str(
+                "F7CB1C942D657C41D436C7A1B6E29F65F3E900DBB9AFF4064DC4AB2F843ACDA8",
+            )?
+            .to_vec(),
+        );
+        assert_eq!(signature, expected);
+
+        Ok(())
+    }
+
     fn load_file(path: &str) -> Result<Vec<u8>> {
         let mut pb = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
         pb.push("data");
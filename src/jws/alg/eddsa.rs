@@ -220,8 +220,7 @@ impl EddsaJwsAlgorithm {
                 None => bail!("A parameter x is required."),
             };
 
-            let pkcs8 = EdKeyPair::to_pkcs8(&x, true, curve);
-            let public_key = PKey::public_key_from_der(&pkcs8)?;
+            let public_key = EdKeyPair::public_key_from_bytes(&x, curve)?;
             let key_id = jwk.key_id().map(|val| val.to_string());
 
             Ok(EddsaJwsVerifier {
@@ -379,6 +378,36 @@ mod tests {
     use std::fs;
     use std::path::PathBuf;
 
+    #[test]
+    fn reject_eddsa_jwk_with_short_x() -> Result<()> {
+        for (curve, expected_len) in [("Ed25519", 32), ("Ed448", 57)] {
+            let mut jwk = Jwk::new("OKP");
+            jwk.set_parameter("crv", Some(Value::String(curve.to_string())))?;
+            jwk.set_parameter(
+                "x",
+                Some(Value::String(util::encode_base64_urlsafe_nopad(vec![
+                    1u8;
+                    expected_len - 1
+                ]))),
+            )?;
+
+            match EddsaJwsAlgorithm::Eddsa.verifier_from_jwk(&jwk) {
+                Err(JoseError::InvalidKeyFormat(err)) => assert_eq!(
+                    err.to_string(),
+                    format!(
+                        "The length of public key must be {} for {}: {}",
+                        expected_len,
+                        curve,
+                        expected_len - 1
+                    )
+                ),
+                other => panic!("A short x parameter was accepted: {:?}", other),
+            }
+        }
+
+        Ok(())
+    }
+
     #[test]
     fn sign_and_verify_eddsa_generated_der() -> Result<()> {
         let input = b"abcde12345";
@@ -1,13 +1,17 @@
 use std::fmt::Display;
 use std::ops::Deref;
 
-use anyhow::bail;
-use openssl::hash::MessageDigest;
+use anyhow::{anyhow, bail};
+use openssl::hash::{hash, MessageDigest};
+use openssl::md::Md;
 use openssl::pkey::{PKey, Private, Public};
+use openssl::pkey_ctx::PkeyCtx;
+use openssl::rsa::Padding;
 use openssl::sign::{Signer, Verifier};
 
 use crate::jwk::{alg::rsa::RsaKeyPair, Jwk};
-use crate::jws::{JwsAlgorithm, JwsSigner, JwsVerifier};
+use crate::jws::alg::rsassa_pss::{RsassaPssJwsAlgorithm, RsassaPssJwsVerifier};
+use crate::jws::{JwsAlgorithm, JwsHeader, JwsSigner, JwsVerifier};
 use crate::util::der::{DerBuilder, DerType};
 use crate::util::{self, HashAlgorithm};
 use crate::{JoseError, Value};
@@ -31,8 +35,8 @@ impl RsassaJwsAlgorithm {
     /// * `bits` - RSA key length
     pub fn generate_key_pair(&self, bits: u32) -> Result<RsaKeyPair, JoseError> {
         (|| -> anyhow::Result<RsaKeyPair> {
-            if bits < 2048 {
-                bail!("key length must be 2048 or more.");
+            if bits < util::rsa::MIN_BITS {
+                bail!("key length must be {} or more.", util::rsa::MIN_BITS);
             }
 
             let mut key_pair = RsaKeyPair::generate(bits)?;
@@ -53,9 +57,7 @@ impl RsassaJwsAlgorithm {
         (|| -> anyhow::Result<RsaKeyPair> {
             let mut key_pair = RsaKeyPair::from_der(input)?;
 
-            if key_pair.key_len() * 8 < 2048 {
-                bail!("key length must be 2048 or more.");
-            }
+            util::rsa::check_min_bits(key_pair.private_key(), util::rsa::MIN_BITS)?;
 
             key_pair.set_algorithm(Some(self.name()));
             Ok(key_pair)
@@ -80,9 +82,7 @@ impl RsassaJwsAlgorithm {
         (|| -> anyhow::Result<RsaKeyPair> {
             let mut key_pair = RsaKeyPair::from_pem(input.as_ref())?;
 
-            if key_pair.key_len() * 8 < 2048 {
-                bail!("key length must be 2048 or more.");
-            }
+            util::rsa::check_min_bits(key_pair.private_key(), util::rsa::MIN_BITS)?;
 
             key_pair.set_algorithm(Some(self.name()));
             Ok(key_pair)
@@ -146,9 +146,7 @@ impl RsassaJwsAlgorithm {
             }
 
             let key_pair = RsaKeyPair::from_jwk(jwk)?;
-            if key_pair.key_len() * 8 < 2048 {
-                bail!("key length must be 2048 or more.");
-            }
+            util::rsa::check_min_bits(key_pair.private_key(), util::rsa::MIN_BITS)?;
 
             let private_key = key_pair.into_private_key();
             let key_id = jwk.key_id().map(|val| val.to_string());
@@ -182,10 +180,7 @@ impl RsassaJwsAlgorithm {
 
             let public_key = PKey::public_key_from_der(spki_der)?;
 
-            let rsa = public_key.rsa()?;
-            if rsa.size() * 8 < 2048 {
-                bail!("key length must be 2048 or more.");
-            }
+            util::rsa::check_min_bits(&public_key, util::rsa::MIN_BITS)?;
 
             Ok(RsassaJwsVerifier {
                 algorithm: self.clone(),
@@ -228,10 +223,7 @@ impl RsassaJwsAlgorithm {
 
             let public_key = PKey::public_key_from_der(spki_der)?;
 
-            let rsa = public_key.rsa()?;
-            if rsa.size() * 8 < 2048 {
-                bail!("key length must be 2048 or more.");
-            }
+            util::rsa::check_min_bits(&public_key, util::rsa::MIN_BITS)?;
 
             Ok(RsassaJwsVerifier {
                 algorithm: self.clone(),
@@ -289,10 +281,7 @@ impl RsassaJwsAlgorithm {
             let public_key = PKey::public_key_from_der(&pkcs8)?;
             let key_id = jwk.key_id().map(|val| val.to_string());
 
-            let rsa = public_key.rsa()?;
-            if rsa.size() * 8 < 2048 {
-                bail!("key length must be 2048 or more.");
-            }
+            util::rsa::check_min_bits(&public_key, util::rsa::MIN_BITS)?;
 
             Ok(RsassaJwsVerifier {
                 algorithm: self.clone(),
@@ -375,12 +364,7 @@ impl JwsSigner for RsassaJwsSigner {
 
     fn sign(&self, message: &[u8]) -> Result<Vec<u8>, JoseError> {
         (|| -> anyhow::Result<Vec<u8>> {
-            let md = match &self.algorithm.hash_algorithm() {
-                HashAlgorithm::Sha1 => MessageDigest::sha1(),
-                HashAlgorithm::Sha256 => MessageDigest::sha256(),
-                HashAlgorithm::Sha384 => MessageDigest::sha384(),
-                HashAlgorithm::Sha512 => MessageDigest::sha512(),
-            };
+            let md = self.algorithm.hash_algorithm().message_digest()?;
             let mut signer = Signer::new(md, &self.private_key)?;
             signer.update(message)?;
             let signature = signer.sign_to_vec()?;
@@ -417,6 +401,94 @@ impl RsassaJwsVerifier {
     pub fn remove_key_id(&mut self) {
         self.key_id = None;
     }
+
+    /// Return a verifier that accepts the specified algorithms by the same public key.
+    ///
+    /// The accepted algorithms are RS256, RS384, RS512, PS256, PS384 and PS512.
+    ///
+    /// # Arguments
+    /// * `algorithms` - The algorithms to accept.
+    pub fn accept_algorithms(
+        &self,
+        algorithms: &[&dyn JwsAlgorithm],
+    ) -> Result<RsassaMultiJwsVerifier, JoseError> {
+        (|| -> anyhow::Result<RsassaMultiJwsVerifier> {
+            let mut verifiers: Vec<Box<dyn JwsVerifier>> = Vec::with_capacity(algorithms.len());
+            for algorithm in algorithms {
+                let verifier: Box<dyn JwsVerifier> = match algorithm.name() {
+                    "RS256" => Box::new(self.with_algorithm(RsassaJwsAlgorithm::Rs256)),
+                    "RS384" => Box::new(self.with_algorithm(RsassaJwsAlgorithm::Rs384)),
+                    "RS512" => Box::new(self.with_algorithm(RsassaJwsAlgorithm::Rs512)),
+                    "PS256" => Box::new(self.to_pss_verifier(RsassaPssJwsAlgorithm::Ps256)?),
+                    "PS384" => Box::new(self.to_pss_verifier(RsassaPssJwsAlgorithm::Ps384)?),
+                    "PS512" => Box::new(self.to_pss_verifier(RsassaPssJwsAlgorithm::Ps512)?),
+                    name => bail!("The algorithm is not acceptable by a RSA key: {}", name),
+                };
+                verifiers.push(verifier);
+            }
+
+            Ok(RsassaMultiJwsVerifier { verifiers })
+        })()
+        .map_err(|err| match err.downcast::<JoseError>() {
+            Ok(err) => err,
+            Err(err) => JoseError::InvalidKeyFormat(err),
+        })
+    }
+
+    fn with_algorithm(&self, algorithm: RsassaJwsAlgorithm) -> RsassaJwsVerifier {
+        RsassaJwsVerifier {
+            algorithm,
+            public_key: self.public_key.clone(),
+            key_id: self.key_id.clone(),
+        }
+    }
+
+    fn to_pss_verifier(
+        &self,
+        algorithm: RsassaPssJwsAlgorithm,
+    ) -> Result<RsassaPssJwsVerifier, JoseError> {
+        let pkcs1 = self
+            .public_key
+            .rsa()
+            .and_then(|rsa| rsa.public_key_to_der_pkcs1())
+            .map_err(|err| JoseError::InvalidKeyFormat(err.into()))?;
+        let mut verifier = algorithm.verifier_from_der(&pkcs1)?;
+        if let Some(key_id) = &self.key_id {
+            verifier.set_key_id(key_id.as_str());
+        }
+        Ok(verifier)
+    }
+}
+
+/// A verifier that accepts several RSA algorithms by the same public key.
+#[derive(Debug, Clone)]
+pub struct RsassaMultiJwsVerifier {
+    verifiers: Vec<Box<dyn JwsVerifier>>,
+}
+
+impl RsassaMultiJwsVerifier {
+    /// Return the names of the accepted algorithms.
+    pub fn algorithms(&self) -> Vec<&str> {
+        self.verifiers
+            .iter()
+            .map(|verifier| verifier.algorithm().name())
+            .collect()
+    }
+
+    /// Return a verifier for the alg header claim when it is accepted.
+    ///
+    /// This is intended to be used as a selector of
+    /// [`JwsContext::deserialize_compact_with_selector`](crate::jws::JwsContext::deserialize_compact_with_selector).
+    ///
+    /// # Arguments
+    /// * `header` - The JWS header.
+    pub fn select(&self, header: &JwsHeader) -> Option<&dyn JwsVerifier> {
+        let alg = header.algorithm()?;
+        self.verifiers
+            .iter()
+            .find(|verifier| verifier.algorithm().name() == alg)
+            .map(|verifier| verifier.as_ref())
+    }
 }
 
 impl JwsVerifier for RsassaJwsVerifier {
@@ -433,12 +505,7 @@ impl JwsVerifier for RsassaJwsVerifier {
 
     fn verify(&self, message: &[u8], signature: &[u8]) -> Result<(), JoseError> {
         (|| -> anyhow::Result<()> {
-            let md = match &self.algorithm.hash_algorithm() {
-                HashAlgorithm::Sha1 => MessageDigest::sha1(),
-                HashAlgorithm::Sha256 => MessageDigest::sha256(),
-                HashAlgorithm::Sha384 => MessageDigest::sha384(),
-                HashAlgorithm::Sha512 => MessageDigest::sha512(),
-            };
+            let md = self.algorithm.hash_algorithm().message_digest()?;
             let mut verifier = Verifier::new(md, &self.public_key)?;
             verifier.update(message)?;
             if !verifier.verify(signature)? {
@@ -449,6 +516,46 @@ impl JwsVerifier for RsassaJwsVerifier {
         .map_err(|err| JoseError::InvalidSignature(err))
     }
 
+    fn verify_batch(&self, items: &[(&[u8], &[u8])]) -> Vec<Result<(), JoseError>> {
+        // The key context is initialized once and each item only hashes its message.
+        let context = (|| -> anyhow::Result<(MessageDigest, PkeyCtx<Public>)> {
+            let md = self.algorithm.hash_algorithm().message_digest()?;
+            let mut ctx = PkeyCtx::new(&self.public_key)?;
+            ctx.verify_init()?;
+            ctx.set_rsa_padding(Padding::PKCS1)?;
+            ctx.set_signature_md(match self.algorithm {
+                RsassaJwsAlgorithm::Rs256 => Md::sha256(),
+                RsassaJwsAlgorithm::Rs384 => Md::sha384(),
+                RsassaJwsAlgorithm::Rs512 => Md::sha512(),
+            })?;
+            Ok((md, ctx))
+        })();
+
+        let (md, mut ctx) = match context {
+            Ok(val) => val,
+            Err(err) => {
+                return items
+                    .iter()
+                    .map(|_| Err(JoseError::InvalidSignature(anyhow!("{}", err))))
+                    .collect()
+            }
+        };
+
+        items
+            .iter()
+            .map(|(message, signature)| {
+                (|| -> anyhow::Result<()> {
+                    let digest = hash(md, message)?;
+                    if !ctx.verify(&digest, signature)? {
+                        bail!("The signature does not match.")
+                    }
+                    Ok(())
+                })()
+                .map_err(JoseError::InvalidSignature)
+            })
+            .collect()
+    }
+
     fn box_clone(&self) -> Box<dyn JwsVerifier> {
         Box::new(self.clone())
     }
@@ -467,6 +574,8 @@ mod tests {
     use super::*;
 
     use anyhow::Result;
+
+    use crate::jws::{self, ES256, PS256, RS256};
     use std::fs;
     use std::path::PathBuf;
 
@@ -709,6 +818,80 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn verify_rsassa_with_accepted_algorithms() -> Result<()> {
+        let private_key = load_file("jwk/RSA_private.jwk")?;
+        let private_key = Jwk::from_bytes(&private_key)?;
+        let public_key = load_file("jwk/RSA_public.jwk")?;
+        let public_key = Jwk::from_bytes(&public_key)?;
+
+        let verifier = RsassaJwsAlgorithm::Rs256
+            .verifier_from_jwk(&public_key)?
+            .accept_algorithms(&[&RS256, &PS256])?;
+        assert_eq!(verifier.algorithms(), vec!["RS256", "PS256"]);
+
+        let mut header = JwsHeader::new();
+        let rs256 = jws::serialize_compact(
+            b"abcde12345",
+            &header,
+            &RS256.signer_from_jwk(&private_key)?,
+        )?;
+        let ps256 = jws::serialize_compact(
+            b"abcde12345",
+            &header,
+            &PS256.signer_from_jwk(&private_key)?,
+        )?;
+        for input in [&rs256, &ps256] {
+            let (payload, _) =
+                jws::deserialize_compact_with_selector(
+                    input,
+                    |header| Ok(verifier.select(header)),
+                )?;
+            assert_eq!(payload, b"abcde12345");
+        }
+
+        header.set_algorithm("RS384");
+        assert!(verifier.select(&header).is_none());
+        assert!(RS256
+            .verifier_from_jwk(&public_key)?
+            .accept_algorithms(&[&ES256])
+            .is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn verify_batch_rsassa() -> Result<()> {
+        let private_key = load_file("pem/RSA_2048bit_private.pem")?;
+        let public_key = load_file("pem/RSA_2048bit_public.pem")?;
+
+        for alg in &[
+            RsassaJwsAlgorithm::Rs256,
+            RsassaJwsAlgorithm::Rs384,
+            RsassaJwsAlgorithm::Rs512,
+        ] {
+            let signer = alg.signer_from_pem(&private_key)?;
+            let signature_1 = signer.sign(b"message-1")?;
+            let signature_2 = signer.sign(b"message-2")?;
+
+            let verifier = alg.verifier_from_pem(&public_key)?;
+            let results = verifier.verify_batch(&[
+                (b"message-1", &signature_1),
+                (b"message-2", &signature_1),
+                (b"message-2", &signature_2),
+                (b"message-3", &signature_2),
+            ]);
+
+            assert_eq!(results.len(), 4);
+            assert!(results[0].is_ok());
+            assert!(results[1].is_err());
+            assert!(results[2].is_ok());
+            assert!(results[3].is_err());
+        }
+
+        Ok(())
+    }
+
     fn load_file(path: &str) -> Result<Vec<u8>> {
         let mut pb = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
         pb.push("data");
@@ -2,7 +2,6 @@ use std::fmt::Display;
 use std::ops::Deref;
 
 use anyhow::bail;
-use openssl::hash::MessageDigest;
 use openssl::pkey::{PKey, Private, Public};
 use openssl::rsa::Rsa;
 use openssl::sign::{Signer, Verifier};
@@ -30,8 +29,8 @@ impl RsassaPssJwsAlgorithm {
     /// * `bits` - RSA key length
     pub fn generate_key_pair(&self, bits: u32) -> Result<RsaPssKeyPair, JoseError> {
         (|| -> anyhow::Result<RsaPssKeyPair> {
-            if bits < 2048 {
-                bail!("key length must be 2048 or more.");
+            if bits < util::rsa::MIN_BITS {
+                bail!("key length must be {} or more.", util::rsa::MIN_BITS);
             }
 
             let mut key_pair = RsaPssKeyPair::generate(
@@ -62,9 +61,7 @@ impl RsassaPssJwsAlgorithm {
                 Some(self.salt_len()),
             )?;
 
-            if key_pair.key_len() * 8 < 2048 {
-                bail!("key length must be 2048 or more.");
-            }
+            util::rsa::check_min_bits(key_pair.private_key(), util::rsa::MIN_BITS)?;
 
             key_pair.set_algorithm(Some(self.name()));
             Ok(key_pair)
@@ -94,9 +91,7 @@ impl RsassaPssJwsAlgorithm {
                 Some(self.salt_len()),
             )?;
 
-            if key_pair.key_len() * 8 < 2048 {
-                bail!("key length must be 2048 or more.");
-            }
+            util::rsa::check_min_bits(key_pair.private_key(), util::rsa::MIN_BITS)?;
 
             key_pair.set_algorithm(Some(self.name()));
             Ok(key_pair)
@@ -171,9 +166,7 @@ impl RsassaPssJwsAlgorithm {
                 self.hash_algorithm(),
                 self.salt_len(),
             )?;
-            if key_pair.key_len() * 8 < 2048 {
-                bail!("key length must be 2048 or more.");
-            }
+            util::rsa::check_min_bits(key_pair.private_key(), util::rsa::MIN_BITS)?;
 
             let private_key = key_pair.into_private_key();
             let key_id = jwk.key_id().map(|val| val.to_string());
@@ -237,10 +230,7 @@ impl RsassaPssJwsAlgorithm {
 
             let public_key = PKey::public_key_from_der(spki_der)?;
 
-            let rsa = public_key.rsa()?;
-            if rsa.size() * 8 < 2048 {
-                bail!("key length must be 2048 or more.");
-            }
+            util::rsa::check_min_bits(&public_key, util::rsa::MIN_BITS)?;
 
             Ok(RsassaPssJwsVerifier {
                 algorithm: self.clone(),
@@ -298,10 +288,7 @@ impl RsassaPssJwsAlgorithm {
                 alg => bail!("Inappropriate algorithm: {}", alg),
             };
 
-            let rsa = public_key.rsa()?;
-            if rsa.size() * 8 < 2048 {
-                bail!("key length must be 2048 or more.");
-            }
+            util::rsa::check_min_bits(&public_key, util::rsa::MIN_BITS)?;
 
             Ok(RsassaPssJwsVerifier {
                 algorithm: self.clone(),
@@ -364,10 +351,7 @@ impl RsassaPssJwsAlgorithm {
             let public_key = PKey::public_key_from_der(&pkcs8)?;
             let key_id = jwk.key_id().map(|val| val.to_string());
 
-            let rsa = public_key.rsa()?;
-            if rsa.size() * 8 < 2048 {
-                bail!("key length must be 2048 or more.");
-            }
+            util::rsa::check_min_bits(&public_key, util::rsa::MIN_BITS)?;
 
             Ok(RsassaPssJwsVerifier {
                 algorithm: self.clone(),
@@ -458,12 +442,7 @@ impl JwsSigner for RsassaPssJwsSigner {
 
     fn sign(&self, message: &[u8]) -> Result<Vec<u8>, JoseError> {
         (|| -> anyhow::Result<Vec<u8>> {
-            let md = match &self.algorithm.hash_algorithm() {
-                HashAlgorithm::Sha1 => MessageDigest::sha1(),
-                HashAlgorithm::Sha256 => MessageDigest::sha256(),
-                HashAlgorithm::Sha384 => MessageDigest::sha384(),
-                HashAlgorithm::Sha512 => MessageDigest::sha512(),
-            };
+            let md = self.algorithm.hash_algorithm().message_digest()?;
             let mut signer = Signer::new(md, &self.private_key)?;
             signer.update(message)?;
             let signature = signer.sign_to_vec()?;
@@ -516,12 +495,7 @@ impl JwsVerifier for RsassaPssJwsVerifier {
 
     fn verify(&self, message: &[u8], signature: &[u8]) -> Result<(), JoseError> {
         (|| -> anyhow::Result<()> {
-            let md = match &self.algorithm.hash_algorithm() {
-                HashAlgorithm::Sha1 => MessageDigest::sha1(),
-                HashAlgorithm::Sha256 => MessageDigest::sha256(),
-                HashAlgorithm::Sha384 => MessageDigest::sha384(),
-                HashAlgorithm::Sha512 => MessageDigest::sha512(),
-            };
+            let md = self.algorithm.hash_algorithm().message_digest()?;
             let mut verifier = Verifier::new(md, &self.public_key)?;
             verifier.update(message)?;
             if !verifier.verify(signature)? {
@@ -66,6 +66,22 @@ pub trait JwsVerifier: Debug + Send + Sync {
     /// * `signature` - a signature data.
     fn verify(&self, message: &[u8], signature: &[u8]) -> Result<(), JoseError>;
 
+    /// Verify many pairs of data and signature by the same key.
+    ///
+    /// The results are returned in the same order as the input. The RSASSA-PKCS1-v1_5
+    /// and ECDSA verifiers set up the key context once for the whole batch; the others
+    /// verify each pair in turn.
+    ///
+    /// # Arguments
+    ///
+    /// * `items` - pairs of a signing input and a signature data.
+    fn verify_batch(&self, items: &[(&[u8], &[u8])]) -> Vec<Result<(), JoseError>> {
+        items
+            .iter()
+            .map(|(message, signature)| self.verify(message, signature))
+            .collect()
+    }
+
     fn box_clone(&self) -> Box<dyn JwsVerifier>;
 }
 
@@ -1,10 +1,11 @@
 use std::collections::BTreeSet;
 use std::fmt::Debug;
 
-use anyhow::bail;
+use anyhow::{anyhow, bail};
 
-use crate::jws::{JwsHeader, JwsHeaderSet, JwsSigner, JwsVerifier};
-use crate::util;
+use crate::jwk::JwkSet;
+use crate::jws::{self, JwsHeader, JwsHeaderSet, JwsSigner, JwsVerifier, VerifyOutcome};
+use crate::util::{self, CompactError};
 use crate::{JoseError, Map, Value};
 
 #[derive(Debug, Eq, PartialEq, Clone)]
@@ -79,14 +80,13 @@ impl JwsContext {
         F: Fn(&JwsHeader) -> Option<&'a dyn JwsSigner>,
     {
         (|| -> anyhow::Result<String> {
-            let mut b64 = true;
-            if let Some(vals) = header.critical() {
-                if vals.contains(&"b64") {
-                    if let Some(val) = header.base64url_encode_payload() {
-                        b64 = val;
-                    }
-                }
-            }
+            let b64 = match header.base64url_encode_payload() {
+                Some(false) => match header.critical() {
+                    Some(vals) if vals.contains(&"b64") => false,
+                    _ => bail!("The b64 header claim name must be in critical."),
+                },
+                _ => true,
+            };
 
             let signer = match selector(header) {
                 Some(val) => val,
@@ -185,6 +185,10 @@ impl JwsContext {
             result.push_str("{\"signatures\":[");
 
             for (i, header) in headers.iter().enumerate() {
+                if header.claims_set(false).contains_key("alg") {
+                    bail!("The JWS alg header claim must be protected.");
+                }
+
                 let merged_map = header.to_map();
                 let merged = JwsHeader::from_map(merged_map)?;
                 let signer = match selector(i, &merged) {
@@ -284,22 +288,21 @@ impl JwsContext {
     {
         (|| -> anyhow::Result<String> {
             let protected_map = header.claims_set(true);
-            let mut b64 = true;
-            match protected_map.get("crit") {
-                Some(Value::Array(vals)) => {
-                    if vals.iter().any(|val| match val {
-                        Value::String(val2) => val2 == "b64",
-                        _ => false,
-                    }) {
-                        b64 = match protected_map.get("b64") {
-                            Some(Value::Bool(val3)) => *val3,
-                            _ => false,
-                        };
-                    }
-                }
-                _ => {}
+            if header.claims_set(false).contains_key("b64") {
+                bail!("The b64 header claim must be in protected.");
+            }
+            if header.claims_set(false).contains_key("alg") {
+                bail!("The JWS alg header claim must be protected.");
             }
 
+            let b64 = match protected_map.get("b64") {
+                Some(Value::Bool(false)) => match protected_map.get("crit") {
+                    Some(Value::Array(vals)) if vals.iter().any(|val| val == "b64") => false,
+                    _ => bail!("The b64 header claim name must be in critical."),
+                },
+                _ => true,
+            };
+
             let merged_map = header.to_map();
             let merged = JwsHeader::from_map(merged_map)?;
             let signer = match selector(&merged) {
@@ -338,6 +341,7 @@ impl JwsContext {
             };
 
             let message = format!("{}.{}", &protected_b64, payload);
+            let payload = Value::String(payload.to_string()).to_string();
             let signature = signer.sign(message.as_bytes())?;
 
             let mut json = String::new();
@@ -352,9 +356,8 @@ impl JwsContext {
                 json.push_str(&unprotcted_json);
             }
 
-            json.push_str(",\"payload\":\"");
+            json.push_str(",\"payload\":");
             json.push_str(&payload);
-            json.push_str("\"");
 
             json.push_str(",\"signature\":\"");
             util::encode_base64_urlsafe_nopad_buf(&signature, &mut json);
@@ -416,8 +419,8 @@ impl JwsContext {
             let payload = &input[(indexies[0] + 1)..(indexies[1])];
             let signature = &input[(indexies[1] + 1)..];
 
-            let header = util::decode_base64_urlsafe_no_pad(header)?;
-            let header: Map<String, Value> = serde_json::from_slice(&header)?;
+            let header = CompactError::decode_segment(header, 1, "header")?;
+            let header: Map<String, Value> = CompactError::parse_segment(&header, 1, "header")?;
             let header = JwsHeader::from_map(header)?;
 
             let verifier = match selector(&header)? {
@@ -425,59 +428,274 @@ impl JwsContext {
                 None => bail!("A verifier is not found."),
             };
 
-            match header.claim("alg") {
-                Some(Value::String(val)) => {
-                    let expected_alg = verifier.algorithm().name();
-                    if val != expected_alg {
-                        bail!("The JWS alg header claim is not {}: {}", expected_alg, val);
-                    }
+            let b64 = self.check_compact_header(&header, verifier)?;
+
+            let message = &input[..(indexies[1])];
+            let signature = CompactError::decode_segment(signature, 3, "signature")?;
+            verifier.verify(message, &signature)?;
+
+            let payload = if b64 {
+                CompactError::decode_segment(payload, 2, "payload")?
+            } else {
+                payload.to_vec()
+            };
+
+            Ok((payload, header))
+        })()
+        .map_err(|err| match err.downcast::<JoseError>() {
+            Ok(err) => err,
+            Err(err) => JoseError::InvalidJwsFormat(err),
+        })
+    }
+
+    /// Verify the input that is formatted by compact serialization with a detached payload.
+    ///
+    /// The payload part of the input must be empty. The payload is used as is for the signing
+    /// input when the b64 header claim is false, and is base64url encoded otherwise.
+    ///
+    /// # Arguments
+    ///
+    /// * `input` - The input data.
+    /// * `payload` - The detached payload.
+    /// * `max_payload_len` - The maximum length of the detached payload in bytes.
+    /// * `verifier` - The JWS verifier.
+    pub fn deserialize_compact_detached(
+        &self,
+        input: impl AsRef<[u8]>,
+        payload: &[u8],
+        max_payload_len: usize,
+        verifier: &dyn JwsVerifier,
+    ) -> Result<JwsHeader, JoseError> {
+        (|| -> anyhow::Result<JwsHeader> {
+            if payload.len() > max_payload_len {
+                bail!(
+                    "The detached payload size must be {} or less: {}",
+                    max_payload_len,
+                    payload.len()
+                );
+            }
+
+            let input = input.as_ref();
+            let indexies: Vec<usize> = input
+                .iter()
+                .enumerate()
+                .filter(|(_, b)| **b == b'.')
+                .map(|(pos, _)| pos)
+                .collect();
+            if indexies.len() != 2 {
+                bail!(
+                    "The compact serialization form of JWS must be three parts separated by colon."
+                );
+            }
+            if indexies[1] != indexies[0] + 1 {
+                bail!("The payload part of a detached JWS must be empty.");
+            }
+
+            let header_b64 = &input[0..indexies[0]];
+            let signature = &input[(indexies[1] + 1)..];
+
+            let header = Self::decode_compact_header(input)?;
+
+            let b64 = self.check_compact_header(&header, verifier)?;
+
+            let mut message = Vec::with_capacity(header_b64.len() + 1 + payload.len() * 4 / 3 + 3);
+            message.extend_from_slice(header_b64);
+            message.push(b'.');
+            if b64 {
+                message.extend_from_slice(util::encode_base64_urlsafe_nopad(payload).as_bytes());
+            } else {
+                message.extend_from_slice(payload);
+            }
+
+            let signature = CompactError::decode_segment(signature, 3, "signature")?;
+            verifier.verify(&message, &signature)?;
+
+            Ok(header)
+        })()
+        .map_err(|err| match err.downcast::<JoseError>() {
+            Ok(err) => err,
+            Err(err) => JoseError::InvalidJwsFormat(err),
+        })
+    }
+
+    fn decode_compact_header(input: &[u8]) -> Result<JwsHeader, JoseError> {
+        (|| -> anyhow::Result<JwsHeader> {
+            let header = match input.iter().position(|b| *b == b'.') {
+                Some(pos) => &input[..pos],
+                None => bail!(
+                    "The compact serialization form of JWS must be three parts separated by colon."
+                ),
+            };
+            let header = CompactError::decode_segment(header, 1, "header")?;
+            let header: Map<String, Value> = CompactError::parse_segment(&header, 1, "header")?;
+            let header = JwsHeader::from_map(header)?;
+            Ok(header)
+        })()
+        .map_err(|err| match err.downcast::<JoseError>() {
+            Ok(err) => err,
+            Err(err) => JoseError::InvalidJwsFormat(err),
+        })
+    }
+
+    fn check_compact_header(
+        &self,
+        header: &JwsHeader,
+        verifier: &dyn JwsVerifier,
+    ) -> anyhow::Result<bool> {
+        match header.claim("alg") {
+            Some(Value::String(val)) => {
+                let expected_alg = verifier.algorithm().name();
+                if val != expected_alg {
+                    bail!("The JWS alg header claim is not {}: {}", expected_alg, val);
                 }
-                Some(_) => bail!("The JWS alg header claim must be a string."),
-                None => bail!("The JWS alg header claim is required."),
             }
+            Some(_) => bail!("The JWS alg header claim must be a string."),
+            None => bail!("The JWS alg header claim is required."),
+        }
 
-            match verifier.key_id() {
-                Some(expected) => match header.key_id() {
-                    Some(actual) if expected == actual => {}
-                    Some(actual) => bail!("The JWS kid header claim is mismatched: {}", actual),
-                    None => bail!("The JWS kid header claim is required."),
-                },
-                None => {}
+        if let Some(expected) = verifier.key_id() {
+            match header.key_id() {
+                Some(actual) if expected == actual => {}
+                Some(actual) => bail!("The JWS kid header claim is mismatched: {}", actual),
+                None => bail!("The JWS kid header claim is required."),
             }
+        }
 
-            let mut b64 = true;
-            if let Some(Value::Array(vals)) = header.claim("crit") {
-                for val in vals {
-                    if let Value::String(val2) = val {
-                        if !self.is_acceptable_critical(val2) {
-                            bail!("The critical name '{}' is not supported.", val2);
-                        }
+        let mut b64 = true;
+        if let Some(Value::Array(vals)) = header.claim("crit") {
+            for val in vals {
+                if let Value::String(val2) = val {
+                    if !self.is_acceptable_critical(val2) {
+                        bail!("The critical name '{}' is not supported.", val2);
+                    }
 
-                        if val2 == "b64" {
-                            if let Some(val) = header.base64url_encode_payload() {
-                                b64 = val;
-                            }
+                    if val2 == "b64" {
+                        if let Some(val) = header.base64url_encode_payload() {
+                            b64 = val;
                         }
                     }
                 }
             }
+        }
 
-            let message = &input[..(indexies[1])];
-            let signature = util::decode_base64_urlsafe_no_pad(signature)?;
-            verifier.verify(message, &signature)?;
+        Ok(b64)
+    }
 
-            let payload = if b64 {
-                util::decode_base64_urlsafe_no_pad(payload)?
+    /// Verify the input that is formatted by compact serialization by using a JWK set,
+    /// and return the outcome including the key ID and the algorithm that verified it.
+    ///
+    /// The candidates are the keys that have the kid header claim as the key ID,
+    /// or all keys in the JWK set when the kid header claim is absent.
+    ///
+    /// # Arguments
+    ///
+    /// * `input` - The input data.
+    /// * `jwk_set` - a JWK set.
+    pub fn verify_with_key_set(
+        &self,
+        input: impl AsRef<[u8]>,
+        jwk_set: &JwkSet,
+    ) -> Result<VerifyOutcome, JoseError> {
+        let input = input.as_ref();
+        let header = Self::decode_compact_header(input)?;
+
+        let alg = match header.algorithm() {
+            Some(val) => val,
+            None => {
+                return Err(JoseError::InvalidJwsFormat(anyhow!(
+                    "The JWS alg header claim is required."
+                )))
+            }
+        };
+        let candidates = match header.key_id() {
+            Some(val) => jwk_set.get(val),
+            None => jwk_set.keys(),
+        };
+
+        let mut last_error = None;
+        for jwk in candidates {
+            match jwk.algorithm() {
+                Some(val) if val != alg => continue,
+                _ => {}
+            }
+
+            let verifier = if header.key_id().is_some() {
+                jws::verifier_from_jwk(alg, jwk)
             } else {
-                payload.to_vec()
+                let mut jwk = jwk.clone();
+                jwk.set_parameter("kid", None)?;
+                jws::verifier_from_jwk(alg, &jwk)
+            };
+            let verifier = match verifier {
+                Ok(val) => val,
+                Err(err @ JoseError::UnsupportedSignatureAlgorithm(_)) => return Err(err),
+                Err(err) => {
+                    last_error = Some(err);
+                    continue;
+                }
             };
 
-            Ok((payload, header))
+            match self.deserialize_compact(input, verifier.as_ref()) {
+                Ok((payload, header)) => {
+                    let key_id = jwk.key_id().map(|val| val.to_string());
+                    return Ok(VerifyOutcome::new(payload, header, key_id, alg.to_string()));
+                }
+                Err(err) => last_error = Some(err),
+            }
+        }
+
+        Err(last_error.unwrap_or_else(|| {
+            JoseError::InvalidSignature(anyhow!("A key in the JWK set is not found."))
+        }))
+    }
+
+    /// Verify the input that is formatted by compact serialization by using the public key
+    /// in the jwk header claim.
+    ///
+    /// The signature only proves that the signer holds the private key of the embedded key.
+    /// The caller must establish trust in that key independently, for example by comparing
+    /// its thumbprint with a value bound to the token (like the jkt of a DPoP bound token).
+    ///
+    /// # Arguments
+    ///
+    /// * `input` - The input data.
+    /// * `allowed_algs` - acceptable values of the alg header claim.
+    pub fn verify_with_embedded_jwk(
+        &self,
+        input: impl AsRef<[u8]>,
+        allowed_algs: &[&str],
+    ) -> Result<(Vec<u8>, JwsHeader), JoseError> {
+        let input = input.as_ref();
+        let header = Self::decode_compact_header(input)?;
+
+        let verifier = (|| -> anyhow::Result<Box<dyn JwsVerifier>> {
+            let alg = match header.algorithm() {
+                Some(val) => val,
+                None => bail!("The JWS alg header claim is required."),
+            };
+            if !allowed_algs.contains(&alg) {
+                bail!("The JWS alg header claim is not allowed: {}", alg);
+            }
+
+            let jwk = match header.jwk() {
+                Some(val) => val,
+                None => bail!("The JWS jwk header claim is required."),
+            };
+            if jwk.parameter("d").is_some() || jwk.parameter("k").is_some() {
+                bail!("The JWS jwk header claim must not contain a private key.");
+            }
+
+            // The kid of the embedded key must not be required in the header.
+            let mut jwk = jwk.clone();
+            jwk.set_parameter("kid", None)?;
+            Ok(jws::verifier_from_embedded_jwk(alg, &jwk)?)
         })()
         .map_err(|err| match err.downcast::<JoseError>() {
             Ok(err) => err,
             Err(err) => JoseError::InvalidJwsFormat(err),
-        })
+        })?;
+
+        self.deserialize_compact(input, verifier.as_ref())
     }
 
     /// Deserialize the input that is formatted by json serialization.
@@ -573,7 +791,7 @@ impl JwsContext {
                 let protected_map: Map<String, Value> = serde_json::from_slice(&protected_vec)?;
 
                 let mut b64 = true;
-                if let Some(Value::Array(vals)) = protected_map.get("critical") {
+                if let Some(Value::Array(vals)) = protected_map.get("crit") {
                     for val in vals {
                         match val {
                             Value::String(name) => {
@@ -49,10 +49,9 @@ impl JwsHeader {
 
         (|| -> anyhow::Result<()> {
             if let Some(Value::Bool(false)) = map.get("b64") {
-                if let Some(Value::Array(vals)) = map.get("crit") {
-                    if !vals.iter().any(|e| e == "b64") {
-                        bail!("The b64 header claim name must be in critical.");
-                    }
+                match map.get("crit") {
+                    Some(Value::Array(vals)) if vals.iter().any(|e| e == "b64") => {}
+                    _ => bail!("The b64 header claim name must be in critical."),
                 }
             }
             Ok(())
@@ -272,16 +271,21 @@ impl JwsHeader {
 
     /// Set values for critical header claim (crit).
     ///
+    /// Fails if the values are empty, or contain an empty or duplicate name.
+    ///
     /// # Arguments
     ///
     /// * `values` - critical claim names
-    pub fn set_critical(&mut self, values: &Vec<impl AsRef<str>>) {
+    pub fn set_critical(&mut self, values: &Vec<impl AsRef<str>>) -> Result<(), JoseError> {
         let key = "crit";
         let vec = values
             .iter()
             .map(|v| Value::String(v.as_ref().to_string()))
             .collect();
-        self.claims.insert(key.to_string(), Value::Array(vec));
+        let value = Value::Array(vec);
+        Self::check_claim(key, &value)?;
+        self.claims.insert(key.to_string(), value);
+        Ok(())
     }
 
     /// Return values for critical header claim (crit).
@@ -397,14 +401,26 @@ impl JwsHeader {
                 },
                 "crit" => match &value {
                     Value::Array(vals) => {
-                        for val in vals {
+                        if vals.is_empty() {
+                            bail!("The JWS {} header claim must not be empty.", key);
+                        }
+
+                        for (i, val) in vals.iter().enumerate() {
                             match val {
+                                Value::String(val) if val.is_empty() => bail!(
+                                    "An element of the JWS {} header claim must not be empty.",
+                                    key
+                                ),
                                 Value::String(_) => {}
                                 _ => bail!(
                                     "An element of the JWS {} header claim must be a string.",
                                     key
                                 ),
                             }
+
+                            if vals[..i].contains(val) {
+                                bail!("The JWS {} header claim has a duplicate name: {}", key, val);
+                            }
                         }
                     }
                     _ => bail!("The JWS {} header claim must be a array.", key),
@@ -530,7 +546,7 @@ mod tests {
         header.set_key_id("kid");
         header.set_token_type("typ");
         header.set_content_type("cty");
-        header.set_critical(&vec!["crit0", "crit1"]);
+        header.set_critical(&vec!["crit0", "crit1"])?;
         header.set_url("url");
         header.set_nonce(b"nonce");
         header.set_claim("header_claim", Some(json!("header_claim")))?;
@@ -583,4 +599,35 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn test_critical_jws_header() -> Result<()> {
+        let mut header = JwsHeader::new();
+        header.set_critical(&vec!["b64", "exp"])?;
+        assert_eq!(header.critical(), Some(vec!["b64", "exp"]));
+
+        let header = JwsHeader::from_map(header.claims_set().clone())?;
+        assert_eq!(header.critical(), Some(vec!["b64", "exp"]));
+        assert_eq!(header.claim("crit"), Some(&json!(["b64", "exp"])));
+
+        let mut map = Map::new();
+        map.insert("crit".to_string(), json!([]));
+        assert!(JwsHeader::from_map(map).is_err());
+
+        let mut map = Map::new();
+        map.insert("crit".to_string(), json!(["exp", "exp"]));
+        assert!(JwsHeader::from_map(map).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_critical_jws_header_with_invalid_names() {
+        let mut header = JwsHeader::new();
+        let empty: Vec<&str> = vec![];
+        assert!(header.set_critical(&empty).is_err());
+        assert!(header.set_critical(&vec!["exp", ""]).is_err());
+        assert!(header.set_critical(&vec!["exp", "exp"]).is_err());
+        assert_eq!(header.critical(), None);
+    }
 }
@@ -2,6 +2,8 @@ use std::any::Any;
 use std::fmt::{Debug, Display};
 use std::ops::Deref;
 
+use anyhow::anyhow;
+
 use crate::jwk::Jwk;
 use crate::jws::JwsHeader;
 use crate::util::{self, decode_base64_urlsafe_no_pad};
@@ -317,17 +319,22 @@ impl JwsHeaderSet {
 
     /// Set values for critical header claim (crit).
     ///
+    /// Fails if the values are empty, or contain an empty or duplicate name.
+    ///
     /// # Arguments
     ///
     /// * `values` - critical claim names
-    pub fn set_critical(&mut self, values: &Vec<impl AsRef<str>>) {
+    pub fn set_critical(&mut self, values: &Vec<impl AsRef<str>>) -> Result<(), JoseError> {
         let key = "crit";
         let vec = values
             .iter()
             .map(|v| Value::String(v.as_ref().to_string()))
             .collect();
+        let value = Value::Array(vec);
+        JwsHeader::check_claim(key, &value)?;
         self.unprotected.remove(key);
-        self.protected.insert(key.to_string(), Value::Array(vec));
+        self.protected.insert(key.to_string(), value);
+        Ok(())
     }
 
     /// Return values for critical header claim (crit).
@@ -430,6 +437,15 @@ impl JwsHeaderSet {
         match value {
             Some(val) => {
                 JwsHeader::check_claim(key, &val)?;
+                match key {
+                    "alg" | "crit" | "b64" if !protection => {
+                        return Err(JoseError::InvalidJwsFormat(anyhow!(
+                            "The JWS {} header claim must be protected.",
+                            key
+                        )));
+                    }
+                    _ => {}
+                }
                 if protection {
                     self.unprotected.remove(key);
                     self.protected.insert(key.to_string(), val);
@@ -546,7 +562,7 @@ mod tests {
         header.set_key_id("kid", true);
         header.set_token_type("typ", true);
         header.set_content_type("cty", true);
-        header.set_critical(&vec!["crit0", "crit1"]);
+        header.set_critical(&vec!["crit0", "crit1"])?;
         header.set_url("url", true);
         header.set_nonce(b"nonce", true);
         header.set_claim("header_claim", Some(json!("header_claim")), true)?;
@@ -0,0 +1,51 @@
+use crate::jws::JwsHeader;
+
+/// Represent a result of the JWS verification by using a JWK set.
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub struct VerifyOutcome {
+    payload: Vec<u8>,
+    header: JwsHeader,
+    key_id: Option<String>,
+    algorithm: String,
+}
+
+impl VerifyOutcome {
+    pub(crate) fn new(
+        payload: Vec<u8>,
+        header: JwsHeader,
+        key_id: Option<String>,
+        algorithm: String,
+    ) -> Self {
+        Self {
+            payload,
+            header,
+            key_id,
+            algorithm,
+        }
+    }
+
+    /// Return the verified payload.
+    pub fn payload(&self) -> &[u8] {
+        &self.payload
+    }
+
+    /// Return the decoded JWS header claims.
+    pub fn header(&self) -> &JwsHeader {
+        &self.header
+    }
+
+    /// Return the key ID (kid) of the JWK that verified the signature.
+    pub fn key_id(&self) -> Option<&str> {
+        self.key_id.as_deref()
+    }
+
+    /// Return the name of the algorithm that verified the signature.
+    pub fn algorithm(&self) -> &str {
+        &self.algorithm
+    }
+
+    /// Return the verified payload and the decoded JWS header claims.
+    pub fn into_parts(self) -> (Vec<u8>, JwsHeader) {
+        (self.payload, self.header)
+    }
+}
@@ -1,10 +1,14 @@
 //! JSON Web Token (JWT) support.
 
 pub mod alg;
+mod clock;
+mod confirmation;
 mod jwt_context;
 mod jwt_payload;
 mod jwt_payload_validator;
 
+pub use crate::jwt::clock::{Clock, SystemClock};
+pub use crate::jwt::confirmation::Confirmation;
 pub use crate::jwt::jwt_context::JwtContext;
 pub use crate::jwt::jwt_payload::JwtPayload;
 pub use crate::jwt::jwt_payload_validator::JwtPayloadValidator;
@@ -12,11 +16,12 @@ pub use crate::jwt::jwt_payload_validator::JwtPayloadValidator;
 pub use crate::jwt::alg::unsecured::UnsecuredJwsAlgorithm::None;
 
 use std::sync::LazyLock;
+use std::time::Duration;
 
 use crate::jwe::{JweDecrypter, JweEncrypter, JweHeader};
 use crate::jwk::{Jwk, JwkSet};
 use crate::jws::{JwsHeader, JwsSigner, JwsVerifier};
-use crate::{JoseError, JoseHeader};
+use crate::{JoseError, JoseHeader, Value};
 
 static DEFAULT_CONTEXT: LazyLock<JwtContext> = LazyLock::new(|| JwtContext::new());
 
@@ -60,6 +65,25 @@ pub fn encode_with_encrypter(
     DEFAULT_CONTEXT.encode_with_encrypter(payload, header, encrypter)
 }
 
+/// Return the string repsentation of the nested JWT that is signed and then encrypted.
+///
+/// The cty header claim of the JWE is set to "JWT".
+///
+/// # Arguments
+///
+/// * `payload` - The payload data.
+/// * `signer` - a signer object.
+/// * `header` - The JWE heaser claims.
+/// * `encrypter` - a encrypter object.
+pub fn encode_signed_and_encrypted(
+    payload: &JwtPayload,
+    signer: &dyn JwsSigner,
+    header: &JweHeader,
+    encrypter: &dyn JweEncrypter,
+) -> Result<String, JoseError> {
+    DEFAULT_CONTEXT.encode_signed_and_encrypted(payload, signer, header, encrypter)
+}
+
 /// Return the Jose header decoded from JWT.
 ///
 /// # Arguments
@@ -69,6 +93,28 @@ pub fn decode_header(input: impl AsRef<[u8]>) -> Result<Box<dyn JoseHeader>, Jos
     DEFAULT_CONTEXT.decode_header(input)
 }
 
+/// Return the distinct key IDs referenced by the kid header claim of JWTs,
+/// in the order of first appearance.
+///
+/// It is intended for prefetching the keys from a remote JWK set,
+/// and the tokens are not verified. The tokens without kid header claim are skipped.
+///
+/// # Arguments
+///
+/// * `tokens` - JWT string representations of JWS or JWE.
+pub fn collect_key_ids(tokens: &[&str]) -> Result<Vec<String>, JoseError> {
+    let mut key_ids: Vec<String> = Vec::new();
+    for token in tokens {
+        let header = decode_header(token)?;
+        if let Some(Value::String(val)) = header.claim("kid") {
+            if !key_ids.contains(val) {
+                key_ids.push(val.clone());
+            }
+        }
+    }
+    Ok(key_ids)
+}
+
 /// Return the JWT object decoded with the "none" algorithm.
 ///
 /// # Arguments
@@ -138,6 +184,21 @@ pub fn decode_with_decrypter(
     DEFAULT_CONTEXT.decode_with_decrypter(input, decrypter)
 }
 
+/// Return the JWT object decoded from the nested JWT that is signed and then encrypted.
+///
+/// # Arguments
+///
+/// * `input` - a JWT string representation.
+/// * `decrypter` - a decrypter of the decrypting algorithm.
+/// * `verifier` - a verifier of the signing algorithm.
+pub fn decode_encrypted_and_verify(
+    input: impl AsRef<[u8]>,
+    decrypter: &dyn JweDecrypter,
+    verifier: &dyn JwsVerifier,
+) -> Result<(JwtPayload, JwsHeader), JoseError> {
+    DEFAULT_CONTEXT.decode_encrypted_and_verify(input, decrypter, verifier)
+}
+
 /// Return the JWT object decoded with a selected decrypting algorithm.
 ///
 /// # Arguments
@@ -172,6 +233,46 @@ where
     DEFAULT_CONTEXT.decode_with_decrypter_in_jwk_set(input, jwk_set, selector)
 }
 
+/// Validate a DPoP proof JWT defined in RFC 9449 and return the JWK SHA-256 thumbprint of the proof key.
+///
+/// The returned thumbprint can be compared with the jkt member of the confirmation claim (cnf)
+/// in the access token. Replay detection by the jti claim is not performed.
+///
+/// # Arguments
+///
+/// * `input` - a DPoP proof JWT string representation.
+/// * `htm` - a HTTP method of the request.
+/// * `htu` - a HTTP URI of the request.
+/// * `max_age` - a acceptable difference between the current time and the iat claim.
+pub fn validate_dpop_proof(
+    input: impl AsRef<[u8]>,
+    htm: &str,
+    htu: &str,
+    max_age: Duration,
+) -> Result<Vec<u8>, JoseError> {
+    DEFAULT_CONTEXT.validate_dpop_proof(input, htm, htu, max_age)
+}
+
+/// Validate a DPoP proof JWT defined in RFC 9449 by using the clock for the current time
+/// and return the JWK SHA-256 thumbprint of the proof key.
+///
+/// # Arguments
+///
+/// * `input` - a DPoP proof JWT string representation.
+/// * `htm` - a HTTP method of the request.
+/// * `htu` - a HTTP URI of the request.
+/// * `max_age` - a acceptable difference between the current time and the iat claim.
+/// * `clock` - a clock for the current time.
+pub fn validate_dpop_proof_with_clock(
+    input: impl AsRef<[u8]>,
+    htm: &str,
+    htu: &str,
+    max_age: Duration,
+    clock: &dyn Clock,
+) -> Result<Vec<u8>, JoseError> {
+    DEFAULT_CONTEXT.validate_dpop_proof_with_clock(input, htm, htu, max_age, clock)
+}
+
 #[cfg(test)]
 mod tests {
     use std::fs;
@@ -181,20 +282,24 @@ mod tests {
     use anyhow::Result;
     use serde_json::json;
 
+    use crate::jwe::{self, JweHeader};
     #[allow(deprecated)]
     use crate::jwe::{
         Dir, A128GCMKW, A128KW, A192GCMKW, A192KW, A256GCMKW, A256KW, ECDH_ES, ECDH_ES_A128KW,
         ECDH_ES_A192KW, ECDH_ES_A256KW, PBES2_HS256_A128KW, PBES2_HS384_A192KW, PBES2_HS512_A256KW,
         RSA1_5, RSA_OAEP, RSA_OAEP_256,
     };
-    use crate::jwk::Jwk;
+    use crate::jwk::{Jwk, KeyPair, ThumbprintHash};
+    use crate::jws;
+    #[cfg(feature = "secp256k1")]
+    use crate::jws::ES256K;
     use crate::jws::{
-        EdDSA, JwsHeader, ES256, ES256K, ES384, ES512, HS256, HS384, HS512, PS256, PS384, PS512,
-        RS256, RS384, RS512,
+        EdDSA, JwsHeader, ES256, ES384, ES512, HS256, HS384, HS512, PS256, PS384, PS512, RS256,
+        RS384, RS512,
     };
-    use crate::jwt::{self, JwtPayload};
+    use crate::jwt::{self, Clock, JwtPayload};
     use crate::Value;
-    use crate::{util, JoseHeader};
+    use crate::{util, JoseError, JoseHeader};
 
     #[test]
     fn test_decode_header() -> Result<()> {
@@ -230,6 +335,85 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_collect_key_ids() -> Result<()> {
+        let mut payload = JwtPayload::new();
+        payload.set_subject("subject");
+
+        let key = util::random_bytes(32);
+        let mut signer = HS256.signer_from_bytes(&key)?;
+        signer.set_key_id("key-1");
+        let jws1 = jwt::encode_with_signer(&payload, &JwsHeader::new(), &signer)?;
+        signer.set_key_id("key-2");
+        let jws2 = jwt::encode_with_signer(&payload, &JwsHeader::new(), &signer)?;
+
+        let mut header = JweHeader::new();
+        header.set_content_encryption("A128GCM");
+        header.set_key_id("key-1");
+        let encrypter = A256KW.encrypter_from_bytes(&key)?;
+        let jwe = jwt::encode_with_encrypter(&payload, &header, &encrypter)?;
+
+        let key_ids = jwt::collect_key_ids(&[&jws1, &jwe, &jws2])?;
+        assert_eq!(key_ids, vec!["key-1", "key-2"]);
+
+        assert!(jwt::collect_key_ids(&[&jws1, "invalid"]).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_jwt_with_invalid_utf8_payload() -> Result<()> {
+        let payload = b"{\"sub\":\"\xff\xfe\"}";
+        let key = util::random_bytes(32);
+
+        let signer = HS256.signer_from_bytes(&key)?;
+        let jws = jws::serialize_compact(payload, &JwsHeader::new(), &signer)?;
+        let verifier = HS256.verifier_from_bytes(&key)?;
+        match jwt::decode_with_verifier(&jws, &verifier) {
+            Err(JoseError::InvalidJwtFormat(err)) => assert!(err
+                .to_string()
+                .starts_with("The JWT payload must be a valid UTF-8 string")),
+            other => panic!("An invalid UTF-8 payload was accepted: {:?}", other),
+        }
+
+        let mut header = JweHeader::new();
+        header.set_content_encryption("A128GCM");
+        let encrypter = A256KW.encrypter_from_bytes(&key)?;
+        let jwe = jwe::serialize_compact(payload, &header, &encrypter)?;
+        let decrypter = A256KW.decrypter_from_bytes(&key)?;
+        match jwt::decode_with_decrypter(&jwe, &decrypter) {
+            Err(JoseError::InvalidJwtFormat(err)) => assert!(err
+                .to_string()
+                .starts_with("The JWT payload must be a valid UTF-8 string")),
+            other => panic!("An invalid UTF-8 payload was accepted: {:?}", other),
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_jwt_with_non_object_payload() -> Result<()> {
+        let key = util::random_bytes(32);
+        let signer = HS256.signer_from_bytes(&key)?;
+        let jws = jws::serialize_compact(br#"["sub","exp"]"#, &JwsHeader::new(), &signer)?;
+
+        let verifier = HS256.verifier_from_bytes(&key)?;
+        match jwt::decode_with_verifier(&jws, &verifier) {
+            Err(JoseError::InvalidJwtFormat(err)) => {
+                assert_eq!(err.to_string(), "The JWT payload must be a JSON object.")
+            }
+            other => panic!("A non-object payload was accepted: {:?}", other),
+        }
+
+        let jws = jws::serialize_compact(br#"{"exp":"tomorrow"}"#, &JwsHeader::new(), &signer)?;
+        assert!(matches!(
+            jwt::decode_with_verifier(&jws, &verifier),
+            Err(JoseError::InvalidJwtFormat(_))
+        ));
+
+        Ok(())
+    }
+
     #[test]
     fn test_jwt_unsecured() -> Result<()> {
         let mut src_header = JwsHeader::new();
@@ -367,17 +551,25 @@ mod tests {
 
     #[test]
     fn test_jwt_with_ecdsa_pem() -> Result<()> {
-        for alg in &[ES256, ES384, ES512, ES256K] {
+        for alg in &[
+            ES256,
+            ES384,
+            ES512,
+            #[cfg(feature = "secp256k1")]
+            ES256K,
+        ] {
             let private_key = load_file(match alg {
                 ES256 => "pem/EC_P-256_private.pem",
                 ES384 => "pem/EC_P-384_private.pem",
                 ES512 => "pem/EC_P-521_private.pem",
+                #[cfg(feature = "secp256k1")]
                 ES256K => "pem/EC_secp256k1_private.pem",
             })?;
             let public_key = load_file(match alg {
                 ES256 => "pem/EC_P-256_public.pem",
                 ES384 => "pem/EC_P-384_public.pem",
                 ES512 => "pem/EC_P-521_public.pem",
+                #[cfg(feature = "secp256k1")]
                 ES256K => "pem/EC_secp256k1_public.pem",
             })?;
 
@@ -400,17 +592,25 @@ mod tests {
 
     #[test]
     fn test_jwt_with_ecdsa_der() -> Result<()> {
-        for alg in &[ES256, ES384, ES512, ES256K] {
+        for alg in &[
+            ES256,
+            ES384,
+            ES512,
+            #[cfg(feature = "secp256k1")]
+            ES256K,
+        ] {
             let private_key = load_file(match alg {
                 ES256 => "der/EC_P-256_pkcs8_private.der",
                 ES384 => "der/EC_P-384_pkcs8_private.der",
                 ES512 => "der/EC_P-521_pkcs8_private.der",
+                #[cfg(feature = "secp256k1")]
                 ES256K => "der/EC_secp256k1_pkcs8_private.der",
             })?;
             let public_key = load_file(match alg {
                 ES256 => "der/EC_P-256_spki_public.der",
                 ES384 => "der/EC_P-384_spki_public.der",
                 ES512 => "der/EC_P-521_spki_public.der",
+                #[cfg(feature = "secp256k1")]
                 ES256K => "der/EC_secp256k1_spki_public.der",
             })?;
 
@@ -505,11 +705,18 @@ mod tests {
 
     #[test]
     fn test_external_jwt_verify_with_ecdsa() -> Result<()> {
-        for alg in &[ES256, ES384, ES512, ES256K] {
+        for alg in &[
+            ES256,
+            ES384,
+            ES512,
+            #[cfg(feature = "secp256k1")]
+            ES256K,
+        ] {
             let jwk = Jwk::from_bytes(&load_file(match alg {
                 ES256 => "jwk/EC_P-256_public.jwk",
                 ES384 => "jwk/EC_P-384_public.jwk",
                 ES512 => "jwk/EC_P-521_public.jwk",
+                #[cfg(feature = "secp256k1")]
                 ES256K => "jwk/EC_secp256k1_public.jwk",
             })?)?;
             let verifier = alg.verifier_from_jwk(&jwk)?;
@@ -818,6 +1025,173 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_jwt_signed_and_encrypted() -> Result<()> {
+        let private_key = load_file("pem/RSA_2048bit_private.pem")?;
+        let public_key = load_file("pem/RSA_2048bit_public.pem")?;
+
+        let signer = RS256.signer_from_pem(&private_key)?;
+        let verifier = RS256.verifier_from_pem(&public_key)?;
+        let encrypter = RSA_OAEP_256.encrypter_from_pem(&public_key)?;
+        let decrypter = RSA_OAEP_256.decrypter_from_pem(&private_key)?;
+
+        let mut src_payload = JwtPayload::new();
+        src_payload.set_issuer("joe");
+        src_payload.set_subject("user");
+
+        let mut header = JweHeader::new();
+        header.set_content_encryption("A256GCM");
+        let jwt = jwt::encode_signed_and_encrypted(&src_payload, &signer, &header, &encrypter)?;
+        assert_eq!(jwt.split('.').count(), 5);
+
+        let (_, jwe_header) = jwe::deserialize_compact(&jwt, &decrypter)?;
+        assert_eq!(jwe_header.content_type(), Some("JWT"));
+        assert_eq!(jwe_header.algorithm(), Some("RSA-OAEP-256"));
+
+        let (dst_payload, jws_header) =
+            jwt::decode_encrypted_and_verify(&jwt, &decrypter, &verifier)?;
+        assert_eq!(dst_payload, src_payload);
+        assert_eq!(jws_header.algorithm(), Some("RS256"));
+        assert_eq!(jws_header.token_type(), Some("JWT"));
+
+        let jwt = jwt::encode_with_encrypter(&src_payload, &header, &encrypter)?;
+        assert!(jwt::decode_encrypted_and_verify(&jwt, &decrypter, &verifier).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_validate_dpop_proof() -> Result<()> {
+        #[derive(Debug)]
+        struct FakeClock(SystemTime);
+
+        impl Clock for FakeClock {
+            fn now(&self) -> SystemTime {
+                self.0
+            }
+        }
+
+        let key_pair = ES256.generate_key_pair()?;
+        let signer = ES256.signer_from_jwk(&key_pair.to_jwk_key_pair())?;
+        let public_jwk = key_pair.to_jwk_public_key();
+
+        let mut header = JwsHeader::new();
+        header.set_token_type("dpop+jwt");
+        header.set_jwk(public_jwk.clone());
+
+        let iat = SystemTime::UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+        let encode = |header: &JwsHeader, htu: &str| -> Result<String> {
+            let mut payload = JwtPayload::new();
+            payload.set_jwt_id("e1j3V_bKic8-LAEB");
+            payload.set_claim("htm", Some(json!("POST")))?;
+            payload.set_claim("htu", Some(json!(htu)))?;
+            payload.set_issued_at(&iat);
+            Ok(jwt::encode_with_signer(&payload, header, &signer)?)
+        };
+        let max_age = Duration::from_secs(60);
+        let clock = FakeClock(iat + Duration::from_secs(10));
+
+        let proof = encode(&header, "https://server.example.com/token")?;
+        let thumbprint = jwt::validate_dpop_proof_with_clock(
+            &proof,
+            "POST",
+            "https://server.example.com/token?foo=bar",
+            max_age,
+            &clock,
+        )?;
+        assert_eq!(thumbprint, public_jwk.thumbprint(ThumbprintHash::Sha256)?);
+
+        let mut public_jwk_with_kid = public_jwk.clone();
+        public_jwk_with_kid.set_key_id("key-1");
+        let mut header_with_kid = header.clone();
+        header_with_kid.set_jwk(public_jwk_with_kid);
+        let proof_with_kid = encode(&header_with_kid, "https://server.example.com/token")?;
+        let thumbprint = jwt::validate_dpop_proof_with_clock(
+            &proof_with_kid,
+            "POST",
+            "https://server.example.com/token",
+            max_age,
+            &clock,
+        )?;
+        assert_eq!(thumbprint, public_jwk.thumbprint(ThumbprintHash::Sha256)?);
+
+        let stale_clock = FakeClock(iat + Duration::from_secs(3600));
+        match jwt::validate_dpop_proof_with_clock(
+            &proof,
+            "POST",
+            "https://server.example.com/token",
+            max_age,
+            &stale_clock,
+        ) {
+            Err(JoseError::InvalidClaim(err)) => {
+                assert!(err.to_string().contains("too old"), "{}", err)
+            }
+            other => panic!("A stale DPoP proof was accepted: {:?}", other),
+        }
+
+        let early_clock = FakeClock(iat - Duration::from_secs(3600));
+        match jwt::validate_dpop_proof_with_clock(
+            &proof,
+            "POST",
+            "https://server.example.com/token",
+            max_age,
+            &early_clock,
+        ) {
+            Err(JoseError::InvalidClaim(err)) => {
+                assert!(err.to_string().contains("too new"), "{}", err)
+            }
+            other => panic!("A future DPoP proof was accepted: {:?}", other),
+        }
+
+        let proof = encode(&header, "https://server.example.com/other")?;
+        match jwt::validate_dpop_proof_with_clock(
+            &proof,
+            "POST",
+            "https://server.example.com/token",
+            max_age,
+            &clock,
+        ) {
+            Err(JoseError::InvalidClaim(err)) => {
+                assert!(err.to_string().contains("htu"), "{}", err)
+            }
+            other => panic!("A DPoP proof for other URI was accepted: {:?}", other),
+        }
+
+        let proof = encode(&header, "https://server.example.com/token")?;
+        assert!(jwt::validate_dpop_proof_with_clock(
+            &proof,
+            "GET",
+            "https://server.example.com/token",
+            max_age,
+            &clock,
+        )
+        .is_err());
+
+        header.set_jwk(key_pair.to_jwk_key_pair());
+        let proof = encode(&header, "https://server.example.com/token")?;
+        assert!(jwt::validate_dpop_proof_with_clock(
+            &proof,
+            "POST",
+            "https://server.example.com/token",
+            max_age,
+            &clock,
+        )
+        .is_err());
+
+        let mut payload = JwtPayload::new();
+        payload.set_jwt_id("e1j3V_bKic8-LAEB");
+        payload.set_claim("htm", Some(json!("POST")))?;
+        payload.set_claim("htu", Some(json!("https://server.example.com/token")))?;
+        payload.set_issued_at(&SystemTime::now());
+        let mut header = JwsHeader::new();
+        header.set_token_type("dpop+jwt");
+        header.set_jwk(public_jwk.clone());
+        let proof = jwt::encode_with_signer(&payload, &header, &signer)?;
+        jwt::validate_dpop_proof(&proof, "POST", "https://server.example.com/token", max_age)?;
+
+        Ok(())
+    }
+
     fn load_file(path: &str) -> Result<Vec<u8>> {
         let mut pb = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
         pb.push("data");
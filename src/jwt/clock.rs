@@ -0,0 +1,18 @@
+use std::fmt::Debug;
+use std::time::SystemTime;
+
+/// Represent a source of the current time for JWT validation.
+pub trait Clock: Debug + Send + Sync {
+    /// Return the current time.
+    fn now(&self) -> SystemTime;
+}
+
+/// A clock using the system time.
+#[derive(Debug, Eq, PartialEq, Copy, Clone, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> SystemTime {
+        SystemTime::now()
+    }
+}
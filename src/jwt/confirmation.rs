@@ -0,0 +1,74 @@
+use anyhow::bail;
+
+use crate::jwk::{Jwk, ThumbprintHash};
+use crate::util;
+use crate::{JoseError, Map, Value};
+
+/// Represent a proof-of-possession key of the JWT confirmation payload claim (cnf) defined in RFC 7800.
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub enum Confirmation {
+    /// A public key embedded as the jwk member
+    Jwk(Jwk),
+    /// A JWK SHA-256 thumbprint of the jkt member (RFC 9449)
+    JwkThumbprint(Vec<u8>),
+}
+
+impl Confirmation {
+    /// Test whether the key is the proof-of-possession key of this confirmation.
+    ///
+    /// The keys are compared by the JWK SHA-256 thumbprints in constant time.
+    ///
+    /// # Arguments
+    ///
+    /// * `jwk` - a public key presented by the presenter
+    pub fn is_confirmed_by(&self, jwk: &Jwk) -> Result<bool, JoseError> {
+        let expected = match self {
+            Self::Jwk(val) => val.thumbprint(ThumbprintHash::Sha256)?,
+            Self::JwkThumbprint(val) => val.clone(),
+        };
+        let actual = jwk.thumbprint(ThumbprintHash::Sha256)?;
+
+        Ok(util::ct_eq_str(
+            &util::encode_base64_urlsafe_nopad(expected),
+            &util::encode_base64_urlsafe_nopad(actual),
+        ))
+    }
+
+    pub(crate) fn from_map(map: &Map<String, Value>) -> Result<Option<Self>, JoseError> {
+        (|| -> anyhow::Result<Option<Self>> {
+            match map.get("jwk") {
+                Some(Value::Object(val)) => {
+                    return Ok(Some(Self::Jwk(Jwk::from_map(val.clone())?)))
+                }
+                Some(_) => bail!("The jwk member of the cnf claim must be an object."),
+                None => {}
+            }
+
+            match map.get("jkt") {
+                Some(Value::String(val)) => {
+                    let val = util::decode_base64_urlsafe_no_pad(val)?;
+                    return Ok(Some(Self::JwkThumbprint(val)));
+                }
+                Some(_) => bail!("The jkt member of the cnf claim must be a string."),
+                None => {}
+            }
+
+            Ok(None)
+        })()
+        .map_err(JoseError::InvalidClaim)
+    }
+
+    pub(crate) fn to_map(&self) -> Map<String, Value> {
+        let mut map = Map::new();
+        match self {
+            Self::Jwk(val) => {
+                map.insert("jwk".to_string(), Value::Object(val.as_ref().clone()));
+            }
+            Self::JwkThumbprint(val) => {
+                let val = util::encode_base64_urlsafe_nopad(val);
+                map.insert("jkt".to_string(), Value::String(val));
+            }
+        }
+        map
+    }
+}
@@ -1,10 +1,12 @@
+use std::time::{Duration, SystemTime};
+
 use anyhow::bail;
 
 use crate::jwe::{JweContext, JweDecrypter, JweEncrypter, JweHeader};
-use crate::jwk::{Jwk, JwkSet};
-use crate::jws::{JwsContext, JwsHeader, JwsSigner, JwsVerifier};
-use crate::jwt::{self, JwtPayload};
-use crate::util;
+use crate::jwk::{Jwk, JwkSet, ThumbprintHash};
+use crate::jws::{self, JwsContext, JwsHeader, JwsSigner, JwsVerifier};
+use crate::jwt::{self, Clock, JwtPayload, SystemClock};
+use crate::util::{self, CompactError};
 use crate::{JoseError, JoseHeader, Map, Value};
 
 #[derive(Debug, Eq, PartialEq, Clone)]
@@ -116,6 +118,64 @@ impl JwtContext {
         Ok(jwt)
     }
 
+    /// Return the string repsentation of the nested JWT that is signed and then encrypted.
+    ///
+    /// The cty header claim of the JWE is set to "JWT".
+    ///
+    /// # Arguments
+    ///
+    /// * `payload` - The payload data.
+    /// * `signer` - a signer object.
+    /// * `header` - The JWE heaser claims.
+    /// * `encrypter` - a encrypter object.
+    pub fn encode_signed_and_encrypted(
+        &self,
+        payload: &JwtPayload,
+        signer: &dyn JwsSigner,
+        header: &JweHeader,
+        encrypter: &dyn JweEncrypter,
+    ) -> Result<String, JoseError> {
+        let mut jws_header = JwsHeader::new();
+        jws_header.set_token_type("JWT");
+        let jws = self.encode_with_signer(payload, &jws_header, signer)?;
+
+        let mut header = header.clone();
+        header.set_content_type("JWT");
+        let jwt = self
+            .jwe_context
+            .serialize_compact(jws.as_bytes(), &header, encrypter)?;
+        Ok(jwt)
+    }
+
+    /// Return the JWT object decoded from the nested JWT that is signed and then encrypted.
+    ///
+    /// # Arguments
+    ///
+    /// * `input` - a JWT string representation.
+    /// * `decrypter` - a decrypter of the decrypting algorithm.
+    /// * `verifier` - a verifier of the signing algorithm.
+    pub fn decode_encrypted_and_verify(
+        &self,
+        input: impl AsRef<[u8]>,
+        decrypter: &dyn JweDecrypter,
+        verifier: &dyn JwsVerifier,
+    ) -> Result<(JwtPayload, JwsHeader), JoseError> {
+        (|| -> anyhow::Result<(JwtPayload, JwsHeader)> {
+            let (jws, header) = self.jwe_context.deserialize_compact(input, decrypter)?;
+            match header.content_type() {
+                Some(val) if val.eq_ignore_ascii_case("JWT") => {}
+                Some(val) => bail!("The JWE cty header claim is not JWT: {}", val),
+                None => bail!("The JWE cty header claim is required."),
+            }
+
+            Ok(self.decode_with_verifier(jws, verifier)?)
+        })()
+        .map_err(|err| match err.downcast::<JoseError>() {
+            Ok(err) => err,
+            Err(err) => JoseError::InvalidJwtFormat(err),
+        })
+    }
+
     /// Return the Jose header decoded from JWT.
     ///
     /// # Arguments
@@ -211,8 +271,7 @@ impl JwtContext {
                         })
                     })?;
 
-            let payload: Map<String, Value> = serde_json::from_slice(&payload)?;
-            let payload = JwtPayload::from_map(payload)?;
+            let payload = Self::parse_payload(&payload)?;
 
             Ok((payload, header))
         })()
@@ -253,6 +312,134 @@ impl JwtContext {
         })
     }
 
+    /// Validate a DPoP proof JWT defined in RFC 9449 and return the JWK SHA-256 thumbprint of the proof key.
+    ///
+    /// The returned thumbprint can be compared with the jkt member of the confirmation claim (cnf)
+    /// in the access token. Replay detection by the jti claim is not performed.
+    ///
+    /// # Arguments
+    ///
+    /// * `input` - a DPoP proof JWT string representation.
+    /// * `htm` - a HTTP method of the request.
+    /// * `htu` - a HTTP URI of the request.
+    /// * `max_age` - a acceptable difference between the current time and the iat claim.
+    pub fn validate_dpop_proof(
+        &self,
+        input: impl AsRef<[u8]>,
+        htm: &str,
+        htu: &str,
+        max_age: Duration,
+    ) -> Result<Vec<u8>, JoseError> {
+        self.validate_dpop_proof_with_clock(input, htm, htu, max_age, &SystemClock)
+    }
+
+    /// Validate a DPoP proof JWT defined in RFC 9449 by using the clock for the current time
+    /// and return the JWK SHA-256 thumbprint of the proof key.
+    ///
+    /// # Arguments
+    ///
+    /// * `input` - a DPoP proof JWT string representation.
+    /// * `htm` - a HTTP method of the request.
+    /// * `htu` - a HTTP URI of the request.
+    /// * `max_age` - a acceptable difference between the current time and the iat claim.
+    /// * `clock` - a clock for the current time.
+    pub fn validate_dpop_proof_with_clock(
+        &self,
+        input: impl AsRef<[u8]>,
+        htm: &str,
+        htu: &str,
+        max_age: Duration,
+        clock: &dyn Clock,
+    ) -> Result<Vec<u8>, JoseError> {
+        (|| -> anyhow::Result<Vec<u8>> {
+            let input = input.as_ref();
+            let header_b64 = match input.iter().position(|b| *b == b'.') {
+                Some(pos) => &input[..pos],
+                None => bail!("The DPoP proof must be a compact serialization form of JWS."),
+            };
+            let header = CompactError::decode_segment(header_b64, 1, "header")?;
+            let header: Map<String, Value> = CompactError::parse_segment(&header, 1, "header")?;
+            let header = JwsHeader::from_map(header)?;
+
+            match header.token_type() {
+                Some("dpop+jwt") => {}
+                Some(val) => bail!("The DPoP proof typ header claim is not dpop+jwt: {}", val),
+                None => bail!("The DPoP proof typ header claim is required."),
+            }
+
+            let alg = match header.algorithm() {
+                Some(val) => val,
+                None => bail!("The DPoP proof alg header claim is required."),
+            };
+
+            let jwk = match header.jwk() {
+                Some(val) => val,
+                None => bail!("The DPoP proof jwk header claim is required."),
+            };
+            if jwk.parameter("d").is_some() || jwk.parameter("k").is_some() {
+                bail!("The DPoP proof jwk header claim must not contain a private key.");
+            }
+
+            // The kid of the embedded key must not be required in the header.
+            let mut verifier_jwk = jwk.clone();
+            verifier_jwk.set_parameter("kid", None)?;
+            let verifier = jws::verifier_from_embedded_jwk(alg, &verifier_jwk)?;
+            let (payload, _) = self.decode_with_verifier(input, &*verifier)?;
+
+            (|| -> anyhow::Result<()> {
+                match payload.claim("htm") {
+                    Some(Value::String(val)) if val == htm => {}
+                    Some(Value::String(val)) => bail!("Key htm is invalid: {}", val),
+                    Some(_) => bail!("Key htm must be a string."),
+                    None => bail!("Key htm is missing."),
+                }
+
+                let expected_htu = match htu.find(['?', '#']) {
+                    Some(pos) => &htu[..pos],
+                    None => htu,
+                };
+                match payload.claim("htu") {
+                    Some(Value::String(val)) if val == expected_htu => {}
+                    Some(Value::String(val)) => bail!("Key htu is invalid: {}", val),
+                    Some(_) => bail!("Key htu must be a string."),
+                    None => bail!("Key htu is missing."),
+                }
+
+                match payload.jwt_id() {
+                    Some(val) if !val.is_empty() => {}
+                    _ => bail!("Key jti is missing."),
+                }
+
+                let issued_at = match payload.issued_at() {
+                    Some(val) => val,
+                    None => bail!("Key iat is missing."),
+                };
+                let now = clock.now();
+                if issued_at < now.checked_sub(max_age).unwrap_or(SystemTime::UNIX_EPOCH) {
+                    bail!(
+                        "The issued time is too old: {}",
+                        time::OffsetDateTime::from(issued_at),
+                    );
+                }
+                if now.checked_add(max_age).is_some_and(|val| issued_at > val) {
+                    bail!(
+                        "The issued time is too new: {}",
+                        time::OffsetDateTime::from(issued_at),
+                    );
+                }
+
+                Ok(())
+            })()
+            .map_err(JoseError::InvalidClaim)?;
+
+            Ok(jwk.thumbprint(ThumbprintHash::Sha256)?)
+        })()
+        .map_err(|err| match err.downcast::<JoseError>() {
+            Ok(err) => err,
+            Err(err) => JoseError::InvalidJwtFormat(err),
+        })
+    }
+
     /// Return the JWT object decoded by the selected decrypter.
     ///
     /// # Arguments
@@ -293,8 +480,7 @@ impl JwtContext {
                         Ok(Some(decrypter))
                     })?;
 
-            let payload: Map<String, Value> = serde_json::from_slice(&payload)?;
-            let payload = JwtPayload::from_map(payload)?;
+            let payload = Self::parse_payload(&payload)?;
 
             Ok((payload, header))
         })()
@@ -334,4 +520,16 @@ impl JwtContext {
             Ok(None)
         })
     }
+
+    fn parse_payload(payload: &[u8]) -> anyhow::Result<JwtPayload> {
+        let payload = match std::str::from_utf8(payload) {
+            Ok(val) => val,
+            Err(err) => bail!("The JWT payload must be a valid UTF-8 string: {}", err),
+        };
+        let payload = match serde_json::from_str(payload)? {
+            Value::Object(val) => JwtPayload::from_map(val)?,
+            _ => bail!("The JWT payload must be a JSON object."),
+        };
+        Ok(payload)
+    }
 }
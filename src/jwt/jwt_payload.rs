@@ -2,8 +2,9 @@ use std::convert::Into;
 use std::fmt::Display;
 use std::time::{Duration, SystemTime};
 
+use crate::jwt::Confirmation;
 use crate::{JoseError, Map, Number, Value};
-use anyhow::bail;
+use anyhow::{anyhow, bail};
 
 #[derive(Debug, Eq, PartialEq, Clone, Default)]
 pub struct JwtPayload {
@@ -210,6 +211,31 @@ impl JwtPayload {
         }
     }
 
+    /// Set a value for confirmation payload claim (cnf).
+    ///
+    /// # Arguments
+    ///
+    /// * `value` - a proof-of-possession key
+    pub fn set_confirmation(&mut self, value: &Confirmation) {
+        self.claims
+            .insert("cnf".to_string(), Value::Object(value.to_map()));
+    }
+
+    /// Return the value for confirmation payload claim (cnf).
+    ///
+    /// The jwk member takes precedence over the jkt member. None is returned when
+    /// the claim is absent or has neither member, and an error is returned when
+    /// the claim or its member is malformed.
+    pub fn confirmation(&self) -> Result<Option<Confirmation>, JoseError> {
+        match self.claims.get("cnf") {
+            Some(Value::Object(val)) => Confirmation::from_map(val),
+            Some(_) => Err(JoseError::InvalidClaim(anyhow!(
+                "The cnf claim must be an object."
+            ))),
+            None => Ok(None),
+        }
+    }
+
     /// Set a value for payload claim of a specified key.
     ///
     /// # Arguments
@@ -315,6 +341,9 @@ mod tests {
     use serde_json::json;
 
     use super::JwtPayload;
+    use crate::jwk::{Jwk, ThumbprintHash};
+    use crate::jwt::Confirmation;
+    use crate::{util, JoseError};
 
     #[test]
     fn test_new_payload() -> Result<()> {
@@ -343,4 +372,89 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn test_confirmation() -> Result<()> {
+        let jwk = Jwk::from_bytes(
+            br#"{"kty":"EC","crv":"P-256","x":"l8tFrhx-34tV3hRICRDY9zCkDlpBhF42UQUfWVAWBFs","y":"9VE4jf_Ok_o64zbTTlcuNJajHmt6v9TDVrU0CdvGRDA"}"#,
+        )?;
+        let thumbprint = jwk.thumbprint(ThumbprintHash::Sha256)?;
+
+        let payload = JwtPayload::from_map(
+            json!({ "cnf": { "jwk": jwk } })
+                .as_object()
+                .unwrap()
+                .clone(),
+        )?;
+        assert_eq!(
+            payload.confirmation()?,
+            Some(Confirmation::Jwk(jwk.clone()))
+        );
+
+        let jkt = util::encode_base64_urlsafe_nopad(&thumbprint);
+        let payload = JwtPayload::from_map(
+            json!({ "cnf": { "jkt": jkt } })
+                .as_object()
+                .unwrap()
+                .clone(),
+        )?;
+        assert_eq!(
+            payload.confirmation()?,
+            Some(Confirmation::JwkThumbprint(thumbprint.clone()))
+        );
+
+        let mut payload = JwtPayload::new();
+        assert_eq!(payload.confirmation()?, None);
+        payload.set_confirmation(&Confirmation::JwkThumbprint(thumbprint.clone()));
+        assert_eq!(payload.claim("cnf"), Some(&json!({ "jkt": jkt })));
+
+        payload.set_claim("cnf", Some(json!({ "x5t#S256": jkt })))?;
+        assert_eq!(payload.confirmation()?, None);
+
+        payload.set_claim("cnf", Some(json!({ "jwk": { "crv": "P-256" } })))?;
+        match payload.confirmation() {
+            Err(JoseError::InvalidClaim(err)) => {
+                assert!(err.to_string().contains("kty"), "{}", err)
+            }
+            other => panic!("A malformed jwk member was accepted: {:?}", other),
+        }
+
+        payload.set_claim("cnf", Some(json!({ "jkt": "not base64url!" })))?;
+        match payload.confirmation() {
+            Err(JoseError::InvalidClaim(_)) => {}
+            other => panic!("A malformed jkt member was accepted: {:?}", other),
+        }
+
+        payload.set_claim("cnf", Some(json!({ "jkt": 1 })))?;
+        match payload.confirmation() {
+            Err(JoseError::InvalidClaim(err)) => assert_eq!(
+                err.to_string(),
+                "The jkt member of the cnf claim must be a string."
+            ),
+            other => panic!("A malformed jkt member was accepted: {:?}", other),
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_confirmation_is_confirmed_by() -> Result<()> {
+        let jwk = Jwk::from_bytes(
+            br#"{"kty":"EC","crv":"P-256","x":"l8tFrhx-34tV3hRICRDY9zCkDlpBhF42UQUfWVAWBFs","y":"9VE4jf_Ok_o64zbTTlcuNJajHmt6v9TDVrU0CdvGRDA"}"#,
+        )?;
+        let other = Jwk::from_bytes(
+            br#"{"kty":"EC","crv":"P-256","x":"MKBCTNIcKUSDii11ySs3526iDZ8AiTo7Tu6KPAqv7D4","y":"4Etl6SRW2YiLUrN5vfvVHuhp7x8PxltmWWlbbM4IFyM"}"#,
+        )?;
+        let thumbprint = jwk.thumbprint(ThumbprintHash::Sha256)?;
+
+        let confirmation = Confirmation::JwkThumbprint(thumbprint);
+        assert!(confirmation.is_confirmed_by(&jwk)?);
+        assert!(!confirmation.is_confirmed_by(&other)?);
+
+        let confirmation = Confirmation::Jwk(jwk.clone());
+        assert!(confirmation.is_confirmed_by(&jwk)?);
+        assert!(!confirmation.is_confirmed_by(&other)?);
+
+        Ok(())
+    }
 }
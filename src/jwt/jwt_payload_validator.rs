@@ -3,31 +3,76 @@ use std::time::SystemTime;
 
 use anyhow::bail;
 
-use crate::jwt::JwtPayload;
+use crate::jwt::{Clock, JwtPayload, SystemClock};
 use crate::{JoseError, Map, Value};
 
 /// Represents JWT payload validator.
-#[derive(Debug, Eq, PartialEq)]
+#[derive(Debug)]
 pub struct JwtPayloadValidator {
+    clock: Option<Box<dyn Clock>>,
     base_time: Option<SystemTime>,
     min_issued_time: Option<SystemTime>,
     max_issued_time: Option<SystemTime>,
+    issuers: Option<Vec<String>>,
     audience: Option<String>,
+    require_expiration: bool,
+    require_not_before: bool,
     claims: Map<String, Value>,
 }
 
+/// The clock is not compared, because it has no notion of equality.
+impl PartialEq for JwtPayloadValidator {
+    fn eq(&self, other: &Self) -> bool {
+        self.base_time == other.base_time
+            && self.min_issued_time == other.min_issued_time
+            && self.max_issued_time == other.max_issued_time
+            && self.issuers == other.issuers
+            && self.audience == other.audience
+            && self.require_expiration == other.require_expiration
+            && self.require_not_before == other.require_not_before
+            && self.claims == other.claims
+    }
+}
+
+impl Eq for JwtPayloadValidator {}
+
 impl JwtPayloadValidator {
     /// Return a new JwtPayloadValidator.
     pub fn new() -> Self {
         Self {
+            clock: None,
             base_time: None,
             min_issued_time: None,
             max_issued_time: None,
+            issuers: None,
             audience: None,
+            require_expiration: false,
+            require_not_before: false,
             claims: Map::new(),
         }
     }
 
+    /// Return this validator that uses the clock for the current time.
+    ///
+    /// The default is the system time. The base time is preferred
+    /// for time related claims (exp, nbf) validation when it is set.
+    ///
+    /// # Arguments
+    ///
+    /// * `clock` - a clock
+    pub fn with_clock(mut self, clock: impl Clock + 'static) -> Self {
+        self.clock = Some(Box::new(clock));
+        self
+    }
+
+    /// Return the clock for the current time.
+    pub fn clock(&self) -> &dyn Clock {
+        match &self.clock {
+            Some(val) => val.as_ref(),
+            None => &SystemClock,
+        }
+    }
+
     /// Set a base time for time related claims (exp, nbf) validation.
     ///
     /// # Arguments
@@ -42,6 +87,34 @@ impl JwtPayloadValidator {
         self.base_time.as_ref()
     }
 
+    /// Set whether the expiration time payload claim (exp) is required.
+    ///
+    /// # Arguments
+    ///
+    /// * `value` - a flag to reject a JWT without exp
+    pub fn set_require_expiration(&mut self, value: bool) {
+        self.require_expiration = value;
+    }
+
+    /// Test whether the expiration time payload claim (exp) is required.
+    pub fn is_require_expiration(&self) -> bool {
+        self.require_expiration
+    }
+
+    /// Set whether the not before payload claim (nbf) is required.
+    ///
+    /// # Arguments
+    ///
+    /// * `value` - a flag to reject a JWT without nbf
+    pub fn set_require_not_before(&mut self, value: bool) {
+        self.require_not_before = value;
+    }
+
+    /// Test whether the not before payload claim (nbf) is required.
+    pub fn is_require_not_before(&self) -> bool {
+        self.require_not_before
+    }
+
     /// Set a minimum time for issued at payload claim (iat) validation.
     ///
     /// # Arguments
@@ -77,6 +150,7 @@ impl JwtPayloadValidator {
     /// * `value` - a issuer
     pub fn set_issuer(&mut self, value: impl Into<String>) {
         let value: String = value.into();
+        self.issuers = None;
         self.claims.insert("iss".to_string(), Value::String(value));
     }
 
@@ -88,6 +162,26 @@ impl JwtPayloadValidator {
         }
     }
 
+    /// Set acceptable values for issuer payload claim (iss) validation.
+    ///
+    /// The validation passes if the issuer matches any of them.
+    ///
+    /// # Arguments
+    ///
+    /// * `values` - acceptable issuers
+    pub fn set_issuers(&mut self, values: &[&str]) {
+        let values: Vec<String> = values.iter().map(|val| val.to_string()).collect();
+        self.claims.remove("iss");
+        self.issuers = Some(values);
+    }
+
+    /// Return acceptable values for issuer payload claim (iss) validation.
+    pub fn issuers(&self) -> Option<Vec<&str>> {
+        self.issuers
+            .as_ref()
+            .map(|vals| vals.iter().map(|val| val.as_str()).collect())
+    }
+
     /// Set a value for subject payload claim (sub) validation.
     ///
     /// # Arguments
@@ -168,27 +262,29 @@ impl JwtPayloadValidator {
     /// * `payload` - a decoded JWT payload.
     pub fn validate(&self, payload: &JwtPayload) -> Result<(), JoseError> {
         (|| -> anyhow::Result<()> {
-            let now = SystemTime::now();
+            let now = self.clock().now();
             let current_time = self.base_time().unwrap_or(&now);
             let min_issued_time = self.min_issued_time().unwrap_or(&SystemTime::UNIX_EPOCH);
             let max_issued_time = self.max_issued_time().unwrap_or(&now);
 
-            if let Some(not_before) = payload.not_before() {
-                if &not_before > current_time {
-                    bail!(
-                        "The token is not yet valid: {}",
-                        time::OffsetDateTime::from(not_before),
-                    );
-                }
+            match payload.not_before() {
+                Some(not_before) if &not_before > current_time => bail!(
+                    "The token is not yet valid: {}",
+                    time::OffsetDateTime::from(not_before),
+                ),
+                Some(_) => {}
+                None if self.require_not_before => bail!("Key nbf is missing."),
+                None => {}
             }
 
-            if let Some(expires_at) = payload.expires_at() {
-                if &expires_at <= current_time {
-                    bail!(
-                        "The token has expired: {}",
-                        time::OffsetDateTime::from(expires_at),
-                    );
-                }
+            match payload.expires_at() {
+                Some(expires_at) if &expires_at <= current_time => bail!(
+                    "The token has expired: {}",
+                    time::OffsetDateTime::from(expires_at),
+                ),
+                Some(_) => {}
+                None if self.require_expiration => bail!("Key exp is missing."),
+                None => {}
             }
 
             if let Some(issued_at) = payload.issued_at() {
@@ -207,11 +303,40 @@ impl JwtPayloadValidator {
                 }
             }
 
+            if let Some(issuers) = &self.issuers {
+                match payload.issuer() {
+                    Some(val) if issuers.iter().any(|issuer| issuer == val) => {}
+                    Some(val) => bail!("Key iss is invalid: {}", val),
+                    None => bail!("Key iss is missing."),
+                }
+            }
+
             if let Some(audience) = &self.audience {
-                if let Some(audiences) = payload.audience() {
-                    if !audiences.contains(&audience.as_str()) {
-                        bail!("Key aud is invalid: {}", audiences.join(", "));
+                match payload.claim("aud") {
+                    Some(Value::String(val)) => {
+                        if val != audience {
+                            bail!("Key aud is invalid: {}", val);
+                        }
                     }
+                    Some(Value::Array(vals)) => {
+                        if vals.is_empty() {
+                            bail!("Key aud must not be empty.");
+                        }
+
+                        let mut audiences = Vec::with_capacity(vals.len());
+                        for val in vals {
+                            match val {
+                                Value::String(val) => audiences.push(val.as_str()),
+                                _ => bail!("An element of key aud must be a string: {}", val),
+                            }
+                        }
+
+                        if !audiences.contains(&audience.as_str()) {
+                            bail!("Key aud is invalid: {}", audiences.join(", "));
+                        }
+                    }
+                    Some(val) => bail!("Key aud must be a string or array: {}", val),
+                    None => bail!("Key aud is missing."),
                 }
             }
 
@@ -241,7 +366,44 @@ mod tests {
     use anyhow::Result;
     use serde_json::json;
 
-    use crate::jwt::{JwtPayload, JwtPayloadValidator};
+    use crate::jwt::{Clock, JwtPayload, JwtPayloadValidator};
+    use crate::JoseError;
+
+    #[derive(Debug)]
+    struct FakeClock(SystemTime);
+
+    impl Clock for FakeClock {
+        fn now(&self) -> SystemTime {
+            self.0
+        }
+    }
+
+    #[test]
+    fn test_jwt_payload_validate_with_clock() -> Result<()> {
+        let expires_at = SystemTime::UNIX_EPOCH + Duration::from_secs(60);
+        let mut payload = JwtPayload::new();
+        payload.set_expires_at(&expires_at);
+
+        let before = expires_at - Duration::from_secs(1);
+        let validator = JwtPayloadValidator::new().with_clock(FakeClock(before));
+        assert_eq!(validator.clock().now(), before);
+        validator.validate(&payload)?;
+
+        let validator = JwtPayloadValidator::new().with_clock(FakeClock(expires_at));
+        assert!(validator.validate(&payload).is_err());
+
+        let after = expires_at + Duration::from_secs(1);
+        let validator = JwtPayloadValidator::new().with_clock(FakeClock(after));
+        match validator.validate(&payload) {
+            Err(JoseError::InvalidClaim(err)) => {
+                assert!(err.to_string().starts_with("The token has expired"))
+            }
+            other => panic!("An expired token was accepted: {:?}", other),
+        }
+        assert_eq!(validator, JwtPayloadValidator::new());
+
+        Ok(())
+    }
 
     #[test]
     fn test_jwt_payload_validate() -> Result<()> {
@@ -264,4 +426,112 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn test_jwt_payload_validate_required_claims() -> Result<()> {
+        let mut payload = JwtPayload::new();
+        payload.set_issuer("iss");
+
+        let mut validator = JwtPayloadValidator::new();
+        assert!(!validator.is_require_expiration());
+        assert!(!validator.is_require_not_before());
+        validator.validate(&payload)?;
+
+        validator.set_require_expiration(true);
+        match validator.validate(&payload) {
+            Err(JoseError::InvalidClaim(err)) => assert_eq!(err.to_string(), "Key exp is missing."),
+            other => panic!("A token without exp was accepted: {:?}", other),
+        }
+
+        validator.set_require_expiration(false);
+        validator.set_require_not_before(true);
+        match validator.validate(&payload) {
+            Err(JoseError::InvalidClaim(err)) => assert_eq!(err.to_string(), "Key nbf is missing."),
+            other => panic!("A token without nbf was accepted: {:?}", other),
+        }
+
+        validator.set_require_expiration(true);
+        validator.set_base_time(SystemTime::UNIX_EPOCH + Duration::from_secs(30));
+        payload.set_expires_at(&(SystemTime::UNIX_EPOCH + Duration::from_secs(60)));
+        payload.set_not_before(&(SystemTime::UNIX_EPOCH + Duration::from_secs(10)));
+        validator.validate(&payload)?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_jwt_payload_validate_audience() -> Result<()> {
+        let mut validator = JwtPayloadValidator::new();
+        validator.set_audience("aud1");
+
+        let mut payload = JwtPayload::new();
+        payload.set_claim("aud", Some(json!("aud1")))?;
+        validator.validate(&payload)?;
+
+        payload.set_claim("aud", Some(json!("aud0")))?;
+        assert!(validator.validate(&payload).is_err());
+
+        payload.set_audience(vec!["aud0", "aud1"]);
+        validator.validate(&payload)?;
+
+        payload.set_audience(vec!["aud0", "aud2"]);
+        assert!(validator.validate(&payload).is_err());
+
+        let mut payload = JwtPayload::new();
+        match validator.validate(&payload) {
+            Err(JoseError::InvalidClaim(err)) => assert_eq!(err.to_string(), "Key aud is missing."),
+            other => panic!("A token without aud was accepted: {:?}", other),
+        }
+
+        payload.set_claim("aud", Some(json!([])))?;
+        match validator.validate(&payload) {
+            Err(JoseError::InvalidClaim(err)) => {
+                assert_eq!(err.to_string(), "Key aud must not be empty.")
+            }
+            other => panic!("A token with empty aud was accepted: {:?}", other),
+        }
+
+        let claims = json!({ "aud": ["aud1", 1] });
+        assert!(JwtPayload::from_map(claims.as_object().unwrap().clone()).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_jwt_payload_validate_issuers() -> Result<()> {
+        let mut validator = JwtPayloadValidator::new();
+        validator.set_issuers(&["https://tenant1.example.com", "https://tenant2.example.com"]);
+        assert_eq!(
+            validator.issuers(),
+            Some(vec![
+                "https://tenant1.example.com",
+                "https://tenant2.example.com"
+            ])
+        );
+
+        let mut payload = JwtPayload::new();
+        payload.set_issuer("https://tenant2.example.com");
+        validator.validate(&payload)?;
+
+        payload.set_issuer("https://tenant3.example.com");
+        match validator.validate(&payload) {
+            Err(JoseError::InvalidClaim(err)) => assert_eq!(
+                err.to_string(),
+                "Key iss is invalid: https://tenant3.example.com"
+            ),
+            other => panic!("A token from other issuer was accepted: {:?}", other),
+        }
+
+        let payload = JwtPayload::new();
+        match validator.validate(&payload) {
+            Err(JoseError::InvalidClaim(err)) => assert_eq!(err.to_string(), "Key iss is missing."),
+            other => panic!("A token without iss was accepted: {:?}", other),
+        }
+
+        validator.set_issuer("https://tenant1.example.com");
+        assert_eq!(validator.issuers(), None);
+        assert_eq!(validator.issuer(), Some("https://tenant1.example.com"));
+
+        Ok(())
+    }
 }
@@ -2,6 +2,7 @@
 //!
 //! `josekit` is a JOSE (Javascript Object Signing and Encryption: JWT, JWS, JWE, JWA, JWK) library.
 
+pub mod alg_names;
 pub mod jwe;
 pub mod jwk;
 pub mod jws;
@@ -10,9 +11,12 @@ pub mod util;
 
 mod jose_error;
 mod jose_header;
+mod security_policy;
 
 pub use crate::jose_error::JoseError;
 pub use crate::jose_header::JoseHeader;
+pub use crate::jwt::collect_key_ids;
+pub use crate::security_policy::SecurityPolicy;
 
 pub use serde_json::{Map, Number, Value};
 
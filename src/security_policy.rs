@@ -0,0 +1,287 @@
+use anyhow::bail;
+
+use crate::jwe::JweHeader;
+use crate::jwk::Jwk;
+use crate::jws::JwsHeader;
+use crate::JoseError;
+
+/// Represents a security policy that tokens and keys are checked against.
+///
+/// The policy centralizes the allowed algorithms, the minimum RSA key size
+/// and the required token type, so an application can declare them once.
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub struct SecurityPolicy {
+    min_rsa_bits: usize,
+    allowed_jwe_algs: Option<Vec<String>>,
+    allowed_jwe_encs: Option<Vec<String>>,
+    allowed_jws_algs: Option<Vec<String>>,
+    require_typ: Option<String>,
+}
+
+impl SecurityPolicy {
+    /// Return a new SecurityPolicy that requires RSA keys of 2048 bits or more
+    /// and allows any algorithm.
+    pub fn new() -> Self {
+        Self {
+            min_rsa_bits: 2048,
+            allowed_jwe_algs: None,
+            allowed_jwe_encs: None,
+            allowed_jws_algs: None,
+            require_typ: None,
+        }
+    }
+
+    /// Set a minimum bit length of RSA keys.
+    ///
+    /// # Arguments
+    ///
+    /// * `value` - a minimum bit length
+    pub fn set_min_rsa_bits(&mut self, value: usize) {
+        self.min_rsa_bits = value;
+    }
+
+    /// Return the minimum bit length of RSA keys.
+    pub fn min_rsa_bits(&self) -> usize {
+        self.min_rsa_bits
+    }
+
+    /// Set acceptable values for JWE algorithm header claim (alg).
+    ///
+    /// # Arguments
+    ///
+    /// * `values` - acceptable algorithms
+    pub fn set_allowed_jwe_algs(&mut self, values: &[&str]) {
+        self.allowed_jwe_algs = Some(values.iter().map(|val| val.to_string()).collect());
+    }
+
+    /// Return acceptable values for JWE algorithm header claim (alg).
+    pub fn allowed_jwe_algs(&self) -> Option<Vec<&str>> {
+        self.allowed_jwe_algs
+            .as_ref()
+            .map(|vals| vals.iter().map(|val| val.as_str()).collect())
+    }
+
+    /// Set acceptable values for JWE content encryption header claim (enc).
+    ///
+    /// # Arguments
+    ///
+    /// * `values` - acceptable content encryptions
+    pub fn set_allowed_jwe_encs(&mut self, values: &[&str]) {
+        self.allowed_jwe_encs = Some(values.iter().map(|val| val.to_string()).collect());
+    }
+
+    /// Return acceptable values for JWE content encryption header claim (enc).
+    pub fn allowed_jwe_encs(&self) -> Option<Vec<&str>> {
+        self.allowed_jwe_encs
+            .as_ref()
+            .map(|vals| vals.iter().map(|val| val.as_str()).collect())
+    }
+
+    /// Set acceptable values for JWS algorithm header claim (alg).
+    ///
+    /// # Arguments
+    ///
+    /// * `values` - acceptable algorithms
+    pub fn set_allowed_jws_algs(&mut self, values: &[&str]) {
+        self.allowed_jws_algs = Some(values.iter().map(|val| val.to_string()).collect());
+    }
+
+    /// Return acceptable values for JWS algorithm header claim (alg).
+    pub fn allowed_jws_algs(&self) -> Option<Vec<&str>> {
+        self.allowed_jws_algs
+            .as_ref()
+            .map(|vals| vals.iter().map(|val| val.as_str()).collect())
+    }
+
+    /// Set a required value for token type header claim (typ).
+    ///
+    /// # Arguments
+    ///
+    /// * `value` - a token type
+    pub fn set_require_typ(&mut self, value: impl Into<String>) {
+        self.require_typ = Some(value.into());
+    }
+
+    /// Return the required value for token type header claim (typ).
+    pub fn require_typ(&self) -> Option<&str> {
+        self.require_typ.as_deref()
+    }
+
+    /// Check the JWE header against this policy.
+    ///
+    /// # Arguments
+    ///
+    /// * `header` - a JWE header
+    pub fn check_jwe_header(&self, header: &JweHeader) -> Result<(), JoseError> {
+        (|| -> anyhow::Result<()> {
+            Self::check_allowed("JWE alg", header.algorithm(), &self.allowed_jwe_algs)?;
+            Self::check_allowed(
+                "JWE enc",
+                header.content_encryption(),
+                &self.allowed_jwe_encs,
+            )?;
+            self.check_typ(header.token_type())?;
+            Ok(())
+        })()
+        .map_err(JoseError::InvalidJweFormat)
+    }
+
+    /// Check the JWS header against this policy.
+    ///
+    /// # Arguments
+    ///
+    /// * `header` - a JWS header
+    pub fn check_jws_header(&self, header: &JwsHeader) -> Result<(), JoseError> {
+        (|| -> anyhow::Result<()> {
+            Self::check_allowed("JWS alg", header.algorithm(), &self.allowed_jws_algs)?;
+            self.check_typ(header.token_type())?;
+            Ok(())
+        })()
+        .map_err(JoseError::InvalidJwsFormat)
+    }
+
+    /// Check the JWK against this policy.
+    ///
+    /// A RSA key must have the modulus of the minimum bit length or more.
+    ///
+    /// # Arguments
+    ///
+    /// * `jwk` - a JWK
+    pub fn check_jwk(&self, jwk: &Jwk) -> Result<(), JoseError> {
+        (|| -> anyhow::Result<()> {
+            if jwk.key_type() == "RSA" {
+                let n = match jwk.parameter_bytes("n")? {
+                    Some(val) => val,
+                    None => bail!("A parameter n is required."),
+                };
+                let n = match n.iter().position(|b| *b != 0) {
+                    Some(pos) => &n[pos..],
+                    None => &n[n.len()..],
+                };
+                let bits = match n.first() {
+                    Some(b) => n.len() * 8 - b.leading_zeros() as usize,
+                    None => 0,
+                };
+                if bits < self.min_rsa_bits {
+                    bail!("key length must be {} or more: {}", self.min_rsa_bits, bits);
+                }
+            }
+            Ok(())
+        })()
+        .map_err(|err| match err.downcast::<JoseError>() {
+            Ok(err) => err,
+            Err(err) => JoseError::InvalidKeyFormat(err),
+        })
+    }
+
+    fn check_allowed(
+        name: &str,
+        value: Option<&str>,
+        allowed: &Option<Vec<String>>,
+    ) -> anyhow::Result<()> {
+        if let Some(allowed) = allowed {
+            match value {
+                Some(val) if allowed.iter().any(|e| e == val) => {}
+                Some(val) => bail!("The {} header claim is not allowed: {}", name, val),
+                None => bail!("The {} header claim is required.", name),
+            }
+        }
+        Ok(())
+    }
+
+    fn check_typ(&self, value: Option<&str>) -> anyhow::Result<()> {
+        if let Some(expected) = &self.require_typ {
+            match value {
+                Some(val) if val == expected => {}
+                Some(val) => bail!("The typ header claim is not {}: {}", expected, val),
+                None => bail!("The typ header claim is required."),
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Default for SecurityPolicy {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+    use std::fs;
+    use std::path::PathBuf;
+
+    use super::SecurityPolicy;
+    use crate::jwe::JweHeader;
+    use crate::jwk::Jwk;
+    use crate::jws::JwsHeader;
+    use crate::JoseError;
+
+    #[test]
+    fn test_check_jwe_header() -> Result<()> {
+        let mut policy = SecurityPolicy::new();
+        policy.set_allowed_jwe_algs(&["RSA-OAEP-256", "ECDH-ES"]);
+        policy.set_allowed_jwe_encs(&["A256GCM"]);
+        policy.set_require_typ("JWT");
+
+        let mut header = JweHeader::new();
+        header.set_algorithm("ECDH-ES");
+        header.set_content_encryption("A256GCM");
+        header.set_token_type("JWT");
+        policy.check_jwe_header(&header)?;
+
+        header.set_algorithm("RSA1_5");
+        match policy.check_jwe_header(&header) {
+            Err(JoseError::InvalidJweFormat(err)) => assert_eq!(
+                err.to_string(),
+                "The JWE alg header claim is not allowed: RSA1_5"
+            ),
+            other => panic!("A disallowed alg was accepted: {:?}", other),
+        }
+
+        header.set_algorithm("RSA-OAEP-256");
+        header.set_token_type("at+jwt");
+        assert!(policy.check_jwe_header(&header).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_check_jws_header() -> Result<()> {
+        let mut policy = SecurityPolicy::new();
+        policy.set_allowed_jws_algs(&["ES256"]);
+
+        let mut header = JwsHeader::new();
+        header.set_algorithm("ES256");
+        policy.check_jws_header(&header)?;
+
+        header.set_algorithm("HS256");
+        assert!(policy.check_jws_header(&header).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_check_jwk() -> Result<()> {
+        let jwk = Jwk::from_bytes(load_file("jwk/RSA_public.jwk")?)?;
+
+        let mut policy = SecurityPolicy::new();
+        policy.check_jwk(&jwk)?;
+
+        policy.set_min_rsa_bits(3072);
+        assert!(policy.check_jwk(&jwk).is_err());
+
+        Ok(())
+    }
+
+    fn load_file(path: &str) -> Result<Vec<u8>> {
+        let mut pb = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        pb.push("data");
+        pb.push(path);
+
+        let data = fs::read(&pb)?;
+        Ok(data)
+    }
+}
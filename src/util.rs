@@ -1,21 +1,35 @@
+mod base64_url;
+mod compact_error;
 pub mod der;
 pub mod hash_algorithm;
 pub mod oid;
+mod random_source;
+pub(crate) mod rsa;
 
 use std::sync::LazyLock;
 
 use anyhow::bail;
 use base64::DecodeError;
 use base64::Engine as _;
+use openssl::hash::{Hasher, MessageDigest};
+use openssl::pkcs12::Pkcs12;
+use openssl::pkey::{PKey, Private};
 use openssl::rand;
+use openssl::x509::X509;
 use regex;
 
+use crate::JoseError;
+
+pub use crate::util::base64_url::{Base64UrlReader, Base64UrlWriter};
+pub(crate) use crate::util::compact_error::CompactError;
 pub use crate::util::hash_algorithm::HashAlgorithm;
+pub use crate::util::random_source::{OpensslRandomSource, RandomSource};
 
 pub use HashAlgorithm::Sha1 as SHA_1;
 pub use HashAlgorithm::Sha256 as SHA_256;
 pub use HashAlgorithm::Sha384 as SHA_384;
 pub use HashAlgorithm::Sha512 as SHA_512;
+pub use HashAlgorithm::Sha512_256 as SHA_512_256;
 
 pub fn random_bytes(len: usize) -> Vec<u8> {
     let mut vec = vec![0; len];
@@ -27,6 +41,63 @@ pub(crate) fn ceiling(len: usize, div: usize) -> usize {
     (len + (div - 1)) / div
 }
 
+/// Derive a key by the Concat KDF defined in NIST SP 800-56A.
+///
+/// The AlgorithmID, PartyUInfo and PartyVInfo are prefixed by its 32 bit length
+/// and the SuppPubInfo is set to the key length in bits.
+///
+/// # Arguments
+///
+/// * `z` - The shared secret.
+/// * `key_len` - The length of the derived key in bytes.
+/// * `alg_id` - The algorithm identifier.
+/// * `party_u` - The information of the producer.
+/// * `party_v` - The information of the recipient.
+/// * `hash` - The message digest function.
+pub fn concat_kdf(
+    z: &[u8],
+    key_len: usize,
+    alg_id: &[u8],
+    party_u: &[u8],
+    party_v: &[u8],
+    hash: MessageDigest,
+) -> Result<Vec<u8>, JoseError> {
+    (|| -> anyhow::Result<Vec<u8>> {
+        let key_len_bytes = ((key_len * 8) as u32).to_be_bytes();
+        let alg_id = datalen_prefixed(alg_id);
+        let party_u = datalen_prefixed(party_u);
+        let party_v = datalen_prefixed(party_v);
+
+        let count = ceiling(key_len, hash.size());
+        let mut derived_key = Vec::with_capacity(count * hash.size());
+        for i in 0..count {
+            let mut hasher = Hasher::new(hash)?;
+            hasher.update(&((i + 1) as u32).to_be_bytes())?;
+            hasher.update(z)?;
+            hasher.update(&alg_id)?;
+            hasher.update(&party_u)?;
+            hasher.update(&party_v)?;
+            hasher.update(&key_len_bytes)?;
+
+            let digest = hasher.finish()?;
+            derived_key.extend_from_slice(&digest);
+        }
+        derived_key.truncate(key_len);
+
+        Ok(derived_key)
+    })()
+    .map_err(JoseError::InvalidKeyFormat)
+}
+
+/// Prefix the data by its 32 bit big-endian length, as the Datalen || Data
+/// form of the Concat KDF inputs.
+pub(crate) fn datalen_prefixed(data: &[u8]) -> Vec<u8> {
+    let mut vec = Vec::with_capacity(4 + data.len());
+    vec.extend_from_slice(&(data.len() as u32).to_be_bytes());
+    vec.extend_from_slice(data);
+    vec
+}
+
 pub(crate) fn is_base64_standard(input: &str) -> bool {
     static RE_BASE64_STANDARD: LazyLock<regex::Regex> = LazyLock::new(|| {
         regex::Regex::new(
@@ -71,6 +142,14 @@ pub(crate) fn decode_base64_urlsafe_no_pad(
     base64::engine::general_purpose::URL_SAFE_NO_PAD.decode(input)
 }
 
+/// Compare two base64url strings in constant time.
+///
+/// The comparison time depends only on the length, so it is safe for the values
+/// that have the same decoded length, like thumbprints of the same hash algorithm.
+pub(crate) fn ct_eq_str(a: &str, b: &str) -> bool {
+    a.len() == b.len() && openssl::memcmp::eq(a.as_bytes(), b.as_bytes())
+}
+
 pub(crate) fn parse_pem(input: &[u8]) -> anyhow::Result<(String, Vec<u8>)> {
     static RE_PEM: LazyLock<regex::bytes::Regex> = LazyLock::new(|| {
         regex::bytes::Regex::new(concat!(
@@ -103,10 +182,147 @@ pub(crate) fn parse_pem(input: &[u8]) -> anyhow::Result<(String, Vec<u8>)> {
     Ok(result)
 }
 
+/// Parse all PEM blocks in the input and return the labels and the decoded data in order.
+///
+/// This is for a file that contains multiple blocks, like a private key followed by
+/// its certificate chain.
+///
+/// # Arguments
+///
+/// * `input` - PEM encoded blocks.
+pub fn parse_pem_all(input: &[u8]) -> Result<Vec<(String, Vec<u8>)>, JoseError> {
+    static RE_PEM_BLOCK: LazyLock<regex::bytes::Regex> = LazyLock::new(|| {
+        regex::bytes::Regex::new(concat!(
+            r"-----BEGIN ([A-Z0-9 -]+)-----[\t ]*(?:\r\n|[\r\n])",
+            r"([\t\r\n a-zA-Z0-9+/=]+)",
+            r"-----END ([A-Z0-9 -]+)-----",
+        ))
+        .unwrap()
+    });
+
+    static RE_FILTER: LazyLock<regex::bytes::Regex> =
+        LazyLock::new(|| regex::bytes::Regex::new("[\t\r\n ]").unwrap());
+
+    (|| -> anyhow::Result<Vec<(String, Vec<u8>)>> {
+        let mut blocks = Vec::new();
+        for caps in RE_PEM_BLOCK.captures_iter(input) {
+            match (caps.get(1), caps.get(2), caps.get(3)) {
+                (Some(m1), Some(m2), Some(m3)) if m1.as_bytes() == m3.as_bytes() => {
+                    let label = String::from_utf8(m1.as_bytes().to_vec())?;
+                    let base64_data =
+                        RE_FILTER.replace_all(m2.as_bytes(), regex::bytes::NoExpand(b""));
+                    let data = decode_base64_standard(&base64_data)?;
+                    blocks.push((label, data));
+                }
+                _ => bail!("Mismatched the begging and ending label."),
+            }
+        }
+        if blocks.is_empty() {
+            bail!("Invalid PEM format.");
+        }
+        Ok(blocks)
+    })()
+    .map_err(JoseError::InvalidKeyFormat)
+}
+
+/// Parse a DER encoded PKCS#12 bundle and return the private key and the certificates.
+///
+/// The certificate of the private key comes first, followed by the other certificates.
+///
+/// # Arguments
+///
+/// * `input` - A DER encoded PKCS#12 bundle.
+/// * `password` - The password of the PKCS#12 bundle.
+pub fn parse_pkcs12(input: &[u8], password: &str) -> Result<(PKey<Private>, Vec<X509>), JoseError> {
+    (|| -> anyhow::Result<(PKey<Private>, Vec<X509>)> {
+        let parsed = Pkcs12::from_der(input)?.parse2(password)?;
+        let private_key = match parsed.pkey {
+            Some(val) => val,
+            None => bail!("A private key is not found in the PKCS#12 bundle."),
+        };
+
+        let mut certs = Vec::new();
+        if let Some(val) = parsed.cert {
+            certs.push(val);
+        }
+        if let Some(val) = parsed.ca {
+            certs.extend(val);
+        }
+
+        Ok((private_key, certs))
+    })()
+    .map_err(JoseError::InvalidKeyFormat)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_parse_pem_all() -> anyhow::Result<()> {
+        let mut pb = std::path::PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        pb.push("data/pem/RSA_2048bit_private_with_certificate.pem");
+        let input = std::fs::read(&pb)?;
+
+        let blocks = parse_pem_all(&input)?;
+        assert_eq!(blocks.len(), 2);
+        assert_eq!(blocks[0].0, "PRIVATE KEY");
+        assert_eq!(blocks[1].0, "CERTIFICATE");
+
+        let private_key = PKey::private_key_from_der(&blocks[0].1)?;
+        let cert = X509::from_der(&blocks[1].1)?;
+        assert!(cert.public_key()?.public_eq(&private_key));
+
+        assert!(parse_pem(&input).is_err());
+        assert!(parse_pem_all(b"not a pem").is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_ct_eq_str() {
+        let a = "NzbLsXh8uDCcd-6MNwXF4W_7noWXFZAfHkxZsRGC9Xs";
+        let b = "NzbLsXh8uDCcd-6MNwXF4W_7noWXFZAfHkxZsRGC9Xt";
+        assert!(ct_eq_str(a, a));
+        assert!(!ct_eq_str(a, b));
+        assert!(!ct_eq_str(a, &a[1..]));
+        assert!(ct_eq_str("", ""));
+    }
+
+    #[test]
+    fn test_datalen_prefixed() {
+        assert_eq!(datalen_prefixed(b""), vec![0, 0, 0, 0]);
+        assert_eq!(datalen_prefixed(b"Bob"), vec![0, 0, 0, 3, b'B', b'o', b'b']);
+    }
+
+    #[test]
+    fn test_concat_kdf() -> anyhow::Result<()> {
+        // RFC 7518 Appendix C
+        let z = [
+            158, 86, 217, 29, 129, 113, 53, 211, 114, 131, 66, 131, 191, 132, 38, 156, 251, 49,
+            110, 163, 218, 128, 106, 72, 246, 218, 167, 121, 140, 254, 144, 196,
+        ];
+        let derived_key = concat_kdf(
+            &z,
+            16,
+            b"A128GCM",
+            b"Alice",
+            b"Bob",
+            MessageDigest::sha256(),
+        )?;
+
+        assert_eq!(
+            derived_key,
+            vec![86, 170, 141, 234, 248, 35, 109, 32, 92, 34, 40, 205, 113, 167, 16, 26]
+        );
+        assert_eq!(
+            encode_base64_urlsafe_nopad(&derived_key),
+            "VqqN6vgjbSBcIijNcacQGg"
+        );
+
+        Ok(())
+    }
+
     #[test]
     fn test_is_base64_standard() {
         assert_eq!(
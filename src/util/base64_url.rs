@@ -0,0 +1,110 @@
+use std::io::{self, Read, Write};
+
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::engine::GeneralPurpose;
+use base64::read::DecoderReader;
+use base64::write::EncoderWriter;
+
+/// A reader that decodes base64url (no padding) data from the inner reader incrementally.
+pub struct Base64UrlReader<R: Read> {
+    inner: DecoderReader<'static, GeneralPurpose, R>,
+}
+
+impl<R: Read> Base64UrlReader<R> {
+    /// Return a new Base64UrlReader.
+    ///
+    /// # Arguments
+    ///
+    /// * `input` - a reader of base64url encoded data
+    pub fn new(input: R) -> Self {
+        Self {
+            inner: DecoderReader::new(input, &URL_SAFE_NO_PAD),
+        }
+    }
+
+    /// Unwrap this reader and return the inner reader.
+    pub fn into_inner(self) -> R {
+        self.inner.into_inner()
+    }
+}
+
+impl<R: Read> Read for Base64UrlReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.inner.read(buf)
+    }
+}
+
+/// A writer that encodes data as base64url (no padding) to the inner writer incrementally.
+///
+/// The last partial chunk is written by `finish`, or when the writer is dropped.
+pub struct Base64UrlWriter<W: Write> {
+    inner: EncoderWriter<'static, GeneralPurpose, W>,
+}
+
+impl<W: Write> Base64UrlWriter<W> {
+    /// Return a new Base64UrlWriter.
+    ///
+    /// # Arguments
+    ///
+    /// * `output` - a writer of base64url encoded data
+    pub fn new(output: W) -> Self {
+        Self {
+            inner: EncoderWriter::new(output, &URL_SAFE_NO_PAD),
+        }
+    }
+
+    /// Write the remaining encoded data and return the inner writer.
+    pub fn finish(&mut self) -> io::Result<W> {
+        self.inner.finish()
+    }
+}
+
+impl<W: Write> Write for Base64UrlWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.inner.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+    use std::io::{Read, Write};
+
+    use super::{Base64UrlReader, Base64UrlWriter};
+    use crate::util;
+
+    #[test]
+    fn stream_large_base64url() -> Result<()> {
+        let data = util::random_bytes(3 * 1024 * 1024 + 1);
+        let encoded = util::encode_base64_urlsafe_nopad(&data);
+
+        let mut writer = Base64UrlWriter::new(Vec::new());
+        for chunk in data.chunks(1000) {
+            writer.write_all(chunk)?;
+        }
+        let streamed = writer.finish()?;
+        assert_eq!(streamed, encoded.as_bytes());
+
+        let mut reader = Base64UrlReader::new(encoded.as_bytes());
+        let mut decoded = Vec::new();
+        let mut buf = [0; 999];
+        loop {
+            let n = reader.read(&mut buf)?;
+            if n == 0 {
+                break;
+            }
+            decoded.extend_from_slice(&buf[..n]);
+        }
+        assert_eq!(decoded, util::decode_base64_urlsafe_no_pad(&encoded)?);
+        assert_eq!(decoded, data);
+
+        let mut reader = Base64UrlReader::new(&b"AB+C"[..]);
+        assert!(reader.read_to_end(&mut Vec::new()).is_err());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,64 @@
+use base64::DecodeError;
+use thiserror::Error;
+
+/// An error for a segment of the compact serialization form.
+///
+/// The index is 1-origin counted from the left segment.
+#[derive(Error, Debug)]
+pub(crate) enum CompactError {
+    #[error("The segment {index} ({field}) is not valid base64url: {source}")]
+    InvalidBase64 {
+        index: usize,
+        field: &'static str,
+        #[source]
+        source: DecodeError,
+    },
+
+    #[error("The segment {index} ({field}) is not valid JSON object: {source}")]
+    InvalidJson {
+        index: usize,
+        field: &'static str,
+        #[source]
+        source: serde_json::Error,
+    },
+}
+
+impl CompactError {
+    /// Decode a base64url segment of the compact serialization form.
+    ///
+    /// # Arguments
+    ///
+    /// * `input` - A base64url encoded segment
+    /// * `index` - A 1-origin index of the segment
+    /// * `field` - A name of the segment
+    pub fn decode_segment(
+        input: impl AsRef<[u8]>,
+        index: usize,
+        field: &'static str,
+    ) -> Result<Vec<u8>, CompactError> {
+        super::decode_base64_urlsafe_no_pad(input).map_err(|source| CompactError::InvalidBase64 {
+            index,
+            field,
+            source,
+        })
+    }
+
+    /// Parse a JSON object segment of the compact serialization form.
+    ///
+    /// # Arguments
+    ///
+    /// * `input` - A decoded segment
+    /// * `index` - A 1-origin index of the segment
+    /// * `field` - A name of the segment
+    pub fn parse_segment<T: serde::de::DeserializeOwned>(
+        input: &[u8],
+        index: usize,
+        field: &'static str,
+    ) -> Result<T, CompactError> {
+        serde_json::from_slice(input).map_err(|source| CompactError::InvalidJson {
+            index,
+            field,
+            source,
+        })
+    }
+}
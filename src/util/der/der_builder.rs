@@ -51,14 +51,24 @@ impl DerBuilder {
         self.append(DerType::Integer, None, &vec);
     }
 
+    /// Append a INTEGER from a big-endian byte slice.
+    ///
+    /// When `sign` is false, the value is an unsigned magnitude: leading zero bytes are trimmed
+    /// and a zero byte is prefixed if the top bit is set, so that it is encoded as positive.
+    /// When `sign` is true, the value is already a two's complement representation.
     pub fn append_integer_from_be_slice(&mut self, value: &[u8], sign: bool) {
-        let prefix = if sign && value.len() > 0 && value[0] & 0b10000000 != 0 {
-            Some(0)
-        } else {
-            None
-        };
+        if sign {
+            self.append(DerType::Integer, None, value);
+            return;
+        }
 
-        self.append(DerType::Integer, prefix, value);
+        let zeros = value.iter().take_while(|b| **b == 0).count();
+        let value = &value[zeros..];
+        match value.first() {
+            None => self.append(DerType::Integer, None, &[0]),
+            Some(val) if val & 0b10000000 != 0 => self.append(DerType::Integer, Some(0), value),
+            Some(_) => self.append(DerType::Integer, None, value),
+        }
     }
 
     pub fn append_null(&mut self) {
@@ -196,6 +206,34 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn write_integer_from_be_slice() -> Result<()> {
+        // A modulus whose top bit is set needs a zero prefix to stay positive.
+        let mut builder = DerBuilder::new();
+        builder.append_integer_from_be_slice(&[0x80, 0x01], false);
+        assert_eq!(builder.build(), vec![2, 3, 0x00, 0x80, 0x01]);
+
+        // Redundant leading zero bytes must be trimmed.
+        let mut builder = DerBuilder::new();
+        builder.append_integer_from_be_slice(&[0x00, 0x00, 0x7F, 0x01], false);
+        assert_eq!(builder.build(), vec![2, 2, 0x7F, 0x01]);
+
+        let mut builder = DerBuilder::new();
+        builder.append_integer_from_be_slice(&[0x00, 0x00, 0xFF], false);
+        assert_eq!(builder.build(), vec![2, 2, 0x00, 0xFF]);
+
+        let mut builder = DerBuilder::new();
+        builder.append_integer_from_be_slice(&[0x00, 0x00], false);
+        assert_eq!(builder.build(), vec![2, 1, 0x00]);
+
+        // A two's complement value is written as it is.
+        let mut builder = DerBuilder::new();
+        builder.append_integer_from_be_slice(&[0xFF, 0x01], true);
+        assert_eq!(builder.build(), vec![2, 2, 0xFF, 0x01]);
+
+        Ok(())
+    }
+
     #[test]
     fn write_sequence_of_integer() -> Result<()> {
         let mut builder = DerBuilder::new();
@@ -1,11 +1,20 @@
 use std::fmt::Display;
 
+use anyhow::bail;
+use openssl::hash::MessageDigest;
+
+use crate::util::oid::{
+    ObjectIdentifier, OID_SHA1, OID_SHA256, OID_SHA384, OID_SHA512, OID_SHA512_256,
+};
+
 #[derive(Debug, Clone, Copy, Eq, PartialEq)]
+#[non_exhaustive]
 pub enum HashAlgorithm {
     Sha1,
     Sha256,
     Sha384,
     Sha512,
+    Sha512_256,
 }
 
 impl HashAlgorithm {
@@ -15,6 +24,7 @@ impl HashAlgorithm {
             Self::Sha256 => "SHA-256",
             Self::Sha384 => "SHA-384",
             Self::Sha512 => "SHA-512",
+            Self::Sha512_256 => "SHA-512/256",
         }
     }
 
@@ -24,6 +34,31 @@ impl HashAlgorithm {
             Self::Sha256 => 32,
             Self::Sha384 => 48,
             Self::Sha512 => 64,
+            Self::Sha512_256 => 32,
+        }
+    }
+
+    pub(crate) fn message_digest(&self) -> anyhow::Result<MessageDigest> {
+        let md = match self {
+            Self::Sha1 => MessageDigest::sha1(),
+            Self::Sha256 => MessageDigest::sha256(),
+            Self::Sha384 => MessageDigest::sha384(),
+            Self::Sha512 => MessageDigest::sha512(),
+            Self::Sha512_256 => match MessageDigest::from_name("SHA512-256") {
+                Some(val) => val,
+                None => bail!("The hash algorithm is not supported by OpenSSL: {}", self),
+            },
+        };
+        Ok(md)
+    }
+
+    pub fn oid(&self) -> &'static ObjectIdentifier {
+        match self {
+            Self::Sha1 => &OID_SHA1,
+            Self::Sha256 => &OID_SHA256,
+            Self::Sha384 => &OID_SHA384,
+            Self::Sha512 => &OID_SHA512,
+            Self::Sha512_256 => &OID_SHA512_256,
         }
     }
 }
@@ -33,3 +68,26 @@ impl Display for HashAlgorithm {
         fmt.write_str(self.name())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::HashAlgorithm;
+
+    #[test]
+    fn test_output_len() {
+        for hash in [
+            HashAlgorithm::Sha1,
+            HashAlgorithm::Sha256,
+            HashAlgorithm::Sha384,
+            HashAlgorithm::Sha512,
+            HashAlgorithm::Sha512_256,
+        ] {
+            assert_eq!(hash.message_digest().unwrap().size(), hash.output_len());
+        }
+
+        assert_eq!(HashAlgorithm::Sha256.output_len(), 32);
+        assert_eq!(HashAlgorithm::Sha384.output_len(), 48);
+        assert_eq!(HashAlgorithm::Sha512.output_len(), 64);
+        assert_eq!(HashAlgorithm::Sha512_256.output_len(), 32);
+    }
+}
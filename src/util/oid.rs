@@ -18,6 +18,9 @@ pub static OID_SHA384: LazyLock<ObjectIdentifier> =
 pub static OID_SHA512: LazyLock<ObjectIdentifier> =
     LazyLock::new(|| ObjectIdentifier::from_slice(&[2, 16, 840, 1, 101, 3, 4, 2, 3]));
 
+pub static OID_SHA512_256: LazyLock<ObjectIdentifier> =
+    LazyLock::new(|| ObjectIdentifier::from_slice(&[2, 16, 840, 1, 101, 3, 4, 2, 6]));
+
 pub static OID_MGF1: LazyLock<ObjectIdentifier> =
     LazyLock::new(|| ObjectIdentifier::from_slice(&[1, 2, 840, 113549, 1, 1, 8]));
 
@@ -0,0 +1,38 @@
+use std::fmt::Debug;
+
+use openssl::rand;
+
+use crate::JoseError;
+
+/// Represent a source of random bytes for content encryption keys and initialization vectors.
+pub trait RandomSource: Debug + Send + Sync {
+    /// Fill the buffer with random bytes.
+    ///
+    /// # Arguments
+    ///
+    /// * `buf` - a buffer to be filled
+    fn fill(&self, buf: &mut [u8]) -> Result<(), JoseError>;
+
+    /// Return a boxed copy of this random source.
+    fn box_clone(&self) -> Box<dyn RandomSource>;
+}
+
+impl Clone for Box<dyn RandomSource> {
+    fn clone(&self) -> Self {
+        self.box_clone()
+    }
+}
+
+/// A random source using the cryptographically secure generator of OpenSSL.
+#[derive(Debug, Eq, PartialEq, Copy, Clone, Default)]
+pub struct OpensslRandomSource;
+
+impl RandomSource for OpensslRandomSource {
+    fn fill(&self, buf: &mut [u8]) -> Result<(), JoseError> {
+        rand::rand_bytes(buf).map_err(|err| JoseError::RandomGenerationFailed(err.into()))
+    }
+
+    fn box_clone(&self) -> Box<dyn RandomSource> {
+        Box::new(*self)
+    }
+}
@@ -0,0 +1,53 @@
+use anyhow::bail;
+use openssl::pkey::{HasPublic, PKeyRef};
+
+/// The minimum bit length of RSA keys for JWS and JWE.
+pub(crate) const MIN_BITS: u32 = 2048;
+
+/// Check that the RSA key has the minimum bit length or more.
+///
+/// # Arguments
+///
+/// * `pkey` - A RSA key
+/// * `min` - A minimum bit length
+pub(crate) fn check_min_bits<T: HasPublic>(pkey: &PKeyRef<T>, min: u32) -> anyhow::Result<()> {
+    if pkey.bits() < min {
+        bail!("key length must be {} or more.", min);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+    use openssl::pkey::PKey;
+    use openssl::rsa::Rsa;
+
+    use super::{check_min_bits, MIN_BITS};
+    use crate::jwe::RSA_OAEP;
+    use crate::jws::RS256;
+    use crate::JoseError;
+
+    #[test]
+    fn reject_rsa_1024bit_key() -> Result<()> {
+        let pkey = PKey::from_rsa(Rsa::generate(1024)?)?;
+        assert!(check_min_bits(&pkey, MIN_BITS).is_err());
+        check_min_bits(&pkey, 1024)?;
+
+        match RSA_OAEP.encrypter_from_der(pkey.public_key_to_der()?) {
+            Err(JoseError::InvalidKeyFormat(err)) => {
+                assert_eq!(err.to_string(), "key length must be 2048 or more.")
+            }
+            other => panic!("A 1024-bit key was accepted: {:?}", other),
+        }
+
+        match RS256.signer_from_der(pkey.private_key_to_der()?) {
+            Err(JoseError::InvalidKeyFormat(err)) => {
+                assert_eq!(err.to_string(), "key length must be 2048 or more.")
+            }
+            other => panic!("A 1024-bit key was accepted: {:?}", other),
+        }
+
+        Ok(())
+    }
+}